@@ -46,6 +46,6 @@ fn main() {
     })
     .expect("Rendering to context failed");
 
-    img.write_to_file(c"bl-getting-started-5.bmp")
+    img.write_to_file("bl-getting-started-5.bmp")
         .expect("Writing to file failed");
 }
@@ -23,6 +23,6 @@ fn main() {
         ctx.end()
     })
     .expect("Rendering to context failed");
-    img.write_to_file(c"bl-getting-started-1.bmp")
+    img.write_to_file("bl-getting-started-1.bmp")
         .expect("Writing to file failed");
 }
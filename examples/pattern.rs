@@ -7,7 +7,7 @@ fn main() {
         ctx.fill_all()?;
 
         // Read an image from file.
-        let texture = Image::read_from_file(c"assets/ferris.png")?;
+        let texture = Image::read_from_file("assets/ferris.png")?;
 
         // Create a pattern and use it to fill a rounded-rect.
         let pattern = Pattern::new(&texture, None, Default::default(), None)?;
@@ -22,6 +22,6 @@ fn main() {
     })
     .expect("Rendering to context failed");
 
-    img.write_to_file(c"bl-getting-started-3.bmp")
+    img.write_to_file("bl-getting-started-3.bmp")
         .expect("Writing to file failed");
 }
@@ -35,7 +35,7 @@ fn main() {
 
         // Multiply a circle with our logo scaled to the radius of the circle on top of
         // your image.
-        let mut logo = Image::read_from_file(c"assets/rust-logo-512x512-blk.png")?;
+        let mut logo = Image::read_from_file("assets/rust-logo-512x512-blk.png")?;
         logo.scale(
             SizeI {
                 w: 2 * c_r as i32,
@@ -73,6 +73,6 @@ fn main() {
     })
     .expect("Rendering to context failed");
 
-    img.write_to_file(c"rust_bl_logo.bmp")
+    img.write_to_file("rust_bl_logo.bmp")
         .expect("Writing to file failed");
 }
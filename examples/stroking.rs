@@ -36,6 +36,6 @@ fn main() {
     })
     .expect("Rendering to context failed");
 
-    img.write_to_file(c"bl-getting-started-6.bmp")
+    img.write_to_file("bl-getting-started-6.bmp")
         .expect("Writing to file failed");
 }
@@ -1,119 +1,130 @@
-//! A contiguous growable array for use with the blend2d api.
+//! A contiguous growable array managed by Blend2D.
 use std::borrow::Borrow;
-use std::iter::FromIterator;
 use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::os::raw::c_void;
 use std::{fmt, io, ops, ptr, slice};
 
 use crate::codec::ImageCodec;
-use crate::error::{errcode_to_result, expect_mem_err, OutOfMemory, Result};
-use crate::util::range_to_tuple;
-use crate::variant::WrappedBlCore;
-
-/// A contiguous growable array for use with the blend2d api.
-/// This is an array managed by blend2d, unless required one should use [`Vec`]
-/// instead. Its api tries to mimic [`Vec`] as close as possible.
+use crate::color::{Rgba, Rgba32, Rgba64};
+use crate::geometry::{Box, BoxI, Circle, Ellipse, Line, Point, PointI, Rect, RectI, Size, SizeI};
+use crate::gradient::Gradient;
+use crate::image::Image;
+use crate::path::Path;
+use crate::pattern::Pattern;
+use crate::string::String;
+use crate::util::bl_range;
+use crate::{Error, err_to_result};
+
+/// A contiguous growable array managed by Blend2D.
 ///
-/// [`String`]: std/vec/struct.Vec.html
+/// Blend2D returns its containers as arrays, for example the data written by
+/// an [`ImageEncoder`](crate::codec::ImageEncoder). The array derefs to a
+/// slice and copies are reference counted, so nothing is copied on the way
+/// out. Unless an array is required, prefer [`Vec`].
 #[repr(transparent)]
-pub struct Array<T: ArrayType> {
-    core: ffi::BLArrayCore,
-    _pd: PhantomData<T>,
-}
-
-unsafe impl<T: ArrayType> WrappedBlCore for Array<T> {
-    type Core = ffi::BLArrayCore;
-    const IMPL_TYPE_INDEX: usize = T::IMPL_IDX;
-
-    #[inline]
-    fn from_core(core: Self::Core) -> Self {
-        Array {
-            core,
-            _pd: PhantomData,
-        }
-    }
-}
+pub struct Array<T: ArrayType>(pub(crate) ffi::BLArrayCore, PhantomData<T>);
 
 impl<T: ArrayType> Array<T> {
     /// Creates a new empty array.
+    #[inline]
     pub fn new() -> Self {
-        Self::from_core(*Self::none())
-    }
-
-    /// Creates a new empty array with space for `cap` elements.
-    pub fn with_capacity(cap: usize) -> Self {
-        let mut this = Array::from_core(*Self::none());
-        this.reserve(cap);
-        this
+        let mut core = MaybeUninit::<ffi::BLArrayCore>::uninit();
+        unsafe {
+            ffi::bl_array_init(core.as_mut_ptr(), T::OBJECT_TYPE);
+            Array(core.assume_init(), PhantomData)
+        }
     }
 
-    /// Clears the array and its contents.
+    /// Creates a new empty array with space for at least `capacity` items.
     #[inline]
-    pub fn clear(&mut self) {
-        unsafe { ffi::blArrayClear(self.core_mut()) };
+    pub fn with_capacity(capacity: usize) -> Result<Self, Error> {
+        let mut array = Self::new();
+        array.reserve(capacity)?;
+        Ok(array)
     }
 
-    /// Shrinks the arrays allocated capacity down to its currently used size.
+    /// Clears the array, keeping its capacity.
     #[inline]
-    pub fn shrink_to_fit(&mut self) {
-        unsafe { expect_mem_err(ffi::blArrayShrink(self.core_mut())) };
+    pub fn clear(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_array_clear(&mut self.0) })
     }
 
-    /// Reserves capacity for at least n items.
-    ///
-    /// # Panics
-    ///
-    /// Panics if blend2d returns an
-    /// [`OutOfMemory`](../error/enum.Error.html#variant.OutOfMemory) error
+    /// Shrinks the capacity of the array to fit its length.
     #[inline]
-    pub fn reserve(&mut self, n: usize) {
-        self.try_reserve(n).expect("memory allocation failed");
+    pub fn shrink(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_array_shrink(&mut self.0) })
     }
 
-    /// Reserves capacity for at least n items.
+    /// Reserves capacity for at least `n` items in total.
     #[inline]
-    pub fn try_reserve(&mut self, n: usize) -> std::result::Result<(), OutOfMemory> {
-        unsafe { OutOfMemory::from_errcode(ffi::blArrayReserve(self.core_mut(), n)) }
+    pub fn reserve(&mut self, n: usize) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_array_reserve(&mut self.0, n) })
     }
 
-    /// Truncates the array down to n elements.
+    /// Shortens the array to `n` items, does nothing if it is shorter
+    /// already.
     #[inline]
-    pub fn truncate(&mut self, n: usize) {
-        unsafe {
-            expect_mem_err(ffi::blArrayResize(
-                self.core_mut(),
-                n.min(self.len()),
-                ptr::null(),
-            ))
-        };
+    pub fn truncate(&mut self, n: usize) -> Result<(), Error> {
+        let n = n.min(self.len());
+        err_to_result(unsafe { ffi::bl_array_resize(&mut self.0, n, ptr::null()) })
     }
 
-    /// Resizes the array so that its len is equal to `n`, filling any new items
-    /// with `fill`.
-    pub fn resize(&mut self, n: usize, fill: T)
+    /// Resizes the array to `n` items, filling new items with clones of
+    /// `fill`.
+    pub fn resize(&mut self, n: usize, fill: T) -> Result<(), Error>
     where
         T: Clone,
     {
-        unsafe {
-            let diff = n.checked_sub(self.len()).unwrap_or_default();
-            let buff = vec![fill; diff];
-            expect_mem_err(ffi::blArrayResize(
-                self.core_mut(),
-                n,
-                buff.as_ptr() as *const _,
-            ))
-        };
+        let len = self.len();
+        if n <= len {
+            return self.truncate(n);
+        }
+        self.reserve(n)?;
+        for _ in len..n {
+            self.push(fill.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Appends `item` to the end of the array.
+    #[inline]
+    pub fn push(&mut self, item: T) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_array_append_item(&mut self.0, item_ptr(&item)) })
+    }
+
+    /// Inserts `item` at `index`, shifting all items after it.
+    #[inline]
+    pub fn insert(&mut self, index: usize, item: T) -> Result<(), Error> {
+        if index > self.len() {
+            return Err(Error);
+        }
+        err_to_result(unsafe { ffi::bl_array_insert_item(&mut self.0, index, item_ptr(&item)) })
+    }
+
+    /// Replaces the item at `index` with `item`.
+    #[inline]
+    pub fn replace(&mut self, index: usize, item: T) -> Result<(), Error> {
+        if index >= self.len() {
+            return Err(Error);
+        }
+        err_to_result(unsafe { ffi::bl_array_replace_item(&mut self.0, index, item_ptr(&item)) })
     }
 
-    /// Removes the element at the given index.
+    /// Removes the item at `index`, shifting all items after it.
     #[inline]
-    pub fn remove(&mut self, index: usize) -> Result<()> {
-        unsafe { errcode_to_result(ffi::blArrayRemoveIndex(self.core_mut(), index)) }
+    pub fn remove(&mut self, index: usize) -> Result<(), Error> {
+        if index >= self.len() {
+            return Err(Error);
+        }
+        err_to_result(unsafe { ffi::bl_array_remove_index(&mut self.0, index) })
     }
 
-    /// Removes the elements whose indices reside inside of the range
-    pub fn remove_range<R: ops::RangeBounds<usize>>(&mut self, range: R) -> Result<()> {
-        let (start, end) = range_to_tuple(range, || self.len());
-        unsafe { errcode_to_result(ffi::blArrayRemoveRange(self.core_mut(), start, end)) }
+    /// Removes the items in `range`.
+    #[inline]
+    pub fn remove_range<R: ops::RangeBounds<usize>>(&mut self, range: R) -> Result<(), Error> {
+        let (start, end) = self.checked_range(range)?;
+        err_to_result(unsafe { ffi::bl_array_remove_range(&mut self.0, start, end) })
     }
 
     /// Returns the array as a slice.
@@ -122,127 +133,163 @@ impl<T: ArrayType> Array<T> {
         self
     }
 
-    /// Returns the length of the array.
+    /// The number of items in the array.
     #[inline]
     pub fn len(&self) -> usize {
-        unsafe { ffi::blArrayGetSize(self.core()) }
+        unsafe { ffi::bl_array_get_size(&self.0) }
     }
 
-    /// Returns true if this array has no elements.
+    /// Returns true if the array has no items.
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
-    /// Returns the current capacity of the array.
+    /// The number of items the array can hold without reallocating.
     #[inline]
     pub fn capacity(&self) -> usize {
-        self.impl_().capacity as usize
+        unsafe { ffi::bl_array_get_capacity(&self.0) }
     }
 
-    #[inline]
-    fn data_ptr(&self) -> *const T {
-        unsafe { ffi::blArrayGetData(self.core()) as *const _ }
+    fn checked_range<R: ops::RangeBounds<usize>>(&self, range: R) -> Result<(usize, usize), Error> {
+        let range = bl_range(range);
+        let end = range.end.min(self.len());
+        if range.start > end {
+            return Err(Error);
+        }
+        Ok((range.start, end))
     }
 }
 
-impl<T> Array<T>
-where
-    T: ArrayType + Clone,
-{
-    /// Appends all items in the slice to the array.
-    pub fn extend_from_slice<S: AsRef<[T]>>(&mut self, data: S) {
-        unsafe {
-            let data = data.as_ref();
-            expect_mem_err(ffi::blArrayAppendView(
-                self.core_mut(),
-                data.as_ptr() as *const _,
-                data.len(),
-            ))
-        };
+impl<T: ArrayType + Clone> Array<T> {
+    /// Appends clones of all items of `data`.
+    #[inline]
+    pub fn extend_from_slice(&mut self, data: &[T]) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_array_append_data(&mut self.0, data.as_ptr() as *const c_void, data.len())
+        })
     }
 
-    /// Inserts all items in the slice into the array at the given index.
-    pub fn insert_from_slice<S: AsRef<[T]>>(&mut self, index: usize, data: S) {
-        unsafe {
-            let data = data.as_ref();
-            expect_mem_err(ffi::blArrayInsertView(
-                self.core_mut(),
+    /// Inserts clones of all items of `data` at `index`.
+    #[inline]
+    pub fn insert_from_slice(&mut self, index: usize, data: &[T]) -> Result<(), Error> {
+        if index > self.len() {
+            return Err(Error);
+        }
+        err_to_result(unsafe {
+            ffi::bl_array_insert_data(
+                &mut self.0,
                 index,
-                data.as_ptr() as *const _,
+                data.as_ptr() as *const c_void,
                 data.len(),
-            ))
-        };
+            )
+        })
     }
 
-    /// Replaces the elements specified by the range of indices with the given
-    /// slice.
-    pub fn replace_from_slice<R, S>(&mut self, range: R, data: S)
+    /// Replaces the items in `range` with clones of all items of `data`.
+    #[inline]
+    pub fn replace_from_slice<R>(&mut self, range: R, data: &[T]) -> Result<(), Error>
     where
         R: ops::RangeBounds<usize>,
-        S: AsRef<[T]>,
     {
-        let (start, end) = range_to_tuple(range, || self.len());
-        let data = data.as_ref();
-        unsafe {
-            expect_mem_err(ffi::blArrayReplaceView(
-                self.core_mut(),
+        let (start, end) = self.checked_range(range)?;
+        err_to_result(unsafe {
+            ffi::bl_array_replace_data(
+                &mut self.0,
                 start,
                 end,
-                data.as_ptr() as *const _,
+                data.as_ptr() as *const c_void,
                 data.len(),
-            ))
-        };
+            )
+        })
     }
 }
 
+impl Array<ImageCodec> {
+    /// Finds the codec called `name` in the array.
+    pub fn find_codec_by_name(&self, name: &str) -> Option<&ImageCodec> {
+        self.iter().find(|codec| codec.name() == name)
+    }
+
+    /// Finds the codec in the array most likely to decode `data`, `None` if no
+    /// codec recognizes it.
+    pub fn find_codec_by_data<R: AsRef<[u8]>>(&self, data: R) -> Option<&ImageCodec> {
+        let data = data.as_ref();
+        self.iter()
+            .map(|codec| (codec.inspect_data(data), codec))
+            .filter(|&(score, _)| score > 0)
+            .max_by_key(|&(score, _)| score)
+            .map(|(_, codec)| codec)
+    }
+}
+
+#[inline]
+fn item_ptr<T>(item: &T) -> *const c_void {
+    item as *const T as *const c_void
+}
+
 impl<T: ArrayType> Extend<T> for Array<T> {
-    fn extend<I>(&mut self, iter: I)
-    where
-        I: IntoIterator<Item = T>,
-    {
+    /// # Panics
+    ///
+    /// Panics if Blend2D fails to allocate memory.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let additional = iter.size_hint().0;
+        self.reserve(self.len().saturating_add(additional))
+            .expect("memory allocation failed");
         for item in iter {
-            self.push(item);
+            self.push(item).expect("memory allocation failed");
         }
     }
 }
 
 impl<T: ArrayType> FromIterator<T> for Array<T> {
-    fn from_iter<I>(iter: I) -> Array<T>
-    where
-        I: IntoIterator<Item = T>,
-    {
-        let iter = iter.into_iter();
-        let len = iter.size_hint().1.unwrap_or(iter.size_hint().0);
-        let mut this = Self::with_capacity(len);
-        this.extend(iter);
-        this
+    /// # Panics
+    ///
+    /// Panics if Blend2D fails to allocate memory.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut array = Self::new();
+        array.extend(iter);
+        array
+    }
+}
+
+impl<T: ArrayType + Clone> From<&[T]> for Array<T> {
+    /// # Panics
+    ///
+    /// Panics if Blend2D fails to allocate memory.
+    fn from(data: &[T]) -> Self {
+        let mut array = Self::new();
+        array
+            .extend_from_slice(data)
+            .expect("memory allocation failed");
+        array
     }
 }
 
 impl<T: ArrayType + Clone> From<Vec<T>> for Array<T> {
-    fn from(v: Vec<T>) -> Self {
-        let mut this = Self::with_capacity(v.len());
-        this.extend_from_slice(&v);
-        this
+    /// # Panics
+    ///
+    /// Panics if Blend2D fails to allocate memory.
+    #[inline]
+    fn from(data: Vec<T>) -> Self {
+        Self::from(&data[..])
     }
 }
 
-impl<'a, T> From<&'a [T]> for Array<T>
-where
-    T: ArrayType + Clone,
-{
-    fn from(v: &[T]) -> Self {
-        let mut this = Self::with_capacity(v.len());
-        this.extend_from_slice(v);
-        this
+impl<T: ArrayType + Clone> From<&Array<T>> for Vec<T> {
+    #[inline]
+    fn from(array: &Array<T>) -> Self {
+        array.to_vec()
     }
 }
 
 impl io::Write for Array<u8> {
+    #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.extend_from_slice(buf);
-        Ok(buf.len())
+        self.extend_from_slice(buf)
+            .map(|_| buf.len())
+            .map_err(|_| io::ErrorKind::OutOfMemory.into())
     }
 
     #[inline]
@@ -251,6 +298,38 @@ impl io::Write for Array<u8> {
     }
 }
 
+impl<T: ArrayType> ops::Deref for Array<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe {
+            let data = ffi::bl_array_get_data(&self.0) as *const T;
+            match data.is_null() {
+                true => &[],
+                false => slice::from_raw_parts(data, self.len()),
+            }
+        }
+    }
+}
+
+impl<T: ArrayType> ops::DerefMut for Array<T> {
+    /// # Panics
+    ///
+    /// Panics if the array is shared and Blend2D fails to allocate memory
+    /// for a copy.
+    fn deref_mut(&mut self) -> &mut [T] {
+        let mut data = ptr::null_mut();
+        unsafe {
+            err_to_result(ffi::bl_array_make_mutable(&mut self.0, &mut data))
+                .expect("memory allocation failed");
+            match data.is_null() {
+                true => &mut [],
+                false => slice::from_raw_parts_mut(data as *mut T, self.len()),
+            }
+        }
+    }
+}
+
 impl<T: ArrayType> AsRef<[T]> for Array<T> {
     #[inline]
     fn as_ref(&self) -> &[T] {
@@ -272,41 +351,10 @@ impl<T: ArrayType> Borrow<[T]> for Array<T> {
     }
 }
 
-impl<T: ArrayType> ops::Deref for Array<T> {
-    type Target = [T];
-
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        unsafe { slice::from_raw_parts(self.data_ptr(), self.len()) }
-    }
-}
-
-impl<T: ArrayType> ops::DerefMut for Array<T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe {
-            let mut data_ptr = ptr::null_mut();
-            expect_mem_err(ffi::blArrayMakeMutable(self.core_mut(), &mut data_ptr));
-            slice::from_raw_parts_mut(data_ptr as _, self.len())
-        }
-    }
-}
-
-impl<T, I> ops::Index<I> for Array<T>
-where
-    T: ArrayType,
-    I: slice::SliceIndex<[T]>,
-{
-    type Output = I::Output;
-
-    #[inline]
-    fn index(&self, index: I) -> &Self::Output {
-        ops::Index::index(&**self, index)
-    }
-}
-
 impl<'a, T: ArrayType> IntoIterator for &'a Array<T> {
     type Item = &'a T;
     type IntoIter = slice::Iter<'a, T>;
+
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -323,283 +371,165 @@ impl<T: ArrayType> Default for Array<T> {
 impl<T: ArrayType> PartialEq for Array<T> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        unsafe { ffi::blArrayEquals(self.core(), other.core()) }
+        unsafe { ffi::bl_array_equals(&self.0, &other.0) }
     }
 }
 
 impl<T: ArrayType> Clone for Array<T> {
+    /// Creates a reference counted copy of the array, its items are copied
+    /// once either copy is modified.
+    #[inline]
     fn clone(&self) -> Self {
-        Self::from_core(self.init_weak())
+        let mut core = MaybeUninit::<ffi::BLArrayCore>::uninit();
+        unsafe {
+            ffi::bl_array_init_weak(core.as_mut_ptr(), &self.0);
+            Array(core.assume_init(), PhantomData)
+        }
     }
 }
 
-impl<T> fmt::Debug for Array<T>
-where
-    T: ArrayType + fmt::Debug,
-{
+impl<T: ArrayType + fmt::Debug> fmt::Debug for Array<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self.as_ref())
+        f.debug_list().entries(self.iter()).finish()
     }
 }
 
 impl<T: ArrayType> Drop for Array<T> {
     #[inline]
     fn drop(&mut self) {
-        unsafe { ffi::blArrayReset(&mut self.core) };
-    }
-}
-
-impl<T> Array<T>
-where
-    T: ArrayType,
-{
-    #[inline]
-    pub fn push(&mut self, item: T) {
-        unsafe { T::push(self.core_mut(), item) };
-    }
-    #[inline]
-    pub fn insert(&mut self, index: usize, item: T) {
-        unsafe { T::insert(self.core_mut(), index, item) };
-    }
-    #[inline]
-    pub fn replace(&mut self, index: usize, item: T) {
-        unsafe { T::replace(self.core_mut(), index, item) };
+        unsafe { ffi::bl_array_destroy(&mut self.0) };
     }
 }
 
-impl Array<ImageCodec> {
-    /// Searches for an image codec in the array by the given name.
-    pub fn find_codec_by_name(&self, name: &str) -> Option<&ImageCodec> {
-        self.iter().find(|c| c.name() == name)
-    }
-
-    /// Searches for an image codec in the array by the given data.
-    pub fn find_codec_by_data<R: AsRef<[u8]>>(&self, data: R) -> Option<&ImageCodec> {
-        self.into_iter()
-            .max_by_key(|codec| codec.inspect_data(data.as_ref()))
-    }
-}
-
-use crate::variant::ImplType;
-
-/// A marker trait for types that can be used for a blend2d [`Array`].
-/// This trait is also implemented for all blend2d core types.
+/// Types that can be stored in an [`Array`].
 ///
-/// [`Array`]: ./struct.Array.html
-pub trait ArrayType: Sized {
-    #[doc(hidden)]
-    const IMPL_IDX: usize;
-    #[doc(hidden)]
-    #[inline]
-    unsafe fn push(core: &mut ffi::BLArrayCore, item: Self) {
-        expect_mem_err(ffi::blArrayAppendItem(core, &item as *const _ as *const _));
-    }
-    #[doc(hidden)]
-    #[inline]
-    unsafe fn insert(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        expect_mem_err(ffi::blArrayInsertItem(
-            core,
-            index,
-            &item as *const _ as *const _,
-        ));
-    }
+/// # Safety
+///
+/// `OBJECT_TYPE` must be a Blend2D array type whose items have the size and
+/// layout of `Self`. Object arrays require `Self` to be a transparent
+/// wrapper around the core of a Blend2D object.
+pub unsafe trait ArrayType: Sized {
     #[doc(hidden)]
-    #[inline]
-    unsafe fn replace(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        expect_mem_err(ffi::blArrayReplaceItem(
-            core,
-            index,
-            &item as *const _ as *const _,
-        ));
-    }
+    const OBJECT_TYPE: ffi::BLObjectType::Type;
 }
 
-#[doc(hidden)]
-impl<T> ArrayType for T
-where
-    T: WrappedBlCore,
-{
-    const IMPL_IDX: usize = ImplType::ArrayVar as usize;
-    #[inline]
-    unsafe fn push(core: &mut ffi::BLArrayCore, item: Self) {
-        expect_mem_err(ffi::blArrayAppendItem(
-            core,
-            item.core() as *const _ as *const _,
-        ));
-    }
-    #[inline]
-    unsafe fn insert(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        expect_mem_err(ffi::blArrayInsertItem(
-            core,
-            index,
-            item.core() as *const _ as *const _,
-        ));
-    }
-    #[inline]
-    unsafe fn replace(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        expect_mem_err(ffi::blArrayReplaceItem(
-            core,
-            index,
-            item.core() as *const _ as *const _,
-        ));
-    }
-}
-
-impl<T> ArrayType for *const T {
-    const IMPL_IDX: usize = usize::IMPL_IDX;
-    #[inline]
-    unsafe fn push(core: &mut ffi::BLArrayCore, item: Self) {
-        usize::push(core, item as usize);
-    }
-    #[inline]
-    unsafe fn insert(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        usize::insert(core, index, item as usize);
-    }
-    #[inline]
-    unsafe fn replace(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        usize::insert(core, index, item as usize);
-    }
-}
-
-impl<T> ArrayType for *mut T {
-    const IMPL_IDX: usize = usize::IMPL_IDX;
-    #[inline]
-    unsafe fn push(core: &mut ffi::BLArrayCore, item: Self) {
-        usize::push(core, item as usize);
-    }
-    #[inline]
-    unsafe fn insert(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        usize::insert(core, index, item as usize);
-    }
-    #[inline]
-    unsafe fn replace(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-        usize::insert(core, index, item as usize);
-    }
-}
-
-// Macro-zone ahead, you have been warned
-
 macro_rules! impl_array_type {
-    ($( $append:ident, $insert:ident, $replace:ident for $( ($ty:ty = $idx:expr) ),+);*$(;)*) => {
-        $(
-            $(
-                impl ArrayType for $ty {
-                    const IMPL_IDX: usize = $idx as usize;
-                    #[inline]
-                    unsafe fn push(core: &mut ffi::BLArrayCore, item: Self) {
-                        expect_mem_err(ffi::$append(core, item as _))
-                    }
-                    #[inline]
-                    unsafe fn insert(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-                        expect_mem_err(ffi::$insert(core, index, item as _))
-                    }
-                    #[inline]
-                    unsafe fn replace(core: &mut ffi::BLArrayCore, index: usize, item: Self) {
-                        expect_mem_err(ffi::$replace(core, index, item as _))
-                    }
-                }
-            )+
-        )*
+    ($($object_type:ident for $($ty:ty),+;)*) => {
+        $($(
+            unsafe impl ArrayType for $ty {
+                const OBJECT_TYPE: ffi::BLObjectType::Type = ffi::BLObjectType::$object_type;
+            }
+        )+)*
     };
-    ($( ( $( $ty:ty ),+ = $idx:expr) );* $(;)*) => {
-        $(
-            $(
-                impl ArrayType for $ty {
-                    const IMPL_IDX: usize = $idx as usize;
-                }
-            )+
-        )*
-    }
 }
 
 impl_array_type! {
-    blArrayAppendU8,  blArrayInsertU8,  blArrayInsertU8  for (i8  = ImplType::ArrayI8),  (u8  = ImplType::ArrayU8);
-    blArrayAppendU16, blArrayInsertU16, blArrayInsertU16 for (i16 = ImplType::ArrayI16), (u16 = ImplType::ArrayU16);
-    blArrayAppendU32, blArrayInsertU32, blArrayInsertU32 for (i32 = ImplType::ArrayI32), (u32 = ImplType::ArrayU32);
-    blArrayAppendU64, blArrayInsertU64, blArrayInsertU64 for (i64 = ImplType::ArrayI64), (u64 = ImplType::ArrayU64);
-    blArrayAppendF32, blArrayInsertF32, blArrayInsertF32 for (f32 = ImplType::ArrayF32);
-    blArrayAppendF64, blArrayInsertF64, blArrayInsertF64 for (f64 = ImplType::ArrayF32);
+    BL_OBJECT_TYPE_ARRAY_INT8 for i8;
+    BL_OBJECT_TYPE_ARRAY_UINT8 for u8;
+    BL_OBJECT_TYPE_ARRAY_INT16 for i16;
+    BL_OBJECT_TYPE_ARRAY_UINT16 for u16;
+    BL_OBJECT_TYPE_ARRAY_INT32 for i32;
+    BL_OBJECT_TYPE_ARRAY_UINT32 for u32;
+    BL_OBJECT_TYPE_ARRAY_INT64 for i64;
+    BL_OBJECT_TYPE_ARRAY_UINT64 for u64;
+    BL_OBJECT_TYPE_ARRAY_FLOAT32 for f32;
+    BL_OBJECT_TYPE_ARRAY_FLOAT64 for f64;
+    BL_OBJECT_TYPE_ARRAY_STRUCT_4 for Rgba32;
+    BL_OBJECT_TYPE_ARRAY_STRUCT_8 for PointI, SizeI, Rgba64;
+    BL_OBJECT_TYPE_ARRAY_STRUCT_16 for Point, Size, BoxI, RectI, Rgba;
+    BL_OBJECT_TYPE_ARRAY_STRUCT_24 for Circle;
+    BL_OBJECT_TYPE_ARRAY_STRUCT_32 for Box, Rect, Ellipse, Line;
+    BL_OBJECT_TYPE_ARRAY_OBJECT for Image, Path, Pattern, Gradient, ImageCodec, String;
 }
 
+// The struct array types above are picked by item size.
+const _: () = {
+    assert!(mem::size_of::<Rgba32>() == 4);
+    assert!(mem::size_of::<PointI>() == 8 && mem::size_of::<SizeI>() == 8);
+    assert!(mem::size_of::<Rgba64>() == 8);
+    assert!(mem::size_of::<Point>() == 16 && mem::size_of::<Size>() == 16);
+    assert!(mem::size_of::<BoxI>() == 16 && mem::size_of::<RectI>() == 16);
+    assert!(mem::size_of::<Rgba>() == 16);
+    assert!(mem::size_of::<Circle>() == 24);
+    assert!(mem::size_of::<Box>() == 32 && mem::size_of::<Rect>() == 32);
+    assert!(mem::size_of::<Ellipse>() == 32 && mem::size_of::<Line>() == 32);
+};
+
 #[cfg(target_pointer_width = "32")]
-impl_array_type!(blArrayAppendU32, blArrayInsertU32, blArrayInsertU32 for (isize = ImplType::ArrayI32), (usize = ImplType::ArrayU32));
-#[cfg(target_pointer_width = "64")]
-impl_array_type!(blArrayAppendU64, blArrayInsertU64, blArrayInsertU64 for (isize = ImplType::ArrayI64), (usize = ImplType::ArrayU64));
+impl_array_type! {
+    BL_OBJECT_TYPE_ARRAY_INT32 for isize;
+    BL_OBJECT_TYPE_ARRAY_UINT32 for usize;
+}
 
-mod scope {
-    use crate::{array::ArrayType, font_defs::*, geometry::*, variant::ImplType, Tag};
-    impl_array_type! {
-        (Tag = ImplType::ArrayStruct4);
-        (PointD, PointI, SizeD, SizeI, FontFeature, FontVariation = ImplType::ArrayStruct8);
-        (Circle = ImplType::ArrayStruct12);
-        (BoxD, BoxI, Ellipse, Line, RectD, RectI = ImplType::ArrayStruct16);
-        (Arc, Chord, Pie, RoundRect, Triangle = ImplType::ArrayStruct24);
-    }
+#[cfg(target_pointer_width = "64")]
+impl_array_type! {
+    BL_OBJECT_TYPE_ARRAY_INT64 for isize;
+    BL_OBJECT_TYPE_ARRAY_UINT64 for usize;
 }
 
 #[cfg(test)]
 mod test_array {
-    use crate::{array::Array, image::Image, path::Path};
+    use crate::array::Array;
+    use crate::string::String;
 
     #[test]
     fn test_array_resize() {
         let mut arr = Array::<i32>::new();
-        arr.resize(10, 32);
+        arr.resize(10, 32).unwrap();
         assert_eq!(&[32; 10][..], &*arr);
+        arr.truncate(4).unwrap();
+        assert_eq!(&[32; 4][..], &*arr);
 
-        let mut path = Path::new();
-        path.move_to(1.0, 2.0);
-        let mut arr = Array::<Path>::new();
-        arr.resize(10, path.clone());
-        assert_eq!(&vec![path; 10][..], &*arr);
+        let s = String::from("item");
+        let mut arr = Array::<String>::new();
+        arr.resize(3, s.clone()).unwrap();
+        assert_eq!(&vec![s; 3][..], &*arr);
     }
 
     #[test]
     fn test_array_ops_prim() {
-        let mut arr = Array::<i32>::new();
-        arr.push(32);
-        arr.push(24);
-        arr.push(16);
-        arr.push(8);
+        let mut arr = Array::<f64>::new();
+        for v in [32.0, 24.0, 16.0, 8.0] {
+            arr.push(v).unwrap();
+        }
         arr.remove(2).unwrap();
-        arr.insert(1, 0);
-        assert_eq!(&[32, 0, 24, 8], &*arr);
+        arr.insert(1, 0.5).unwrap();
+        assert_eq!(&[32.0, 0.5, 24.0, 8.0], &*arr);
+        arr.remove_range(1..3).unwrap();
+        assert_eq!(&[32.0, 8.0], &*arr);
+        assert!(arr.remove(2).is_err());
     }
 
     #[test]
     fn test_array_ops_objects() {
-        let img = [
-            Image::new(1, 1, Default::default()).unwrap(),
-            Image::new(2, 2, Default::default()).unwrap(),
-            Image::new(3, 3, Default::default()).unwrap(),
-            Image::new(4, 4, Default::default()).unwrap(),
-            Image::new(5, 5, Default::default()).unwrap(),
-        ];
-        let mut arr = Array::<Image>::new();
-        for img in img.iter().take(4) {
-            arr.push(img.clone());
+        let items = ["a", "b", "c", "d", "e"].map(String::from);
+        let mut arr = Array::<String>::new();
+        for item in items.iter().take(4) {
+            arr.push(item.clone()).unwrap();
         }
         arr.remove(2).unwrap();
-        arr.insert(1, img[4].clone());
-        assert_eq!(
-            &[
-                img[0].clone(),
-                img[4].clone(),
-                img[1].clone(),
-                img[3].clone()
-            ][..],
-            &*arr
-        );
+        arr.insert(1, items[4].clone()).unwrap();
+        arr.replace(0, String::from("f")).unwrap();
+        let strs = arr.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        assert_eq!(strs, ["f", "e", "b", "d"]);
     }
 
     #[test]
     fn test_array_deref_mut() {
         let data = [0, 1, 2, 3, 4, 5];
-        let mut arr = Array::<i32>::from(&data[..]);
-        assert_eq!(&data, &*arr);
-        for i in 0..data.len() / 2 {
-            arr.swap(i, data.len() - 1 - i);
-        }
+        let mut arr = Array::<u8>::from(&data[..]);
+        let copy = arr.clone();
+        arr.reverse();
         assert_eq!(&[5, 4, 3, 2, 1, 0], &*arr);
+        assert_eq!(&data, &*copy);
+    }
+
+    #[test]
+    fn test_array_write() {
+        use std::io::Write;
+
+        let mut arr = Array::<u8>::new();
+        write!(arr, "{}-{}", 1, 2).unwrap();
+        assert_eq!(b"1-2", &*arr);
     }
 }
@@ -1,150 +1,171 @@
 //! Functionality for decoding and encoding images.
-use std::ffi::CStr;
-use std::{fmt, mem, ptr, str};
+use std::{fmt, ptr, slice, str};
 
-use ffi::BLImageCodecFeatures::*;
+use bitflags::bitflags;
 
 use crate::array::Array;
-use crate::error::{errcode_to_result, expect_mem_err, Result};
 use crate::image::{Image, ImageInfo};
-use crate::util::cast_ref;
-use crate::variant::WrappedBlCore;
+use crate::util::object_impl;
+use crate::{Error, err_to_result};
+
+mod custom;
+pub use self::custom::{ImageCodecImpl, ImageDecoderImpl, ImageEncoderImpl};
+mod frames;
+pub use self::frames::{Frame, FrameBlend, FrameDisposal, Frames};
+mod property;
+pub use self::property::PropertyValue;
 
-bl_enum! {
+use ffi::BLImageCodecFeatures::*;
+bitflags! {
     /// Image codec feature bits.
-    pub enum ImageCodecFeatures {
-        /// Image codec supports reading images (can create BLImageDecoder).
-        Read       = BL_IMAGE_CODEC_FEATURE_READ,
-        /// Image codec supports writing images (can create BLImageEncoder).
-        Write      = BL_IMAGE_CODEC_FEATURE_WRITE,
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct ImageCodecFeatures: u32 {
+        /// Image codec supports reading images (can create an [`ImageDecoder`]).
+        const READ        = BL_IMAGE_CODEC_FEATURE_READ as u32;
+        /// Image codec supports writing images (can create an [`ImageEncoder`]).
+        const WRITE       = BL_IMAGE_CODEC_FEATURE_WRITE as u32;
         /// Image codec supports lossless compression.
-        Lossless   = BL_IMAGE_CODEC_FEATURE_LOSSLESS,
-        /// Image codec supports loosy compression.
-        Lossy      = BL_IMAGE_CODEC_FEATURE_LOSSY,
+        const LOSSLESS    = BL_IMAGE_CODEC_FEATURE_LOSSLESS as u32;
+        /// Image codec supports lossy compression.
+        const LOSSY       = BL_IMAGE_CODEC_FEATURE_LOSSY as u32;
         /// Image codec supports writing multiple frames (GIF).
-        MultiFrame = BL_IMAGE_CODEC_FEATURE_MULTI_FRAME,
+        const MULTI_FRAME = BL_IMAGE_CODEC_FEATURE_MULTI_FRAME as u32;
         /// Image codec supports IPTC metadata.
-        Iptc       = BL_IMAGE_CODEC_FEATURE_IPTC,
+        const IPTC        = BL_IMAGE_CODEC_FEATURE_IPTC as u32;
         /// Image codec supports EXIF metadata.
-        Exif       = BL_IMAGE_CODEC_FEATURE_EXIF,
+        const EXIF        = BL_IMAGE_CODEC_FEATURE_EXIF as u32;
         /// Image codec supports XMP metadata.
-        Xmp        = BL_IMAGE_CODEC_FEATURE_XMP,
+        const XMP         = BL_IMAGE_CODEC_FEATURE_XMP as u32;
+    }
+}
+
+/// Returns the contents of a Blend2D string.
+///
+/// # Safety
+///
+/// The string must outlive the returned slice.
+unsafe fn bl_str<'a>(s: *const ffi::BLStringCore) -> &'a str {
+    unsafe {
+        let data = ffi::bl_string_get_data(s);
+        let size = ffi::bl_string_get_size(s);
+        if data.is_null() || size == 0 {
+            return "";
+        }
+        str::from_utf8(slice::from_raw_parts(data as *const u8, size)).unwrap_or_default()
     }
-    Default => Read
 }
 
 /// Provides a unified interface for inspecting image data and creating image
 /// decoders & encoders.
 #[repr(transparent)]
-pub struct ImageCodec {
-    core: ffi::BLImageCodecCore,
-}
+pub struct ImageCodec(pub(crate) ffi::BLImageCodecCore);
 
-unsafe impl WrappedBlCore for ImageCodec {
-    type Core = ffi::BLImageCodecCore;
-    const IMPL_TYPE_INDEX: usize = crate::variant::ImplType::ImageCodec as usize;
+impl ImageCodec {
+    /// Creates a weak reference to the codec `core`.
+    pub(crate) fn from_core_weak(core: &ffi::BLImageCodecCore) -> Self {
+        let mut codec = std::mem::MaybeUninit::<ffi::BLImageCodecCore>::uninit();
+        unsafe {
+            ffi::bl_image_codec_init_weak(codec.as_mut_ptr(), core);
+            ImageCodec(codec.assume_init())
+        }
+    }
 
     #[inline]
-    fn from_core(core: Self::Core) -> Self {
-        ImageCodec { core }
+    fn impl_(&self) -> &ffi::BLImageCodecImpl {
+        unsafe { &*object_impl(&self.0) }
     }
-}
 
-impl ImageCodec {
     /// Creates an [`ImageDecoder`] for this codec.
-    pub fn create_decoder(&self) -> Option<ImageDecoder> {
-        unsafe {
-            let mut decoder = ImageDecoder::from_core(*ImageDecoder::none());
-            errcode_to_result(ffi::blImageCodecCreateDecoder(
-                self.core(),
-                decoder.core_mut(),
-            ))
+    pub fn create_decoder(&self) -> Result<ImageDecoder, Error> {
+        let mut decoder = ImageDecoder::default();
+        err_to_result(unsafe { ffi::bl_image_codec_create_decoder(&self.0, &mut decoder.0) })
             .map(|_| decoder)
-            .ok()
-        }
     }
 
     /// Creates an [`ImageEncoder`] for this codec.
-    pub fn create_encoder(&self) -> Option<ImageEncoder> {
-        unsafe {
-            let mut encoder = ImageEncoder::from_core(*ImageEncoder::none());
-            errcode_to_result(ffi::blImageCodecCreateEncoder(
-                self.core(),
-                encoder.core_mut(),
-            ))
+    pub fn create_encoder(&self) -> Result<ImageEncoder, Error> {
+        let mut encoder = ImageEncoder::default();
+        err_to_result(unsafe { ffi::bl_image_codec_create_encoder(&self.0, &mut encoder.0) })
             .map(|_| encoder)
-            .ok()
-        }
     }
 
-    /// Inspects the given data blob and determines how likely it is that the
-    /// file belongs to this codec.
+    /// Inspects the given data and returns how likely it is, from `0` to
+    /// `100`, that it belongs to this codec.
     #[inline]
     pub fn inspect_data<R: AsRef<[u8]>>(&self, data: R) -> u32 {
-        // FIXME figure out how to interpret the u32 return value
+        let data = data.as_ref();
+        unsafe { ffi::bl_image_codec_inspect_data(&self.0, data.as_ptr(), data.len()) }
+    }
+
+    /// Finds the built-in codec most likely to decode `data`, `None` if no
+    /// codec recognizes it.
+    pub fn find_by_data<R: AsRef<[u8]>>(data: R) -> Option<ImageCodec> {
+        let data = data.as_ref();
+        let mut codec = std::mem::MaybeUninit::<ffi::BLImageCodecCore>::uninit();
         unsafe {
-            ffi::blImageCodecInspectData(
-                self.core(),
-                data.as_ref().as_ptr() as *const _,
-                data.as_ref().len(),
-            )
+            ffi::bl_image_codec_init(codec.as_mut_ptr());
+            let mut codec = ImageCodec(codec.assume_init());
+            err_to_result(ffi::bl_image_codec_find_by_data(
+                &mut codec.0,
+                data.as_ptr() as *const _,
+                data.len(),
+                ptr::null(),
+            ))
+            .ok()
+            .map(|_| codec)
         }
     }
 
-    /// Returns the blend2d builtin codecs.
-    #[inline]
+    /// Returns the built-in codecs, including the codecs added with
+    /// [`ImageCodec::add_to_built_in`].
     pub fn built_in_codecs() -> Array<ImageCodec> {
-        let mut core = ffi::BLArrayCore {
-            impl_: ptr::null_mut(),
-        };
-        unsafe { ffi::blImageCodecArrayInitBuiltInCodecs(&mut core) };
-        WrappedBlCore::from_core(core)
+        let mut codecs = Array::new();
+        unsafe { ffi::bl_image_codec_array_assign_built_in_codecs(&mut codecs.0) };
+        codecs
     }
 
-    /// Adds a codec to the built in codecs list.
+    /// Adds a codec to the built-in codecs, making it available to
+    /// [`Image::read_from_file`] and [`Image::write_to_file`].
     #[inline]
-    pub fn add_to_built_in(codec: &ImageCodec) {
-        unsafe { expect_mem_err(ffi::blImageCodecAddToBuiltIn(codec.core())) };
+    pub fn add_to_built_in(codec: &ImageCodec) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_image_codec_add_to_built_in(&codec.0) })
     }
 
-    /// Removes the codec from the built in codecs list.
+    /// Removes the codec from the built-in codecs.
     #[inline]
-    pub fn remove_from_built_in(codec: &ImageCodec) {
-        unsafe { ffi::blImageCodecRemoveFromBuiltIn(codec.core()) };
+    pub fn remove_from_built_in(codec: &ImageCodec) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_image_codec_remove_from_built_in(&codec.0) })
     }
 
     /// The codec's name.
     #[inline]
     pub fn name(&self) -> &str {
-        unsafe { str::from_utf8_unchecked(CStr::from_ptr(self.impl_().name).to_bytes()) }
+        unsafe { bl_str(&self.impl_().name) }
     }
 
     /// The codec's vendor.
     #[inline]
     pub fn vendor(&self) -> &str {
-        unsafe { str::from_utf8_unchecked(CStr::from_ptr(self.impl_().vendor).to_bytes()) }
+        unsafe { bl_str(&self.impl_().vendor) }
     }
 
     /// The codec's mime-type.
     #[inline]
     pub fn mime_type(&self) -> &str {
-        unsafe { str::from_utf8_unchecked(CStr::from_ptr(self.impl_().mimeType).to_bytes()) }
+        unsafe { bl_str(&self.impl_().mime_type) }
     }
 
     /// The codec's file extensions.
     pub fn extensions(&self) -> impl Iterator<Item = &str> {
-        unsafe {
-            CStr::from_ptr(self.impl_().extensions)
-                .to_str()
-                .unwrap_or_default()
-                .split('|')
-        }
+        unsafe { bl_str(&self.impl_().extensions) }
+            .split('|')
+            .filter(|e| !e.is_empty())
     }
 
     /// The codec's features.
     #[inline]
     pub fn features(&self) -> ImageCodecFeatures {
-        (self.impl_().features as u32).into()
+        ImageCodecFeatures::from_bits_retain(self.impl_().features)
     }
 }
 
@@ -163,92 +184,82 @@ impl fmt::Debug for ImageCodec {
 impl PartialEq for ImageCodec {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        self.impl_equals(other)
+        unsafe { ffi::bl_object_equals(&self.0 as *const _ as _, &other.0 as *const _ as _) }
     }
 }
 
 impl Clone for ImageCodec {
+    #[inline]
     fn clone(&self) -> Self {
-        Self::from_core(self.init_weak())
+        ImageCodec::from_core_weak(&self.0)
     }
 }
 
 impl Drop for ImageCodec {
+    #[inline]
     fn drop(&mut self) {
-        unsafe { ffi::blImageCodecReset(&mut self.core) };
+        unsafe { ffi::bl_image_codec_destroy(&mut self.0) };
     }
 }
 
 /// An image encoder belonging to a certain [`ImageCodec`].
 #[repr(transparent)]
-pub struct ImageEncoder {
-    core: ffi::BLImageEncoderCore,
-}
-
-unsafe impl WrappedBlCore for ImageEncoder {
-    type Core = ffi::BLImageEncoderCore;
-    const IMPL_TYPE_INDEX: usize = crate::variant::ImplType::ImageEncoder as usize;
+pub struct ImageEncoder(pub(crate) ffi::BLImageEncoderCore);
 
+impl ImageEncoder {
     #[inline]
-    fn from_core(core: Self::Core) -> Self {
-        ImageEncoder { core }
+    fn impl_(&self) -> &ffi::BLImageEncoderImpl {
+        unsafe { &*object_impl(&self.0) }
     }
-}
 
-impl ImageEncoder {
     /// The codec this encoder belongs to.
     #[inline]
-    pub fn codec(&self) -> &ImageCodec {
-        unsafe { cast_ref(&self.impl_().codec) }
+    pub fn codec(&self) -> ImageCodec {
+        ImageCodec::from_core_weak(&self.impl_().codec)
     }
 
     #[inline]
-    pub fn restart(&mut self) -> Result<()> {
-        unsafe { errcode_to_result(ffi::blImageEncoderRestart(self.core_mut())) }
+    pub fn restart(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_image_encoder_restart(&mut self.0) })
     }
 
     /// The last encoding result.
     #[inline]
-    pub fn last_result(&self) -> Result<()> {
-        errcode_to_result(self.impl_().lastResult)
+    pub fn last_result(&self) -> Result<(), Error> {
+        err_to_result(self.impl_().last_result)
     }
 
-    /// The current frame index (to be decoded).
+    /// The current frame index (to be encoded).
     #[inline]
     pub fn frame_index(&self) -> u64 {
-        self.impl_().frameIndex
+        self.impl_().frame_index
     }
 
-    /// The position in the source buffer.
+    /// The position in the destination buffer.
     #[inline]
     pub fn buffer_index(&self) -> usize {
-        self.impl_().bufferIndex
+        self.impl_().buffer_index
     }
 
-    #[inline]
-    pub fn write_frame(&mut self, image: &Image) -> Result<Array<u8>> {
-        unsafe {
-            let mut arr = Array::<u8>::new();
-            errcode_to_result(ffi::blImageEncoderWriteFrame(
-                self.core_mut(),
-                arr.core_mut(),
-                image.core(),
-            ))
-            .map(|_| arr)
-        }
+    /// Encodes `image` as the next frame and returns the encoded data.
+    pub fn write_frame(&mut self, image: &Image) -> Result<Array<u8>, Error> {
+        let mut data = Array::new();
+        err_to_result(unsafe {
+            ffi::bl_image_encoder_write_frame(&mut self.0, &mut data.0, &image.0)
+        })
+        .map(|_| data)
     }
 }
 
-impl PartialEq for ImageEncoder {
+impl Default for ImageEncoder {
+    /// Creates an encoder without a codec.
     #[inline]
-    fn eq(&self, other: &Self) -> bool {
-        self.impl_equals(other)
-    }
-}
-
-impl Clone for ImageEncoder {
-    fn clone(&self) -> Self {
-        Self::from_core(self.init_weak())
+    fn default() -> Self {
+        let mut encoder = std::mem::MaybeUninit::<ffi::BLImageEncoderCore>::uninit();
+        unsafe {
+            ffi::bl_image_encoder_init(encoder.as_mut_ptr());
+            ImageEncoder(encoder.assume_init())
+        }
     }
 }
 
@@ -261,94 +272,81 @@ impl fmt::Debug for ImageEncoder {
 }
 
 impl Drop for ImageEncoder {
+    #[inline]
     fn drop(&mut self) {
-        unsafe { ffi::blImageEncoderReset(&mut self.core) };
+        unsafe { ffi::bl_image_encoder_destroy(&mut self.0) };
     }
 }
 
 /// An image decoder belonging to a certain [`ImageCodec`].
 #[repr(transparent)]
-pub struct ImageDecoder {
-    core: ffi::BLImageDecoderCore,
-}
-
-unsafe impl WrappedBlCore for ImageDecoder {
-    type Core = ffi::BLImageDecoderCore;
-    const IMPL_TYPE_INDEX: usize = crate::variant::ImplType::ImageDecoder as usize;
+pub struct ImageDecoder(pub(crate) ffi::BLImageDecoderCore);
 
+impl ImageDecoder {
     #[inline]
-    fn from_core(core: Self::Core) -> Self {
-        ImageDecoder { core }
+    fn impl_(&self) -> &ffi::BLImageDecoderImpl {
+        unsafe { &*object_impl(&self.0) }
     }
-}
 
-impl ImageDecoder {
     /// The codec this decoder belongs to.
     #[inline]
-    pub fn codec(&self) -> &ImageCodec {
-        unsafe { cast_ref(&self.impl_().codec) }
+    pub fn codec(&self) -> ImageCodec {
+        ImageCodec::from_core_weak(&self.impl_().codec)
     }
 
     #[inline]
-    pub fn restart(&mut self) -> Result<()> {
-        unsafe { errcode_to_result(ffi::blImageDecoderRestart(self.core_mut())) }
+    pub fn restart(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_image_decoder_restart(&mut self.0) })
     }
 
     /// The last decoding result.
     #[inline]
-    pub fn last_result(&self) -> Result<()> {
-        errcode_to_result(self.impl_().lastResult)
+    pub fn last_result(&self) -> Result<(), Error> {
+        err_to_result(self.impl_().last_result)
     }
 
     /// The current frame index (to be decoded).
     #[inline]
     pub fn frame_index(&self) -> u64 {
-        self.impl_().frameIndex
+        self.impl_().frame_index
     }
 
     /// The position in the source buffer.
     #[inline]
     pub fn buffer_index(&self) -> usize {
-        self.impl_().bufferIndex
+        self.impl_().buffer_index
     }
 
-    pub fn read_info<R: AsRef<[u8]>>(&mut self, buf: R) -> Result<ImageInfo> {
-        unsafe {
-            let mut dst = mem::zeroed();
-            errcode_to_result(ffi::blImageDecoderReadInfo(
-                self.core_mut(),
-                &mut dst as *mut _ as *mut _,
-                buf.as_ref().as_ptr() as *const _,
-                buf.as_ref().len(),
-            ))
-            .map(|_| dst)
-        }
+    /// Reads the [`ImageInfo`] from the encoded `data`.
+    pub fn read_info<R: AsRef<[u8]>>(&mut self, data: R) -> Result<ImageInfo, Error> {
+        let data = data.as_ref();
+        let mut info = unsafe { std::mem::zeroed::<ffi::BLImageInfo>() };
+        err_to_result(unsafe {
+            ffi::bl_image_decoder_read_info(&mut self.0, &mut info, data.as_ptr(), data.len())
+        })
+        .map(|_| ImageInfo::from_ffi(&info))
     }
 
-    pub fn read_frame<R: AsRef<[u8]>>(&mut self, buf: R) -> Result<Image> {
-        unsafe {
-            let mut dst = Image::from_core(*Image::none());
-            errcode_to_result(ffi::blImageDecoderReadFrame(
-                self.core_mut(),
-                dst.core_mut(),
-                buf.as_ref().as_ptr() as *const _,
-                buf.as_ref().len(),
-            ))
-            .map(|_| dst)
-        }
+    /// Decodes the next frame from the encoded `data`.
+    pub fn read_frame<R: AsRef<[u8]>>(&mut self, data: R) -> Result<Image, Error> {
+        let data = data.as_ref();
+        let mut image = Image::default();
+        err_to_result(unsafe {
+            ffi::bl_image_decoder_read_frame(&mut self.0, &mut image.0, data.as_ptr(), data.len())
+        })
+        .map(|_| image)
     }
 }
 
-impl PartialEq for ImageDecoder {
+impl Default for ImageDecoder {
+    /// Creates a decoder without a codec.
     #[inline]
-    fn eq(&self, other: &Self) -> bool {
-        self.impl_equals(other)
-    }
-}
-
-impl Clone for ImageDecoder {
-    fn clone(&self) -> Self {
-        Self::from_core(self.init_weak())
+    fn default() -> Self {
+        let mut decoder = std::mem::MaybeUninit::<ffi::BLImageDecoderCore>::uninit();
+        unsafe {
+            ffi::bl_image_decoder_init(decoder.as_mut_ptr());
+            ImageDecoder(decoder.assume_init())
+        }
     }
 }
 
@@ -361,14 +359,19 @@ impl fmt::Debug for ImageDecoder {
 }
 
 impl Drop for ImageDecoder {
+    #[inline]
     fn drop(&mut self) {
-        unsafe { ffi::blImageDecoderReset(&mut self.core) };
+        unsafe { ffi::bl_image_decoder_destroy(&mut self.0) };
     }
 }
 
 #[cfg(test)]
 mod test_codec {
+    use std::io::ErrorKind;
+
     use crate::codec::ImageCodec;
+    use crate::geometry::SizeI;
+    use crate::image::{Format, Image};
 
     #[test]
     fn test_built_in_codecs() {
@@ -382,7 +385,7 @@ mod test_codec {
         let encoder = codec
             .create_encoder()
             .expect("codec does not support encoding");
-        assert_eq!(codec, encoder.codec());
+        assert_eq!(codec, &encoder.codec());
     }
 
     #[test]
@@ -392,6 +395,41 @@ mod test_codec {
         let decoder = codec
             .create_decoder()
             .expect("codec does not support decoding");
-        assert_eq!(codec, decoder.codec());
+        assert_eq!(codec, &decoder.codec());
+    }
+
+    #[test]
+    fn test_probe() {
+        let image = Image::new(3, 2, Format::PRgb32).unwrap();
+        let codecs = ImageCodec::built_in_codecs();
+        let codec = codecs.find_codec_by_name("PNG").unwrap();
+        let data = codec.create_encoder().unwrap().write_frame(&image).unwrap();
+        assert_eq!(ImageCodec::find_by_data(&data).as_ref(), Some(codec));
+        assert_eq!(codecs.find_codec_by_data(&data), Some(codec));
+        let info = Image::probe(&data).unwrap();
+        assert_eq!(info.size, SizeI::new(3, 2));
+        assert!(Image::probe(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_probe_file() {
+        let dir = std::env::temp_dir().join(format!("blend2d-probe-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (png, text) = (dir.join("image.png"), dir.join("image.txt"));
+        Image::new(3, 2, Format::PRgb32)
+            .unwrap()
+            .write_to_file(&png)
+            .unwrap();
+        std::fs::write(&text, b"not an image").unwrap();
+        // Larger than the prefix that is probed first.
+        let large = dir.join("large.bin");
+        std::fs::write(&large, vec![0u8; 100 * 1024]).unwrap();
+
+        assert_eq!(Image::probe_file(&png).unwrap().size, SizeI::new(3, 2));
+        let kind = |path| Image::probe_file(path).unwrap_err().kind();
+        assert_eq!(kind(dir.join("missing.png")), ErrorKind::NotFound);
+        assert_eq!(kind(text), ErrorKind::InvalidData);
+        assert_eq!(kind(large), ErrorKind::InvalidData);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,726 @@
+//! Image codecs implemented in Rust.
+//!
+//! A Rust codec is wrapped into an impl that carries the same virtual tables
+//! Blend2D's built-in codecs use, so once it is added to the built-in codecs
+//! it is picked up by [`Image::read_from_file`] and [`Image::write_to_file`]
+//! like any other codec.
+
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, null_mut};
+use std::slice;
+
+use crate::codec::property::{read_var, write_var};
+use crate::codec::{ImageCodec, ImageCodecFeatures, PropertyValue};
+use crate::image::{Image, ImageInfo};
+use crate::{Error, err_to_result};
+
+use ffi::BLResultCode::*;
+
+/// An image codec implemented in Rust.
+///
+/// Wrap it with [`ImageCodec::new`] and register it with
+/// [`ImageCodec::add_to_built_in`] to make it available to
+/// [`Image::read_from_file`].
+pub trait ImageCodecImpl: Send + Sync + 'static {
+    /// The decoder of the codec, [`Infallible`] if it can't read images.
+    type Decoder: ImageDecoderImpl;
+    /// The encoder of the codec, [`Infallible`] if it can't write images.
+    type Encoder: ImageEncoderImpl;
+
+    /// The codec's name, for example `"PPM"`.
+    fn name(&self) -> &str;
+
+    /// The codec's vendor.
+    fn vendor(&self) -> &str {
+        ""
+    }
+
+    /// The codec's mime-type, for example `"image/x-portable-pixmap"`.
+    fn mime_type(&self) -> &str;
+
+    /// The codec's file extensions separated by `|`, for example `"ppm|pam"`.
+    fn extensions(&self) -> &str;
+
+    /// The codec's features.
+    fn features(&self) -> ImageCodecFeatures;
+
+    /// Returns how likely it is, from `0` to `100`, that `data` is encoded
+    /// in the format of this codec.
+    fn inspect_data(&self, data: &[u8]) -> u32;
+
+    /// Creates a new decoder, `None` if the codec can't read images.
+    fn create_decoder(&self) -> Option<Self::Decoder>;
+
+    /// Creates a new encoder, `None` if the codec can't write images.
+    fn create_encoder(&self) -> Option<Self::Encoder>;
+}
+
+/// A decoder of an [`ImageCodecImpl`].
+///
+/// Decoders get the whole encoded data with every call.
+pub trait ImageDecoderImpl: Send + 'static {
+    /// Resets the decoder to decode from the first frame again.
+    fn restart(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Reads the [`ImageInfo`] from the encoded `data`.
+    fn read_info(&mut self, data: &[u8]) -> Result<ImageInfo, Error>;
+
+    /// Decodes the next frame from the encoded `data`.
+    fn read_frame(&mut self, data: &[u8]) -> Result<Image, Error>;
+
+    /// Returns the value of the property `name`, see
+    /// [`ImageDecoder::property`](super::ImageDecoder::property).
+    ///
+    /// There are no properties by default.
+    fn property(&self, name: &str) -> Result<PropertyValue, Error> {
+        let _ = name;
+        Err(Error)
+    }
+
+    /// Sets the property `name` to `value`, see
+    /// [`ImageDecoder::set_property`](super::ImageDecoder::set_property).
+    ///
+    /// There are no properties by default.
+    fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), Error> {
+        let _ = (name, value);
+        Err(Error)
+    }
+}
+
+/// An encoder of an [`ImageCodecImpl`].
+pub trait ImageEncoderImpl: Send + 'static {
+    /// Resets the encoder to encode the first frame again.
+    fn restart(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Encodes `image` as the next frame and returns the encoded data.
+    fn write_frame(&mut self, image: &Image) -> Result<Vec<u8>, Error>;
+
+    /// Returns the value of the property `name`, see
+    /// [`ImageEncoder::property`](super::ImageEncoder::property).
+    ///
+    /// There are no properties by default.
+    fn property(&self, name: &str) -> Result<PropertyValue, Error> {
+        let _ = name;
+        Err(Error)
+    }
+
+    /// Sets the property `name` to `value`, see
+    /// [`ImageEncoder::set_property`](super::ImageEncoder::set_property).
+    ///
+    /// There are no properties by default.
+    fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), Error> {
+        let _ = (name, value);
+        Err(Error)
+    }
+}
+
+impl ImageDecoderImpl for Infallible {
+    fn read_info(&mut self, _: &[u8]) -> Result<ImageInfo, Error> {
+        match *self {}
+    }
+
+    fn read_frame(&mut self, _: &[u8]) -> Result<Image, Error> {
+        match *self {}
+    }
+}
+
+impl ImageEncoderImpl for Infallible {
+    fn write_frame(&mut self, _: &Image) -> Result<Vec<u8>, Error> {
+        match *self {}
+    }
+}
+
+impl ImageCodec {
+    /// Wraps a codec implemented in Rust.
+    pub fn new<C: ImageCodecImpl>(codec: C) -> Result<ImageCodec, Error> {
+        let mut core = MaybeUninit::<ffi::BLImageCodecCore>::uninit();
+        unsafe {
+            let p = alloc_impl::<CodecImpl<C>>(
+                core.as_mut_ptr() as *mut _,
+                ffi::BLObjectType::BL_OBJECT_TYPE_IMAGE_CODEC as u32,
+            )?;
+            let base = &raw mut (*p).base;
+            (*base).virt = &VTables::<C>::CODEC;
+            init_string(&raw mut (*base).name, codec.name());
+            init_string(&raw mut (*base).vendor, codec.vendor());
+            init_string(&raw mut (*base).mime_type, codec.mime_type());
+            init_string(&raw mut (*base).extensions, codec.extensions());
+            (*base).features = codec.features().bits();
+            // A copy that doesn't hold a reference, decoders and encoders
+            // created by the codec reference the codec through it.
+            (&raw mut (*p).core).write(ptr::read(core.as_ptr()));
+            (&raw mut (*p).codec).write(codec);
+            Ok(ImageCodec(core.assume_init()))
+        }
+    }
+}
+
+#[repr(C)]
+struct CodecImpl<C> {
+    base: ffi::BLImageCodecImpl,
+    core: ffi::BLImageCodecCore,
+    codec: C,
+}
+
+#[repr(C)]
+struct DecoderImpl<D> {
+    base: ffi::BLImageDecoderImpl,
+    decoder: D,
+}
+
+#[repr(C)]
+struct EncoderImpl<E> {
+    base: ffi::BLImageEncoderImpl,
+    encoder: E,
+}
+
+/// Allocates a dynamic, reference counted impl of type `T` for `core`.
+///
+/// # Safety
+///
+/// `core` must point to an uninitialized object core.
+unsafe fn alloc_impl<T>(core: *mut ffi::BLObjectCore, object_type: u32) -> Result<*mut T, Error> {
+    let info = (object_type << ffi::BLObjectInfoShift::BL_OBJECT_INFO_TYPE_SHIFT as u32)
+        | ffi::BLObjectInfoBits::BL_OBJECT_INFO_M_FLAG as u32;
+    let mut p: *mut ffi::BLObjectImpl = null_mut();
+    err_to_result(unsafe {
+        ffi::bl_object_alloc_impl_aligned(
+            core,
+            info,
+            mem::size_of::<T>(),
+            mem::align_of::<T>(),
+            &mut p,
+        )
+    })?;
+    Ok(p as *mut T)
+}
+
+unsafe fn init_string(s: *mut ffi::BLStringCore, value: &str) {
+    unsafe { ffi::bl_string_init_with_data(s, value.as_ptr() as *const c_char, value.len()) };
+}
+
+/// Returns `size` bytes at `data`, which may be null if `size` is zero.
+unsafe fn bytes<'a>(data: *const u8, size: usize) -> &'a [u8] {
+    match size {
+        0 => &[],
+        _ => unsafe { slice::from_raw_parts(data, size) },
+    }
+}
+
+/// Runs `f`, turning errors and panics into the `error` result code.
+fn guard<T, F>(error: ffi::BLResult, f: F) -> Result<T, ffi::BLResult>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(_)) => Err(error),
+        Err(_) => Err(BL_ERROR_INVALID_STATE as _),
+    }
+}
+
+/// The virtual tables of the impls wrapping codec `C`.
+struct VTables<C>(PhantomData<C>);
+
+impl<C: ImageCodecImpl> VTables<C> {
+    const CODEC: ffi::BLImageCodecVirt = ffi::BLImageCodecVirt {
+        base: ffi::BLObjectVirtBase {
+            destroy: Some(destroy_codec::<C>),
+            get_property: Some(get_property),
+            set_property: Some(set_property),
+        },
+        inspect_data: Some(inspect_data::<C>),
+        create_decoder: Some(create_decoder::<C>),
+        create_encoder: Some(create_encoder::<C>),
+    };
+
+    const DECODER: ffi::BLImageDecoderVirt = ffi::BLImageDecoderVirt {
+        base: ffi::BLObjectVirtBase {
+            destroy: Some(destroy_decoder::<C::Decoder>),
+            get_property: Some(object_get_property::<DecoderImpl<C::Decoder>>),
+            set_property: Some(object_set_property::<DecoderImpl<C::Decoder>>),
+        },
+        restart: Some(decoder_restart::<C::Decoder>),
+        read_info: Some(read_info::<C::Decoder>),
+        read_frame: Some(read_frame::<C::Decoder>),
+    };
+
+    const ENCODER: ffi::BLImageEncoderVirt = ffi::BLImageEncoderVirt {
+        base: ffi::BLObjectVirtBase {
+            destroy: Some(destroy_encoder::<C::Encoder>),
+            get_property: Some(object_get_property::<EncoderImpl<C::Encoder>>),
+            set_property: Some(object_set_property::<EncoderImpl<C::Encoder>>),
+        },
+        restart: Some(encoder_restart::<C::Encoder>),
+        write_frame: Some(write_frame::<C::Encoder>),
+    };
+}
+
+unsafe extern "C" fn get_property(
+    _impl: *const ffi::BLObjectImpl,
+    _name: *const c_char,
+    _name_size: usize,
+    _value_out: *mut ffi::BLVarCore,
+) -> ffi::BLResult {
+    BL_ERROR_INVALID_KEY as _
+}
+
+unsafe extern "C" fn set_property(
+    _impl: *mut ffi::BLObjectImpl,
+    _name: *const c_char,
+    _name_size: usize,
+    _value: *const ffi::BLVarCore,
+) -> ffi::BLResult {
+    BL_ERROR_INVALID_KEY as _
+}
+
+/// Forwards the properties of a decoder or encoder impl.
+trait Properties {
+    fn property(&self, name: &str) -> Result<PropertyValue, Error>;
+    fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), Error>;
+}
+
+impl<D: ImageDecoderImpl> Properties for DecoderImpl<D> {
+    fn property(&self, name: &str) -> Result<PropertyValue, Error> {
+        self.decoder.property(name)
+    }
+
+    fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), Error> {
+        self.decoder.set_property(name, value)
+    }
+}
+
+impl<E: ImageEncoderImpl> Properties for EncoderImpl<E> {
+    fn property(&self, name: &str) -> Result<PropertyValue, Error> {
+        self.encoder.property(name)
+    }
+
+    fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), Error> {
+        self.encoder.set_property(name, value)
+    }
+}
+
+/// Returns the property name, `None` if it isn't valid UTF-8.
+unsafe fn property_name<'a>(name: *const c_char, name_size: usize) -> Option<&'a str> {
+    std::str::from_utf8(unsafe { bytes(name as *const u8, name_size) }).ok()
+}
+
+unsafe extern "C" fn object_get_property<T: Properties>(
+    impl_: *const ffi::BLObjectImpl,
+    name: *const c_char,
+    name_size: usize,
+    value_out: *mut ffi::BLVarCore,
+) -> ffi::BLResult {
+    let p = unsafe { &*(impl_ as *const T) };
+    let Some(name) = (unsafe { property_name(name, name_size) }) else {
+        return BL_ERROR_INVALID_KEY as _;
+    };
+    match guard(BL_ERROR_INVALID_KEY as _, || p.property(name)) {
+        Ok(value) => unsafe { write_var(value_out, &value) },
+        Err(code) => code,
+    }
+}
+
+unsafe extern "C" fn object_set_property<T: Properties>(
+    impl_: *mut ffi::BLObjectImpl,
+    name: *const c_char,
+    name_size: usize,
+    value: *const ffi::BLVarCore,
+) -> ffi::BLResult {
+    let p = unsafe { &mut *(impl_ as *mut T) };
+    let Some(name) = (unsafe { property_name(name, name_size) }) else {
+        return BL_ERROR_INVALID_KEY as _;
+    };
+    let value = match unsafe { read_var(value) } {
+        Ok(value) => value,
+        Err(_) => return BL_ERROR_INVALID_VALUE as _,
+    };
+    match guard(BL_ERROR_INVALID_KEY as _, || p.set_property(name, value)) {
+        Ok(()) => BL_SUCCESS as _,
+        Err(code) => code,
+    }
+}
+
+unsafe extern "C" fn destroy_codec<C>(impl_: *mut ffi::BLObjectImpl) -> ffi::BLResult {
+    unsafe {
+        let p = impl_ as *mut CodecImpl<C>;
+        ffi::bl_string_destroy(&mut (*p).base.name);
+        ffi::bl_string_destroy(&mut (*p).base.vendor);
+        ffi::bl_string_destroy(&mut (*p).base.mime_type);
+        ffi::bl_string_destroy(&mut (*p).base.extensions);
+        ptr::drop_in_place(&raw mut (*p).codec);
+        ffi::bl_object_free_impl(impl_)
+    }
+}
+
+unsafe extern "C" fn inspect_data<C: ImageCodecImpl>(
+    impl_: *const ffi::BLImageCodecImpl,
+    data: *const u8,
+    size: usize,
+) -> u32 {
+    let codec = unsafe { &(*(impl_ as *const CodecImpl<C>)).codec };
+    let data = unsafe { bytes(data, size) };
+    panic::catch_unwind(AssertUnwindSafe(|| codec.inspect_data(data).min(100))).unwrap_or(0)
+}
+
+unsafe extern "C" fn create_decoder<C: ImageCodecImpl>(
+    impl_: *const ffi::BLImageCodecImpl,
+    dst: *mut ffi::BLImageDecoderCore,
+) -> ffi::BLResult {
+    let codec_impl = unsafe { &*(impl_ as *const CodecImpl<C>) };
+    let decoder = match guard(BL_ERROR_IMAGE_DECODER_NOT_PROVIDED as _, || {
+        codec_impl.codec.create_decoder().ok_or(Error)
+    }) {
+        Ok(decoder) => decoder,
+        Err(code) => return code,
+    };
+
+    let mut core = MaybeUninit::<ffi::BLImageDecoderCore>::uninit();
+    unsafe {
+        let p = match alloc_impl::<DecoderImpl<C::Decoder>>(
+            core.as_mut_ptr() as *mut _,
+            ffi::BLObjectType::BL_OBJECT_TYPE_IMAGE_DECODER as u32,
+        ) {
+            Ok(p) => p,
+            Err(_) => return BL_ERROR_OUT_OF_MEMORY as _,
+        };
+        let base = &raw mut (*p).base;
+        (*base).virt = &VTables::<C>::DECODER;
+        ffi::bl_image_codec_init_weak(&raw mut (*base).codec, &codec_impl.core);
+        (*base).last_result = BL_SUCCESS as _;
+        (*base).handle = null_mut();
+        (*base).frame_index = 0;
+        (*base).buffer_index = 0;
+        (&raw mut (*p).decoder).write(decoder);
+        ffi::bl_image_decoder_assign_move(dst, core.as_mut_ptr())
+    }
+}
+
+unsafe extern "C" fn create_encoder<C: ImageCodecImpl>(
+    impl_: *const ffi::BLImageCodecImpl,
+    dst: *mut ffi::BLImageEncoderCore,
+) -> ffi::BLResult {
+    let codec_impl = unsafe { &*(impl_ as *const CodecImpl<C>) };
+    let encoder = match guard(BL_ERROR_IMAGE_ENCODER_NOT_PROVIDED as _, || {
+        codec_impl.codec.create_encoder().ok_or(Error)
+    }) {
+        Ok(encoder) => encoder,
+        Err(code) => return code,
+    };
+
+    let mut core = MaybeUninit::<ffi::BLImageEncoderCore>::uninit();
+    unsafe {
+        let p = match alloc_impl::<EncoderImpl<C::Encoder>>(
+            core.as_mut_ptr() as *mut _,
+            ffi::BLObjectType::BL_OBJECT_TYPE_IMAGE_ENCODER as u32,
+        ) {
+            Ok(p) => p,
+            Err(_) => return BL_ERROR_OUT_OF_MEMORY as _,
+        };
+        let base = &raw mut (*p).base;
+        (*base).virt = &VTables::<C>::ENCODER;
+        ffi::bl_image_codec_init_weak(&raw mut (*base).codec, &codec_impl.core);
+        (*base).last_result = BL_SUCCESS as _;
+        (*base).handle = null_mut();
+        (*base).frame_index = 0;
+        (*base).buffer_index = 0;
+        (&raw mut (*p).encoder).write(encoder);
+        ffi::bl_image_encoder_assign_move(dst, core.as_mut_ptr())
+    }
+}
+
+unsafe extern "C" fn destroy_decoder<D>(impl_: *mut ffi::BLObjectImpl) -> ffi::BLResult {
+    unsafe {
+        let p = impl_ as *mut DecoderImpl<D>;
+        ffi::bl_image_codec_destroy(&mut (*p).base.codec);
+        ptr::drop_in_place(&raw mut (*p).decoder);
+        ffi::bl_object_free_impl(impl_)
+    }
+}
+
+unsafe extern "C" fn decoder_restart<D: ImageDecoderImpl>(
+    impl_: *mut ffi::BLImageDecoderImpl,
+) -> ffi::BLResult {
+    let p = unsafe { &mut *(impl_ as *mut DecoderImpl<D>) };
+    let result = guard(BL_ERROR_INVALID_STATE as _, || p.decoder.restart());
+    p.base.last_result = result.err().unwrap_or(BL_SUCCESS as _);
+    p.base.frame_index = 0;
+    p.base.buffer_index = 0;
+    p.base.last_result
+}
+
+unsafe extern "C" fn read_info<D: ImageDecoderImpl>(
+    impl_: *mut ffi::BLImageDecoderImpl,
+    info_out: *mut ffi::BLImageInfo,
+    data: *const u8,
+    size: usize,
+) -> ffi::BLResult {
+    let p = unsafe { &mut *(impl_ as *mut DecoderImpl<D>) };
+    if p.base.last_result != BL_SUCCESS as ffi::BLResult {
+        return p.base.last_result;
+    }
+    let data = unsafe { bytes(data, size) };
+    match guard(BL_ERROR_INVALID_DATA as _, || p.decoder.read_info(data)) {
+        Ok(info) => {
+            unsafe { info_out.write(info.to_ffi()) };
+            BL_SUCCESS as _
+        },
+        Err(code) => {
+            p.base.last_result = code;
+            code
+        },
+    }
+}
+
+unsafe extern "C" fn read_frame<D: ImageDecoderImpl>(
+    impl_: *mut ffi::BLImageDecoderImpl,
+    image_out: *mut ffi::BLImageCore,
+    data: *const u8,
+    size: usize,
+) -> ffi::BLResult {
+    let p = unsafe { &mut *(impl_ as *mut DecoderImpl<D>) };
+    if p.base.last_result != BL_SUCCESS as ffi::BLResult {
+        return p.base.last_result;
+    }
+    let data = unsafe { bytes(data, size) };
+    match guard(BL_ERROR_INVALID_DATA as _, || p.decoder.read_frame(data)) {
+        Ok(mut image) => {
+            p.base.frame_index += 1;
+            unsafe { ffi::bl_image_assign_move(image_out, &mut image.0) }
+        },
+        Err(code) => {
+            p.base.last_result = code;
+            code
+        },
+    }
+}
+
+unsafe extern "C" fn destroy_encoder<E>(impl_: *mut ffi::BLObjectImpl) -> ffi::BLResult {
+    unsafe {
+        let p = impl_ as *mut EncoderImpl<E>;
+        ffi::bl_image_codec_destroy(&mut (*p).base.codec);
+        ptr::drop_in_place(&raw mut (*p).encoder);
+        ffi::bl_object_free_impl(impl_)
+    }
+}
+
+unsafe extern "C" fn encoder_restart<E: ImageEncoderImpl>(
+    impl_: *mut ffi::BLImageEncoderImpl,
+) -> ffi::BLResult {
+    let p = unsafe { &mut *(impl_ as *mut EncoderImpl<E>) };
+    let result = guard(BL_ERROR_INVALID_STATE as _, || p.encoder.restart());
+    p.base.last_result = result.err().unwrap_or(BL_SUCCESS as _);
+    p.base.frame_index = 0;
+    p.base.buffer_index = 0;
+    p.base.last_result
+}
+
+unsafe extern "C" fn write_frame<E: ImageEncoderImpl>(
+    impl_: *mut ffi::BLImageEncoderImpl,
+    dst: *mut ffi::BLArrayCore,
+    image: *const ffi::BLImageCore,
+) -> ffi::BLResult {
+    let p = unsafe { &mut *(impl_ as *mut EncoderImpl<E>) };
+    if p.base.last_result != BL_SUCCESS as ffi::BLResult {
+        return p.base.last_result;
+    }
+    let image = unsafe { &*(image as *const Image) };
+    match guard(BL_ERROR_INVALID_VALUE as _, || p.encoder.write_frame(image)) {
+        Ok(data) => {
+            p.base.frame_index += 1;
+            unsafe { ffi::bl_array_assign_data(dst, data.as_ptr() as *const _, data.len()) }
+        },
+        Err(code) => {
+            p.base.last_result = code;
+            code
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Format;
+
+    /// A codec for binary PPM images, which only writes them if `writable`.
+    struct Ppm {
+        writable: bool,
+    }
+
+    struct PpmDecoder;
+
+    struct PpmEncoder;
+
+    fn parse_header(data: &[u8]) -> Result<(i32, i32, usize), Error> {
+        let mut fields = Vec::new();
+        let mut pos = 2;
+        while fields.len() < 3 {
+            while data.get(pos).ok_or(Error)?.is_ascii_whitespace() {
+                pos += 1;
+            }
+            let start = pos;
+            while data.get(pos).is_some_and(u8::is_ascii_digit) {
+                pos += 1;
+            }
+            let field = std::str::from_utf8(&data[start..pos]).map_err(|_| Error)?;
+            fields.push(field.parse::<i32>().map_err(|_| Error)?);
+        }
+        Ok((fields[0], fields[1], pos + 1))
+    }
+
+    impl ImageDecoderImpl for PpmDecoder {
+        fn read_info(&mut self, data: &[u8]) -> Result<ImageInfo, Error> {
+            let (w, h, _) = parse_header(data)?;
+            Ok(ImageInfo {
+                size: crate::geometry::SizeI::new(w, h),
+                depth: 24,
+                plane_count: 1,
+                frame_count: 1,
+                format: "PPM".into(),
+                ..ImageInfo::default()
+            })
+        }
+
+        fn read_frame(&mut self, data: &[u8]) -> Result<Image, Error> {
+            let (w, h, offset) = parse_header(data)?;
+            let rgb = data
+                .get(offset..offset + (w * h * 3) as usize)
+                .ok_or(Error)?;
+            let mut image = Image::new(w, h, Format::XRgb32)?;
+            let stride = image.stride();
+            let pixels = image.pixels_mut()?;
+            for (i, px) in rgb.chunks_exact(3).enumerate() {
+                let (x, y) = (i % w as usize, i / w as usize);
+                let argb = 0xFF00_0000 | u32::from_be_bytes([0, px[0], px[1], px[2]]);
+                pixels[y * stride + x * 4..][..4].copy_from_slice(&argb.to_ne_bytes());
+            }
+            Ok(image)
+        }
+    }
+
+    impl ImageEncoderImpl for PpmEncoder {
+        fn write_frame(&mut self, image: &Image) -> Result<Vec<u8>, Error> {
+            let size = image.size();
+            let mut data = format!("P6 {} {} 255\n", size.w, size.h).into_bytes();
+            for y in 0..size.h {
+                for px in image.row(y).ok_or(Error)?.chunks_exact(4) {
+                    let [_, r, g, b] = u32::from_ne_bytes(px.try_into().unwrap()).to_be_bytes();
+                    data.extend_from_slice(&[r, g, b]);
+                }
+            }
+            Ok(data)
+        }
+    }
+
+    impl ImageCodecImpl for Ppm {
+        type Decoder = PpmDecoder;
+        type Encoder = PpmEncoder;
+
+        fn name(&self) -> &str {
+            "PPM"
+        }
+
+        fn mime_type(&self) -> &str {
+            "image/x-portable-pixmap"
+        }
+
+        fn extensions(&self) -> &str {
+            "ppm"
+        }
+
+        fn features(&self) -> ImageCodecFeatures {
+            let features = ImageCodecFeatures::READ | ImageCodecFeatures::LOSSLESS;
+            match self.writable {
+                true => features | ImageCodecFeatures::WRITE,
+                false => features,
+            }
+        }
+
+        fn inspect_data(&self, data: &[u8]) -> u32 {
+            if data.starts_with(b"P6") { 100 } else { 0 }
+        }
+
+        fn create_decoder(&self) -> Option<PpmDecoder> {
+            Some(PpmDecoder)
+        }
+
+        fn create_encoder(&self) -> Option<PpmEncoder> {
+            self.writable.then_some(PpmEncoder)
+        }
+    }
+
+    #[test]
+    fn test_custom_codec() {
+        let codec = ImageCodec::new(Ppm { writable: false }).unwrap();
+        assert_eq!(codec.name(), "PPM");
+        assert_eq!(codec.extensions().collect::<Vec<_>>(), ["ppm"]);
+        assert_eq!(codec.inspect_data(b"P6 1 1 255\n"), 100);
+        assert!(codec.create_encoder().is_err());
+
+        let data = b"P6 2 1 255\n\xFF\x00\x00\x00\x00\xFF";
+        let mut decoder = codec.create_decoder().unwrap();
+        assert_eq!(decoder.codec(), codec);
+        assert_eq!(decoder.read_info(data).unwrap().size.w, 2);
+        let image = decoder.read_frame(data).unwrap();
+        assert_eq!(image.size(), crate::geometry::SizeI::new(2, 1));
+        assert_eq!(decoder.frame_index(), 1);
+        assert!(decoder.read_frame(b"P6 x").is_err());
+        assert!(decoder.last_result().is_err());
+    }
+
+    /// An opaque red and blue pixel in PPM and in XRgb32.
+    const PPM_DATA: &[u8] = b"P6 2 1 255\n\xFF\x00\x00\x00\x00\xFF";
+    const XRGB_PIXELS: [u32; 2] = [0xFFFF0000, 0xFF0000FF];
+
+    fn pixels(image: &Image) -> Vec<u32> {
+        image
+            .row(0)
+            .unwrap()
+            .chunks_exact(4)
+            .map(|px| u32::from_ne_bytes(px.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_custom_encoder() {
+        let codec = ImageCodec::new(Ppm { writable: true }).unwrap();
+        let image = codec
+            .create_decoder()
+            .unwrap()
+            .read_frame(PPM_DATA)
+            .unwrap();
+        let mut encoder = codec.create_encoder().unwrap();
+        assert_eq!(encoder.codec(), codec);
+        // The encoded data is copied into a Blend2D array.
+        let data = encoder.write_frame(&image).unwrap();
+        assert_eq!(&*data, PPM_DATA);
+    }
+
+    #[test]
+    fn test_custom_codec_built_in() {
+        let dir = std::env::temp_dir().join(format!("blend2d-codec-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("x.ppm"), dir.join("y.ppm"));
+        std::fs::write(&input, PPM_DATA).unwrap();
+
+        let codec = ImageCodec::new(Ppm { writable: true }).unwrap();
+        ImageCodec::add_to_built_in(&codec).unwrap();
+        let image = Image::read_from_file(&input).unwrap();
+        assert_eq!(image.size(), crate::geometry::SizeI::new(2, 1));
+        assert_eq!(pixels(&image), XRGB_PIXELS);
+        image.write_to_file(&output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), PPM_DATA);
+
+        ImageCodec::remove_from_built_in(&codec).unwrap();
+        assert!(Image::read_from_file(&input).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+//! Decoding all frames of multi-frame and animated images.
+
+use std::iter::FusedIterator;
+use std::time::Duration;
+
+use crate::Error;
+use crate::codec::ImageDecoder;
+use crate::geometry::RectI;
+use crate::image::{Image, ImageInfo};
+
+/// What happens to the area of a frame before the next frame is rendered.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FrameDisposal {
+    /// The area is left as it is.
+    #[default]
+    None,
+    /// The area is cleared to transparent black.
+    Background,
+    /// The area is reverted to what it was before the frame was rendered.
+    Previous,
+}
+
+/// How a frame is composited onto the output buffer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FrameBlend {
+    /// The frame replaces the pixels of its area.
+    #[default]
+    Source,
+    /// The frame is composited over the pixels of its area.
+    Over,
+}
+
+/// A decoded frame of an image, as yielded by [`ImageDecoder::frames`].
+#[derive(Debug)]
+pub struct Frame {
+    /// The decoded image.
+    pub image: Image,
+    /// Zero-based index of the frame.
+    pub index: u64,
+    /// How long the frame is shown, zero for still images.
+    pub delay: Duration,
+    /// The area of the canvas the frame was specified for.
+    pub area: RectI,
+    /// What happens to the area after the frame was shown.
+    pub disposal: FrameDisposal,
+    /// How the frame was composited onto the previous frames.
+    pub blend: FrameBlend,
+}
+
+/// The frame control information of an APNG frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct FrameControl {
+    area: RectI,
+    delay: Duration,
+    disposal: FrameDisposal,
+    blend: FrameBlend,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[inline]
+fn be_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// Collects the `fcTL` chunks of an APNG stream in the order of the frames
+/// they describe, empty for anything that isn't a PNG.
+///
+/// The default image stored in the `IDAT` chunks is only part of the
+/// animation if an `fcTL` chunk precedes it, otherwise it is still decoded as
+/// the first frame and has no frame control.
+fn frame_controls(data: &[u8]) -> Vec<Option<FrameControl>> {
+    let mut controls = Vec::new();
+    let Some(mut chunks) = data.strip_prefix(PNG_SIGNATURE) else {
+        return controls;
+    };
+    while chunks.len() >= 12 {
+        let len = be_u32(chunks) as usize;
+        let Some(body) = len.checked_add(8).and_then(|end| chunks.get(8..end)) else {
+            break;
+        };
+        match &chunks[4..8] {
+            b"fcTL" if len >= 26 => {
+                let delay_num = u16::from_be_bytes([body[20], body[21]]);
+                let delay_den = match u16::from_be_bytes([body[22], body[23]]) {
+                    0 => 100,
+                    den => den,
+                };
+                controls.push(Some(FrameControl {
+                    area: RectI::new(
+                        be_u32(&body[12..]) as i32,
+                        be_u32(&body[16..]) as i32,
+                        be_u32(&body[4..]) as i32,
+                        be_u32(&body[8..]) as i32,
+                    ),
+                    delay: Duration::from_secs_f64(delay_num as f64 / delay_den as f64),
+                    disposal: match body[24] {
+                        1 => FrameDisposal::Background,
+                        2 => FrameDisposal::Previous,
+                        _ => FrameDisposal::None,
+                    },
+                    blend: match body[25] {
+                        1 => FrameBlend::Over,
+                        _ => FrameBlend::Source,
+                    },
+                }));
+            },
+            b"IDAT" if controls.is_empty() => controls.push(None),
+            b"IEND" => break,
+            _ => {},
+        }
+        chunks = len
+            .checked_add(12)
+            .and_then(|start| chunks.get(start..))
+            .unwrap_or_default();
+    }
+    controls
+}
+
+/// An iterator over the decoded [`Frame`]s of an image, created by
+/// [`ImageDecoder::frames`].
+///
+/// Iteration stops after the first error.
+pub struct Frames<'a> {
+    decoder: &'a mut ImageDecoder,
+    data: &'a [u8],
+    info: Option<ImageInfo>,
+    controls: Vec<Option<FrameControl>>,
+    index: u64,
+    done: bool,
+}
+
+impl Frames<'_> {
+    /// The [`ImageInfo`] of the image, `None` if it couldn't be read.
+    #[inline]
+    pub fn info(&self) -> Option<&ImageInfo> {
+        self.info.as_ref()
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<Frame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let Some(info) = &self.info else {
+            self.done = true;
+            return Some(Err(Error));
+        };
+        // A frame count of zero means the codec doesn't know how many frames
+        // there are, in which case decoding goes on until it fails.
+        let frame_count = info.frame_count;
+        if frame_count != 0 && self.index >= frame_count {
+            self.done = true;
+            return None;
+        }
+        let image = match self.decoder.read_frame(self.data) {
+            Ok(image) => image,
+            Err(e) => {
+                self.done = true;
+                return match frame_count == 0 && self.index > 0 {
+                    true => None,
+                    false => Some(Err(e)),
+                };
+            },
+        };
+        let index = self.index;
+        self.index += 1;
+        let size = image.size();
+        let control = self.controls.get(index as usize).copied().flatten();
+        Some(Ok(Frame {
+            image,
+            index,
+            delay: control.map_or(Duration::ZERO, |c| c.delay),
+            area: control.map_or(RectI::new(0, 0, size.w, size.h), |c| c.area),
+            disposal: control.map_or(FrameDisposal::None, |c| c.disposal),
+            blend: control.map_or(FrameBlend::Source, |c| c.blend),
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.info {
+            Some(info) if !self.done && info.frame_count != 0 => {
+                let n = info.frame_count.saturating_sub(self.index) as usize;
+                (0, Some(n))
+            },
+            Some(_) if !self.done => (0, None),
+            _ => (0, Some(0)),
+        }
+    }
+}
+
+impl FusedIterator for Frames<'_> {}
+
+impl ImageDecoder {
+    /// Returns an iterator decoding all frames of the encoded `data`, along
+    /// with their delay, disposal and blend information.
+    ///
+    /// The decoder is restarted first. Frame timing is read from the `fcTL`
+    /// chunks of APNG images, frames of other images have no delay.
+    pub fn frames<'a>(&'a mut self, data: &'a [u8]) -> Frames<'a> {
+        let info = self.restart().and_then(|_| self.read_info(data)).ok();
+        Frames {
+            decoder: self,
+            data,
+            info,
+            controls: frame_controls(data),
+            index: 0,
+            done: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut chunk = (body.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(body);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn fctl(seq: u32, w: u32, h: u32, x: u32, y: u32, delay: [u16; 2], ops: [u8; 2]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for v in [seq, w, h, x, y] {
+            body.extend_from_slice(&v.to_be_bytes());
+        }
+        body.extend_from_slice(&delay[0].to_be_bytes());
+        body.extend_from_slice(&delay[1].to_be_bytes());
+        body.extend_from_slice(&ops);
+        chunk(b"fcTL", &body)
+    }
+
+    #[test]
+    fn test_frame_controls() {
+        let data = [
+            PNG_SIGNATURE.to_vec(),
+            chunk(b"IHDR", &[0; 13]),
+            fctl(0, 4, 4, 0, 0, [1, 10], [0, 0]),
+            chunk(b"IDAT", &[0; 3]),
+            fctl(1, 2, 3, 1, 1, [5, 0], [2, 1]),
+            chunk(b"fdAT", &[0; 7]),
+            chunk(b"IEND", &[]),
+            fctl(2, 1, 1, 0, 0, [1, 1], [0, 0]),
+        ]
+        .concat();
+        let controls = frame_controls(&data)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(controls.len(), 2);
+        assert_eq!(controls[0].delay, Duration::from_millis(100));
+        assert_eq!(controls[0].area, RectI::new(0, 0, 4, 4));
+        assert_eq!(controls[1].delay, Duration::from_millis(50));
+        assert_eq!(controls[1].area, RectI::new(1, 1, 2, 3));
+        assert_eq!(controls[1].disposal, FrameDisposal::Previous);
+        assert_eq!(controls[1].blend, FrameBlend::Over);
+        assert_eq!(frame_controls(&data).len(), 2);
+
+        assert!(frame_controls(b"GIF89a").is_empty());
+        assert!(frame_controls(&data[..40]).is_empty());
+    }
+
+    #[test]
+    fn test_default_image_outside_animation() {
+        let data = [
+            PNG_SIGNATURE.to_vec(),
+            chunk(b"IHDR", &[0; 13]),
+            chunk(b"IDAT", &[0; 3]),
+            chunk(b"IDAT", &[0; 3]),
+            fctl(0, 2, 2, 0, 0, [1, 10], [0, 0]),
+            chunk(b"fdAT", &[0; 7]),
+            chunk(b"IEND", &[]),
+        ]
+        .concat();
+        let controls = frame_controls(&data);
+        assert_eq!(controls.len(), 2);
+        assert_eq!(controls[0], None);
+        assert_eq!(controls[1].unwrap().area, RectI::new(0, 0, 2, 2));
+    }
+
+    #[test]
+    fn test_chunk_length_overflow() {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend_from_slice(&u32::MAX.to_be_bytes());
+        data.extend_from_slice(b"fcTL");
+        data.extend_from_slice(&[0; 30]);
+        assert!(frame_controls(&data).is_empty());
+    }
+
+    #[test]
+    fn test_png_frames() {
+        let image = Image::new(3, 2, crate::image::Format::PRgb32).unwrap();
+        let codecs = crate::codec::ImageCodec::built_in_codecs();
+        let codec = codecs.find_codec_by_name("PNG").unwrap();
+        let data = codec.create_encoder().unwrap().write_frame(&image).unwrap();
+
+        let mut decoder = codec.create_decoder().unwrap();
+        let mut frames = decoder.frames(&data);
+        assert_eq!(frames.info().unwrap().frame_count, 1);
+        let frame = frames.next().unwrap().unwrap();
+        assert_eq!(frame.index, 0);
+        assert_eq!(frame.image.size(), image.size());
+        assert_eq!(frame.area, RectI::new(0, 0, 3, 2));
+        assert_eq!(frame.delay, Duration::ZERO);
+        assert_eq!(frame.disposal, FrameDisposal::None);
+        assert!(frames.next().is_none());
+    }
+}
@@ -0,0 +1,273 @@
+//! Properties of image codecs, decoders and encoders.
+//!
+//! Blend2D's codecs expose their tunables through named properties, for
+//! example `"quality"` of the JPEG encoder or `"compression"` of the PNG
+//! encoder. Blend2D's encoders don't have properties for the PNG filter or
+//! the BMP pixel format, they always pick those themselves.
+
+use std::mem::MaybeUninit;
+use std::os::raw::{c_char, c_void};
+use std::slice;
+
+use crate::codec::{ImageCodec, ImageDecoder, ImageEncoder};
+use crate::{Error, err_to_result};
+
+/// The value of a property.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// A boolean flag.
+    Bool(bool),
+    /// A signed integer.
+    Int(i64),
+    /// An unsigned integer.
+    UInt(u64),
+    /// A floating point number.
+    Double(f64),
+    /// A UTF-8 string, such as a name.
+    String(String),
+}
+
+impl From<bool> for PropertyValue {
+    #[inline]
+    fn from(value: bool) -> Self {
+        PropertyValue::Bool(value)
+    }
+}
+
+impl From<i32> for PropertyValue {
+    #[inline]
+    fn from(value: i32) -> Self {
+        PropertyValue::Int(value as i64)
+    }
+}
+
+impl From<i64> for PropertyValue {
+    #[inline]
+    fn from(value: i64) -> Self {
+        PropertyValue::Int(value)
+    }
+}
+
+impl From<u32> for PropertyValue {
+    #[inline]
+    fn from(value: u32) -> Self {
+        PropertyValue::UInt(value as u64)
+    }
+}
+
+impl From<u64> for PropertyValue {
+    #[inline]
+    fn from(value: u64) -> Self {
+        PropertyValue::UInt(value)
+    }
+}
+
+impl From<f64> for PropertyValue {
+    #[inline]
+    fn from(value: f64) -> Self {
+        PropertyValue::Double(value)
+    }
+}
+
+impl From<&str> for PropertyValue {
+    #[inline]
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.to_owned())
+    }
+}
+
+impl From<String> for PropertyValue {
+    #[inline]
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+
+/// Reads the value of a property from `var`.
+///
+/// # Safety
+///
+/// `var` must point to an initialized `BLVarCore`.
+pub(crate) unsafe fn read_var(var: *const ffi::BLVarCore) -> Result<PropertyValue, Error> {
+    use ffi::BLObjectType::*;
+
+    let var = var as *const c_void;
+    unsafe {
+        match ffi::bl_var_get_type(var) {
+            BL_OBJECT_TYPE_BOOL => {
+                let mut value = false;
+                err_to_result(ffi::bl_var_to_bool(var, &mut value))
+                    .map(|_| PropertyValue::Bool(value))
+            },
+            BL_OBJECT_TYPE_INT64 => {
+                let mut value = 0;
+                err_to_result(ffi::bl_var_to_int64(var, &mut value))
+                    .map(|_| PropertyValue::Int(value))
+            },
+            BL_OBJECT_TYPE_UINT64 => {
+                let mut value = 0;
+                err_to_result(ffi::bl_var_to_uint64(var, &mut value))
+                    .map(|_| PropertyValue::UInt(value))
+            },
+            BL_OBJECT_TYPE_DOUBLE => {
+                let mut value = 0.0;
+                err_to_result(ffi::bl_var_to_double(var, &mut value))
+                    .map(|_| PropertyValue::Double(value))
+            },
+            BL_OBJECT_TYPE_STRING => {
+                let s = var as *const ffi::BLStringCore;
+                let data = ffi::bl_string_get_data(s) as *const u8;
+                let bytes = match data.is_null() {
+                    true => &[][..],
+                    false => slice::from_raw_parts(data, ffi::bl_string_get_size(s)),
+                };
+                Ok(PropertyValue::String(
+                    String::from_utf8_lossy(bytes).into_owned(),
+                ))
+            },
+            _ => Err(Error),
+        }
+    }
+}
+
+/// Assigns `value` to `var`.
+///
+/// # Safety
+///
+/// `var` must point to an initialized `BLVarCore`.
+pub(crate) unsafe fn write_var(var: *mut ffi::BLVarCore, value: &PropertyValue) -> ffi::BLResult {
+    let var = var as *mut c_void;
+    unsafe {
+        match value {
+            PropertyValue::Bool(v) => ffi::bl_var_assign_bool(var, *v),
+            PropertyValue::Int(v) => ffi::bl_var_assign_int64(var, *v),
+            PropertyValue::UInt(v) => ffi::bl_var_assign_uint64(var, *v),
+            PropertyValue::Double(v) => ffi::bl_var_assign_double(var, *v),
+            PropertyValue::String(v) => {
+                let mut s = MaybeUninit::<ffi::BLStringCore>::uninit();
+                ffi::bl_string_init_with_data(s.as_mut_ptr(), v.as_ptr() as *const c_char, v.len());
+                let mut s = s.assume_init();
+                let result = ffi::bl_var_assign_move(var, &raw mut s as *mut c_void);
+                ffi::bl_string_destroy(&mut s);
+                result
+            },
+        }
+    }
+}
+
+/// Runs `f` with a temporary, null initialized `BLVarCore`.
+fn with_var<T>(f: impl FnOnce(*mut ffi::BLVarCore) -> Result<T, Error>) -> Result<T, Error> {
+    let mut var = MaybeUninit::<ffi::BLVarCore>::uninit();
+    unsafe {
+        ffi::bl_var_init_null(var.as_mut_ptr() as *mut c_void);
+        let mut var = var.assume_init();
+        let result = f(&mut var);
+        ffi::bl_var_destroy(&raw mut var as *mut c_void);
+        result
+    }
+}
+
+fn get_property(object: *const c_void, name: &str) -> Result<PropertyValue, Error> {
+    with_var(|var| unsafe {
+        err_to_result(ffi::bl_object_get_property(
+            object,
+            name.as_ptr() as *const c_char,
+            name.len(),
+            var,
+        ))?;
+        read_var(var)
+    })
+}
+
+fn set_property(object: *mut c_void, name: &str, value: &PropertyValue) -> Result<(), Error> {
+    with_var(|var| unsafe {
+        err_to_result(write_var(var, value))?;
+        err_to_result(ffi::bl_object_set_property(
+            object,
+            name.as_ptr() as *const c_char,
+            name.len(),
+            var as *const c_void,
+        ))
+    })
+}
+
+macro_rules! impl_properties {
+    ($($ty:ident),*) => {
+        $(
+            impl $ty {
+                /// Returns the value of the property `name`.
+                ///
+                /// Fails if there is no such property.
+                #[inline]
+                pub fn property(&self, name: &str) -> Result<PropertyValue, Error> {
+                    get_property(&self.0 as *const _ as *const c_void, name)
+                }
+
+                /// Sets the property `name` to `value`.
+                ///
+                /// Fails if there is no such property, if it is read-only or
+                /// if `value` is out of range.
+                #[inline]
+                pub fn set_property<V>(&mut self, name: &str, value: V) -> Result<(), Error>
+                where
+                    V: Into<PropertyValue>,
+                {
+                    set_property(&mut self.0 as *mut _ as *mut c_void, name, &value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_properties!(ImageCodec, ImageDecoder, ImageEncoder);
+
+impl ImageEncoder {
+    /// Sets the quality of lossy encoders such as JPEG, from `0` to `100`.
+    #[inline]
+    pub fn set_quality(&mut self, quality: u32) -> Result<(), Error> {
+        self.set_property("quality", quality)
+    }
+
+    /// Sets the compression level of lossless encoders such as PNG, higher
+    /// levels produce smaller output but take longer.
+    #[inline]
+    pub fn set_compression(&mut self, level: u32) -> Result<(), Error> {
+        self.set_property("compression", level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{Format, Image};
+
+    #[test]
+    fn test_png_compression() {
+        let mut image = Image::new(64, 64, Format::PRgb32).unwrap();
+        for (i, byte) in image.pixels_mut().unwrap().iter_mut().enumerate() {
+            *byte = (i % 61) as u8 | 0x80;
+        }
+        let codecs = ImageCodec::built_in_codecs();
+        let codec = codecs.find_codec_by_name("PNG").unwrap();
+
+        let encode = |level: u32| {
+            let mut encoder = codec.create_encoder().unwrap();
+            encoder.set_compression(level).unwrap();
+            let value = match encoder.property("compression").unwrap() {
+                PropertyValue::Int(v) => v as u64,
+                PropertyValue::UInt(v) => v,
+                other => panic!("unexpected compression {other:?}"),
+            };
+            assert_eq!(value, level as u64);
+            encoder.write_frame(&image).unwrap().len()
+        };
+        assert!(encode(0) > encode(9));
+        assert!(
+            codec
+                .create_encoder()
+                .unwrap()
+                .property("no such property")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,749 @@
+//! 32-bit, 64-bit and floating point RGBA colors.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// 32-bit RGBA color (8-bit per component) stored as `0xAARRGGBB`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rgba32 {
+    pub value: u32,
+}
+
+/// 64-bit RGBA color (16-bit per component) stored as `0xAAAARRRRGGGGBBBB`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rgba64 {
+    pub value: u64,
+}
+
+/// 128-bit RGBA color stored as 4 32-bit floating point values in `[0, 1]`
+/// range.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+#[inline]
+fn div255(x: u32) -> u32 {
+    let x = x + 128;
+    (x + (x >> 8)) >> 8
+}
+
+#[inline]
+fn div65535(x: u64) -> u64 {
+    let x = x + 32768;
+    (x + (x >> 16)) >> 16
+}
+
+#[inline]
+fn unit_to_u8(v: f32) -> u32 {
+    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u32
+}
+
+#[inline]
+fn unit_to_u16(v: f32) -> u64 {
+    (v.clamp(0.0, 1.0) * 65535.0 + 0.5) as u64
+}
+
+impl Rgba32 {
+    pub const TRANSPARENT: Rgba32 = Rgba32::from_value(0x00000000);
+    pub const BLACK: Rgba32 = Rgba32::from_value(0xFF000000);
+    pub const WHITE: Rgba32 = Rgba32::from_value(0xFFFFFFFF);
+
+    /// Creates a color from its packed `0xAARRGGBB` representation.
+    #[inline]
+    pub const fn from_value(value: u32) -> Self {
+        Rgba32 { value }
+    }
+
+    /// Creates a color from its components.
+    #[inline]
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Rgba32 {
+            value: (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32,
+        }
+    }
+
+    /// Creates a fully opaque color from its components.
+    #[inline]
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, 0xFF)
+    }
+
+    #[inline]
+    pub const fn r(self) -> u8 {
+        (self.value >> 16) as u8
+    }
+
+    #[inline]
+    pub const fn g(self) -> u8 {
+        (self.value >> 8) as u8
+    }
+
+    #[inline]
+    pub const fn b(self) -> u8 {
+        self.value as u8
+    }
+
+    #[inline]
+    pub const fn a(self) -> u8 {
+        (self.value >> 24) as u8
+    }
+
+    #[inline]
+    pub fn set_r(&mut self, r: u8) {
+        self.value = (self.value & 0xFF00FFFF) | (r as u32) << 16;
+    }
+
+    #[inline]
+    pub fn set_g(&mut self, g: u8) {
+        self.value = (self.value & 0xFFFF00FF) | (g as u32) << 8;
+    }
+
+    #[inline]
+    pub fn set_b(&mut self, b: u8) {
+        self.value = (self.value & 0xFFFFFF00) | b as u32;
+    }
+
+    #[inline]
+    pub fn set_a(&mut self, a: u8) {
+        self.value = (self.value & 0x00FFFFFF) | (a as u32) << 24;
+    }
+
+    /// Returns true if the alpha component is `0xFF`.
+    #[inline]
+    pub const fn is_opaque(self) -> bool {
+        self.value >= 0xFF000000
+    }
+
+    /// Returns true if the alpha component is zero.
+    #[inline]
+    pub const fn is_transparent(self) -> bool {
+        self.value <= 0x00FFFFFF
+    }
+
+    /// Multiplies the color components by alpha.
+    pub fn premultiply(self) -> Self {
+        let a = self.a() as u32;
+        Rgba32::new(
+            div255(self.r() as u32 * a) as u8,
+            div255(self.g() as u32 * a) as u8,
+            div255(self.b() as u32 * a) as u8,
+            a as u8,
+        )
+    }
+
+    /// Divides the color components by alpha, reverting [`premultiply`].
+    ///
+    /// [`premultiply`]: Rgba32::premultiply
+    pub fn unpremultiply(self) -> Self {
+        let a = self.a() as u32;
+        if a == 0 {
+            return Rgba32::TRANSPARENT;
+        }
+        let f = |c: u8| ((c as u32 * 255 + a / 2) / a).min(255) as u8;
+        Rgba32::new(f(self.r()), f(self.g()), f(self.b()), a as u8)
+    }
+}
+
+impl Rgba64 {
+    pub const TRANSPARENT: Rgba64 = Rgba64::from_value(0x0000000000000000);
+    pub const BLACK: Rgba64 = Rgba64::from_value(0xFFFF000000000000);
+    pub const WHITE: Rgba64 = Rgba64::from_value(0xFFFFFFFFFFFFFFFF);
+
+    /// Creates a color from its packed `0xAAAARRRRGGGGBBBB` representation.
+    #[inline]
+    pub const fn from_value(value: u64) -> Self {
+        Rgba64 { value }
+    }
+
+    /// Creates a color from its components.
+    #[inline]
+    pub const fn new(r: u16, g: u16, b: u16, a: u16) -> Self {
+        Rgba64 {
+            value: (a as u64) << 48 | (r as u64) << 32 | (g as u64) << 16 | b as u64,
+        }
+    }
+
+    /// Creates a fully opaque color from its components.
+    #[inline]
+    pub const fn rgb(r: u16, g: u16, b: u16) -> Self {
+        Self::new(r, g, b, 0xFFFF)
+    }
+
+    #[inline]
+    pub const fn r(self) -> u16 {
+        (self.value >> 32) as u16
+    }
+
+    #[inline]
+    pub const fn g(self) -> u16 {
+        (self.value >> 16) as u16
+    }
+
+    #[inline]
+    pub const fn b(self) -> u16 {
+        self.value as u16
+    }
+
+    #[inline]
+    pub const fn a(self) -> u16 {
+        (self.value >> 48) as u16
+    }
+
+    #[inline]
+    pub fn set_r(&mut self, r: u16) {
+        self.value = (self.value & 0xFFFF0000FFFFFFFF) | (r as u64) << 32;
+    }
+
+    #[inline]
+    pub fn set_g(&mut self, g: u16) {
+        self.value = (self.value & 0xFFFFFFFF0000FFFF) | (g as u64) << 16;
+    }
+
+    #[inline]
+    pub fn set_b(&mut self, b: u16) {
+        self.value = (self.value & 0xFFFFFFFFFFFF0000) | b as u64;
+    }
+
+    #[inline]
+    pub fn set_a(&mut self, a: u16) {
+        self.value = (self.value & 0x0000FFFFFFFFFFFF) | (a as u64) << 48;
+    }
+
+    /// Returns true if the alpha component is `0xFFFF`.
+    #[inline]
+    pub const fn is_opaque(self) -> bool {
+        self.value >= 0xFFFF000000000000
+    }
+
+    /// Returns true if the alpha component is zero.
+    #[inline]
+    pub const fn is_transparent(self) -> bool {
+        self.value <= 0x0000FFFFFFFFFFFF
+    }
+
+    /// Multiplies the color components by alpha.
+    pub fn premultiply(self) -> Self {
+        let a = self.a() as u64;
+        Rgba64::new(
+            div65535(self.r() as u64 * a) as u16,
+            div65535(self.g() as u64 * a) as u16,
+            div65535(self.b() as u64 * a) as u16,
+            a as u16,
+        )
+    }
+
+    /// Divides the color components by alpha, reverting [`premultiply`].
+    ///
+    /// [`premultiply`]: Rgba64::premultiply
+    pub fn unpremultiply(self) -> Self {
+        let a = self.a() as u64;
+        if a == 0 {
+            return Rgba64::TRANSPARENT;
+        }
+        let f = |c: u16| ((c as u64 * 65535 + a / 2) / a).min(65535) as u16;
+        Rgba64::new(f(self.r()), f(self.g()), f(self.b()), a as u16)
+    }
+}
+
+impl Rgba {
+    pub const TRANSPARENT: Rgba = Rgba::new(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Rgba = Rgba::new(0.0, 0.0, 0.0, 1.0);
+    pub const WHITE: Rgba = Rgba::new(1.0, 1.0, 1.0, 1.0);
+
+    /// Creates a color from its components.
+    #[inline]
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Rgba { r, g, b, a }
+    }
+
+    /// Creates a fully opaque color from its components.
+    #[inline]
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.0)
+    }
+
+    /// Returns true if the alpha component is at least `1.0`.
+    #[inline]
+    pub fn is_opaque(self) -> bool {
+        self.a >= 1.0
+    }
+
+    /// Returns true if the alpha component is zero or less.
+    #[inline]
+    pub fn is_transparent(self) -> bool {
+        self.a <= 0.0
+    }
+
+    /// Multiplies the color components by alpha.
+    #[inline]
+    pub fn premultiply(self) -> Self {
+        Rgba::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// Divides the color components by alpha, reverting [`premultiply`].
+    ///
+    /// [`premultiply`]: Rgba::premultiply
+    #[inline]
+    pub fn unpremultiply(self) -> Self {
+        if self.a == 0.0 {
+            return Rgba::TRANSPARENT;
+        }
+        Rgba::new(self.r / self.a, self.g / self.a, self.b / self.a, self.a)
+    }
+}
+
+impl From<u32> for Rgba32 {
+    #[inline]
+    fn from(value: u32) -> Self {
+        Rgba32::from_value(value)
+    }
+}
+
+impl From<Rgba32> for u32 {
+    #[inline]
+    fn from(color: Rgba32) -> Self {
+        color.value
+    }
+}
+
+impl From<u64> for Rgba64 {
+    #[inline]
+    fn from(value: u64) -> Self {
+        Rgba64::from_value(value)
+    }
+}
+
+impl From<Rgba64> for u64 {
+    #[inline]
+    fn from(color: Rgba64) -> Self {
+        color.value
+    }
+}
+
+impl From<Rgba64> for Rgba32 {
+    #[inline]
+    fn from(c: Rgba64) -> Self {
+        Rgba32::new(
+            (c.r() >> 8) as u8,
+            (c.g() >> 8) as u8,
+            (c.b() >> 8) as u8,
+            (c.a() >> 8) as u8,
+        )
+    }
+}
+
+impl From<Rgba> for Rgba32 {
+    #[inline]
+    fn from(c: Rgba) -> Self {
+        Rgba32 {
+            value: unit_to_u8(c.a) << 24
+                | unit_to_u8(c.r) << 16
+                | unit_to_u8(c.g) << 8
+                | unit_to_u8(c.b),
+        }
+    }
+}
+
+impl From<Rgba32> for Rgba64 {
+    #[inline]
+    fn from(c: Rgba32) -> Self {
+        Rgba64::new(
+            c.r() as u16 * 0x101,
+            c.g() as u16 * 0x101,
+            c.b() as u16 * 0x101,
+            c.a() as u16 * 0x101,
+        )
+    }
+}
+
+impl From<Rgba> for Rgba64 {
+    #[inline]
+    fn from(c: Rgba) -> Self {
+        Rgba64 {
+            value: unit_to_u16(c.a) << 48
+                | unit_to_u16(c.r) << 32
+                | unit_to_u16(c.g) << 16
+                | unit_to_u16(c.b),
+        }
+    }
+}
+
+impl From<Rgba32> for Rgba {
+    #[inline]
+    fn from(c: Rgba32) -> Self {
+        Rgba::new(
+            c.r() as f32 / 255.0,
+            c.g() as f32 / 255.0,
+            c.b() as f32 / 255.0,
+            c.a() as f32 / 255.0,
+        )
+    }
+}
+
+impl From<Rgba64> for Rgba {
+    #[inline]
+    fn from(c: Rgba64) -> Self {
+        Rgba::new(
+            c.r() as f32 / 65535.0,
+            c.g() as f32 / 65535.0,
+            c.b() as f32 / 65535.0,
+            c.a() as f32 / 65535.0,
+        )
+    }
+}
+
+/// An error returned when parsing a color from a string fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError;
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid color syntax")
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+impl FromStr for Rgba32 {
+    type Err = ParseColorError;
+
+    /// Parses a CSS color.
+    ///
+    /// Accepts `#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa` hex notations,
+    /// the `rgb()` and `rgba()` functions with either numeric or percentage
+    /// components and the CSS named colors.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            parse_hex(hex)
+        } else if let Some(args) = strip_function(s, "rgba").or_else(|| strip_function(s, "rgb")) {
+            parse_rgb_function(args)
+        } else {
+            named_color(s).ok_or(ParseColorError)
+        }
+    }
+}
+
+impl FromStr for Rgba64 {
+    type Err = ParseColorError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Rgba32>().map(Into::into)
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = ParseColorError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Rgba32>().map(Into::into)
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Rgba32, ParseColorError> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseColorError);
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).unwrap();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    match hex.len() {
+        3 => Ok(Rgba32::rgb(digit(0) * 17, digit(1) * 17, digit(2) * 17)),
+        4 => Ok(Rgba32::new(
+            digit(0) * 17,
+            digit(1) * 17,
+            digit(2) * 17,
+            digit(3) * 17,
+        )),
+        6 => Ok(Rgba32::rgb(byte(0), byte(2), byte(4))),
+        8 => Ok(Rgba32::new(byte(0), byte(2), byte(4), byte(6))),
+        _ => Err(ParseColorError),
+    }
+}
+
+fn strip_function<'s>(s: &'s str, name: &str) -> Option<&'s str> {
+    let rest = s.get(..name.len())?;
+    if !rest.eq_ignore_ascii_case(name) {
+        return None;
+    }
+    s[name.len()..]
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+fn parse_rgb_function(args: &str) -> Result<Rgba32, ParseColorError> {
+    // Both the legacy comma separated and the modern space separated syntax
+    // (`rgb(255 0 0 / 50%)`) are accepted.
+    let (channels, alpha) = match args.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha)),
+        None => (args, None),
+    };
+    let mut parts = channels
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|s| !s.is_empty());
+
+    let mut channel = || parts.next().ok_or(ParseColorError).and_then(parse_channel);
+    let (r, g, b) = (channel()?, channel()?, channel()?);
+    let a = match (alpha, parts.next()) {
+        (Some(a), None) => parse_alpha(a.trim())?,
+        (None, Some(a)) => parse_alpha(a)?,
+        (None, None) => 0xFF,
+        (Some(_), Some(_)) => return Err(ParseColorError),
+    };
+    if parts.next().is_some() {
+        return Err(ParseColorError);
+    }
+    Ok(Rgba32::new(r, g, b, a))
+}
+
+fn parse_channel(s: &str) -> Result<u8, ParseColorError> {
+    let value = match s.strip_suffix('%') {
+        Some(pct) => pct.parse::<f32>().map_err(|_| ParseColorError)? / 100.0 * 255.0,
+        None => s.parse::<f32>().map_err(|_| ParseColorError)?,
+    };
+    Ok((value.clamp(0.0, 255.0) + 0.5) as u8)
+}
+
+fn parse_alpha(s: &str) -> Result<u8, ParseColorError> {
+    let value = match s.strip_suffix('%') {
+        Some(pct) => pct.parse::<f32>().map_err(|_| ParseColorError)? / 100.0,
+        None => s.parse::<f32>().map_err(|_| ParseColorError)?,
+    };
+    Ok(unit_to_u8(value) as u8)
+}
+
+fn named_color(name: &str) -> Option<Rgba32> {
+    if name.eq_ignore_ascii_case("transparent") {
+        return Some(Rgba32::TRANSPARENT);
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, rgb)| Rgba32::from_value(0xFF000000 | rgb))
+}
+
+static NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channels() {
+        let c = Rgba32::from_value(0xFFFF6F3F);
+        assert_eq!((c.r(), c.g(), c.b(), c.a()), (0xFF, 0x6F, 0x3F, 0xFF));
+        let c = Rgba64::from_value(0xFFFFFFFF6F6F3F3F);
+        assert_eq!(
+            (c.r(), c.g(), c.b(), c.a()),
+            (0xFFFF, 0x6F6F, 0x3F3F, 0xFFFF)
+        );
+        assert_eq!(Rgba32::from(c), Rgba32::from_value(0xFFFF6F3F));
+        assert_eq!(Rgba64::from(Rgba32::from_value(0xFFFF6F3F)), c);
+    }
+
+    #[test]
+    fn test_float_round_trip() {
+        let c = Rgba32::new(12, 34, 56, 78);
+        assert_eq!(Rgba32::from(Rgba::from(c)), c);
+        assert_eq!(
+            Rgba32::from(Rgba::new(2.0, -1.0, 0.5, 1.0)),
+            Rgba32::rgb(255, 0, 128)
+        );
+    }
+
+    #[test]
+    fn test_premultiply() {
+        let c = Rgba32::new(255, 128, 0, 128);
+        let p = c.premultiply();
+        assert_eq!(p, Rgba32::new(128, 64, 0, 128));
+        assert_eq!(p.unpremultiply(), Rgba32::new(255, 128, 0, 128));
+        assert_eq!(
+            Rgba32::new(10, 20, 30, 0).premultiply(),
+            Rgba32::TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("#f63".parse(), Ok(Rgba32::from_value(0xFFFF6633)));
+        assert_eq!("#f638".parse(), Ok(Rgba32::from_value(0x88FF6633)));
+        assert_eq!("#FF6F3F".parse(), Ok(Rgba32::from_value(0xFFFF6F3F)));
+        assert_eq!("#ff6f3f80".parse(), Ok(Rgba32::from_value(0x80FF6F3F)));
+        assert_eq!(
+            "rgb(255, 111, 63)".parse(),
+            Ok(Rgba32::from_value(0xFFFF6F3F))
+        );
+        assert_eq!(
+            "rgba(100%, 0%, 0%, 0.5)".parse(),
+            Ok(Rgba32::from_value(0x80FF0000))
+        );
+        assert_eq!(
+            "rgb(255 0 0 / 50%)".parse(),
+            Ok(Rgba32::from_value(0x80FF0000))
+        );
+        assert_eq!("RebeccaPurple".parse(), Ok(Rgba32::from_value(0xFF663399)));
+        assert_eq!("transparent".parse(), Ok(Rgba32::TRANSPARENT));
+        assert_eq!("#ff6f3".parse::<Rgba32>(), Err(ParseColorError));
+        assert_eq!("rgb(1, 2)".parse::<Rgba32>(), Err(ParseColorError));
+        assert_eq!("rgb(1, 2, 3, 4, 5)".parse::<Rgba32>(), Err(ParseColorError));
+        assert_eq!("notacolor".parse::<Rgba32>(), Err(ParseColorError));
+    }
+}
@@ -1,12 +1,21 @@
-use std::ptr::null;
+use std::ptr::{null, null_mut};
 
-use ffi::{self, BLGeometryType, BLStrokeCapPosition, BLTransformOp};
+use ffi::{self, BLContextHint, BLGeometryType, BLStrokeCapPosition, BLTransformOp};
 
 use crate::{
-    Error, Gradient, err_to_result, geometry::StrokeCap, image::Image, path::Path, pattern::Pattern,
+    Error, Gradient, Rgba, Rgba32, Rgba64, err_to_result,
+    geometry::{FillRule, Matrix2D, PointI, Rect, RectI, Size, StrokeCap},
+    image::{Image, SubImage},
+    path::{Path, StrokeOptions},
+    pattern::{Pattern, PatternQuality},
+    region::Region,
 };
 
-pub struct Context(ffi::BLContextCore);
+mod layer;
+use self::layer::LayerStack;
+mod shadow;
+
+pub struct Context(ffi::BLContextCore, LayerStack);
 
 use ffi::BLCompOp::*;
 bl_enum! {
@@ -50,26 +59,117 @@ impl Context {
         f(&mut ctx)?;
         ctx.end()
     }
+    /// Attaches the rendering context to `img`.
+    ///
+    /// Fails if layers are still pushed, [`Context::end`] composites them
+    /// first.
     #[inline]
     pub fn begin(&mut self, img: &mut Image) -> Result<(), Error> {
+        if self.layer_count() > 0 {
+            return Err(Error);
+        }
         err_to_result(unsafe { ffi::bl_context_begin(&mut self.0, &mut img.0, null()) })
     }
+    /// Detaches the rendering context from its image, compositing any layers
+    /// that are still pushed.
     #[inline]
     pub fn end(&mut self) -> Result<(), Error> {
+        while self.layer_count() > 0 {
+            self.pop_layer()?;
+        }
         err_to_result(unsafe { ffi::bl_context_end(&mut self.0) })
     }
+    /// Saves the current rendering state, including the comp op, global
+    /// alpha, styles, transform and clip.
+    #[inline]
+    pub fn save(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_save(&mut self.0, null_mut()) })
+    }
+    /// Restores the rendering state to the last saved state.
+    ///
+    /// Every layer has its own rendering state, so a state saved before
+    /// pushing a layer can only be restored after popping it again.
+    #[inline]
+    pub fn restore(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_restore(&mut self.0, null()) })
+    }
+    /// The composition operator used to render.
+    #[inline]
+    pub fn comp_op(&self) -> CompOp {
+        (unsafe { ffi::bl_context_get_comp_op(&self.0) } as u32).into()
+    }
     #[inline]
     pub fn set_comp_op(&mut self, comp_op: CompOp) -> Result<(), Error> {
         err_to_result(unsafe { ffi::bl_context_set_comp_op(&mut self.0, comp_op as i32) })
     }
+    /// The size of the target image in device pixels.
+    #[inline]
+    pub fn target_size(&self) -> Result<Size, Error> {
+        let mut size = Size::default();
+        err_to_result(unsafe { ffi::bl_context_get_target_size(&self.0, &raw mut size as _) })
+            .map(|_| size)
+    }
+    /// Sets the quality used to sample patterns.
+    #[inline]
+    pub fn set_pattern_quality(&mut self, quality: PatternQuality) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_set_hint(
+                &mut self.0,
+                BLContextHint::BL_CONTEXT_HINT_PATTERN_QUALITY,
+                quality as _,
+            )
+        })
+    }
+    /// The global alpha value applied to everything rendered.
+    #[inline]
+    pub fn global_alpha(&self) -> f64 {
+        unsafe { ffi::bl_context_get_global_alpha(&self.0) }
+    }
+    /// Sets the global alpha value, in the range `0.0..=1.0`, applied to
+    /// everything rendered afterwards.
+    #[inline]
+    pub fn set_global_alpha(&mut self, alpha: f64) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_set_global_alpha(&mut self.0, alpha) })
+    }
     // Fill
     #[inline]
+    pub fn fill_rule(&self) -> FillRule {
+        (unsafe { ffi::bl_context_get_fill_rule(&self.0) } as u32).into()
+    }
+    #[inline]
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_set_fill_rule(&mut self.0, fill_rule as _) })
+    }
+    #[inline]
     pub fn fill_all(&mut self) -> Result<(), Error> {
         err_to_result(unsafe { ffi::bl_context_fill_all(&mut self.0) })
     }
     #[inline]
-    pub fn set_fill_style_rgba32(&mut self, rgba32: u32) -> Result<(), Error> {
-        err_to_result(unsafe { ffi::bl_context_set_fill_style_rgba32(&mut self.0, rgba32) })
+    pub fn fill_all_rgba32(&mut self, rgba32: impl Into<Rgba32>) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_fill_all_rgba32(&mut self.0, rgba32.into().value) })
+    }
+    #[inline]
+    pub fn fill_all_rgba64(&mut self, rgba64: impl Into<Rgba64>) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_fill_all_rgba64(&mut self.0, rgba64.into().value) })
+    }
+    #[inline]
+    pub fn set_fill_style_rgba32(&mut self, rgba32: impl Into<Rgba32>) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_set_fill_style_rgba32(&mut self.0, rgba32.into().value)
+        })
+    }
+    #[inline]
+    pub fn set_fill_style_rgba64(&mut self, rgba64: impl Into<Rgba64>) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_set_fill_style_rgba64(&mut self.0, rgba64.into().value)
+        })
+    }
+    #[inline]
+    pub fn set_fill_style_rgba(&mut self, rgba: impl Into<Rgba>) -> Result<(), Error> {
+        let rgba = rgba.into();
+        err_to_result(unsafe {
+            ffi::bl_context_set_fill_style_rgba(&mut self.0, &raw const rgba as _)
+        })
     }
     #[inline]
     pub fn set_fill_style_gradient(&mut self, gradient: &Gradient) -> Result<(), Error> {
@@ -84,6 +184,16 @@ impl Context {
         })
     }
     #[inline]
+    pub fn fill_rect(&mut self, rect: &Rect) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_fill_geometry(
+                &mut self.0,
+                BLGeometryType::BL_GEOMETRY_TYPE_RECTD,
+                rect as *const _ as _,
+            )
+        })
+    }
+    #[inline]
     pub fn fill_round_rect(
         &mut self,
         x: f64,
@@ -113,13 +223,64 @@ impl Context {
         })
     }
     #[inline]
-    pub fn fill_path_rgba32(&mut self, path: &Path, rgba32: u32) -> Result<(), Error> {
+    pub fn fill_path_rgba32(
+        &mut self,
+        path: &Path,
+        rgba32: impl Into<Rgba32>,
+    ) -> Result<(), Error> {
         err_to_result(unsafe {
             ffi::bl_context_fill_geometry_rgba32(
                 &mut self.0,
                 BLGeometryType::BL_GEOMETRY_TYPE_PATH,
                 &raw const path.0 as _,
-                rgba32,
+                rgba32.into().value,
+            )
+        })
+    }
+    #[inline]
+    pub fn fill_path_rgba64(
+        &mut self,
+        path: &Path,
+        rgba64: impl Into<Rgba64>,
+    ) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_fill_geometry_rgba64(
+                &mut self.0,
+                BLGeometryType::BL_GEOMETRY_TYPE_PATH,
+                &raw const path.0 as _,
+                rgba64.into().value,
+            )
+        })
+    }
+    /// Fills the boxes of the region with the current fill style.
+    #[inline]
+    pub fn fill_region(&mut self, region: &Region) -> Result<(), Error> {
+        let boxes = region.data();
+        let view = ffi::BLArrayView {
+            data: boxes.as_ptr() as _,
+            size: boxes.len(),
+        };
+        err_to_result(unsafe {
+            ffi::bl_context_fill_geometry(
+                &mut self.0,
+                BLGeometryType::BL_GEOMETRY_TYPE_ARRAY_VIEW_BOXI,
+                &raw const view as _,
+            )
+        })
+    }
+    /// Fills the current fill style through the alpha of the `area` of the
+    /// A8 `mask`, or the whole mask if `None`, placed at `origin`.
+    #[inline]
+    pub fn fill_mask<'r, R>(&mut self, origin: PointI, mask: &Image, area: R) -> Result<(), Error>
+    where
+        R: Into<Option<&'r RectI>>,
+    {
+        err_to_result(unsafe {
+            ffi::bl_context_fill_mask_i(
+                &mut self.0,
+                &raw const origin as _,
+                &mask.0,
+                area.into().map_or(null(), |a| a as *const _ as _),
             )
         })
     }
@@ -134,14 +295,65 @@ impl Context {
             )
         })
     }
+    // Blit
+    /// Blits the `area` of `image`, or the whole image if `None`, at `origin`
+    /// in user space.
+    #[inline]
+    pub fn blit_image<'r, R>(&mut self, origin: PointI, image: &Image, area: R) -> Result<(), Error>
+    where
+        R: Into<Option<&'r RectI>>,
+    {
+        err_to_result(unsafe {
+            ffi::bl_context_blit_image_i(
+                &mut self.0,
+                &raw const origin as _,
+                &image.0,
+                area.into().map_or(null(), |a| a as *const _ as _),
+            )
+        })
+    }
+    /// Blits the pixels of the sub-image view at `origin` in user space.
+    #[inline]
+    pub fn blit_sub_image(&mut self, origin: PointI, image: &SubImage<'_>) -> Result<(), Error> {
+        self.blit_image(origin, image.source(), &image.area())
+    }
     // Stroke
+    /// Returns a copy of the stroke options, including the stroke width,
+    /// caps, join and dashes.
+    #[inline]
+    pub fn stroke_options(&self) -> Result<StrokeOptions, Error> {
+        let mut options = StrokeOptions::new();
+        err_to_result(unsafe { ffi::bl_context_get_stroke_options(&self.0, &raw mut options as _) })
+            .map(|_| options)
+    }
+    #[inline]
+    pub fn set_stroke_options(&mut self, options: &StrokeOptions) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_set_stroke_options(&mut self.0, options as *const _ as _)
+        })
+    }
     #[inline]
     pub fn set_stroke_width(&mut self, width: f64) -> Result<(), Error> {
         err_to_result(unsafe { ffi::bl_context_set_stroke_width(&mut self.0, width) })
     }
     #[inline]
-    pub fn set_stroke_style_rgba32(&mut self, rgba32: u32) -> Result<(), Error> {
-        err_to_result(unsafe { ffi::bl_context_set_stroke_style_rgba32(&mut self.0, rgba32) })
+    pub fn set_stroke_style_rgba32(&mut self, rgba32: impl Into<Rgba32>) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_set_stroke_style_rgba32(&mut self.0, rgba32.into().value)
+        })
+    }
+    #[inline]
+    pub fn set_stroke_style_rgba64(&mut self, rgba64: impl Into<Rgba64>) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_set_stroke_style_rgba64(&mut self.0, rgba64.into().value)
+        })
+    }
+    #[inline]
+    pub fn set_stroke_style_rgba(&mut self, rgba: impl Into<Rgba>) -> Result<(), Error> {
+        let rgba = rgba.into();
+        err_to_result(unsafe {
+            ffi::bl_context_set_stroke_style_rgba(&mut self.0, &raw const rgba as _)
+        })
     }
     #[inline]
     pub fn set_stroke_style_gradient(&mut self, gradient: &Gradient) -> Result<(), Error> {
@@ -179,7 +391,75 @@ impl Context {
             )
         })
     }
+    // Clip
+    /// Restricts rendering to the given rectangle, intersected with the
+    /// current clip.
+    #[inline]
+    pub fn clip_to_rect(&mut self, rect: &RectI) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_clip_to_rect_i(&mut self.0, rect as *const _ as _) })
+    }
+    /// Restores the clip to the state of the last saved context state.
+    #[inline]
+    pub fn restore_clipping(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_restore_clipping(&mut self.0) })
+    }
     // Transform
+    /// The meta transform, which maps the user transform into device pixels.
+    #[inline]
+    pub fn meta_transform(&self) -> Result<Matrix2D, Error> {
+        let mut transform = Matrix2D::identity();
+        err_to_result(unsafe {
+            ffi::bl_context_get_meta_transform(&self.0, transform.as_ffi_mut())
+        })
+        .map(|_| transform)
+    }
+    /// The user transform set by [`Context::set_transform`] and friends.
+    #[inline]
+    pub fn user_transform(&self) -> Result<Matrix2D, Error> {
+        let mut transform = Matrix2D::identity();
+        err_to_result(unsafe {
+            ffi::bl_context_get_user_transform(&self.0, transform.as_ffi_mut())
+        })
+        .map(|_| transform)
+    }
+    /// Combines the user transform into the meta transform and resets the
+    /// user transform to identity.
+    #[inline]
+    pub fn user_to_meta(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_context_user_to_meta(&mut self.0) })
+    }
+    /// The final transform, combining the meta and user transforms, that maps
+    /// user space into device pixels.
+    #[inline]
+    pub fn final_transform(&self) -> Result<Matrix2D, Error> {
+        let mut transform = Matrix2D::identity();
+        err_to_result(unsafe {
+            ffi::bl_context_get_final_transform(&self.0, transform.as_ffi_mut())
+        })
+        .map(|_| transform)
+    }
+    /// Replaces the user transform.
+    #[inline]
+    pub fn set_transform(&mut self, transform: &Matrix2D) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_apply_transform_op(
+                &mut self.0,
+                BLTransformOp::BL_TRANSFORM_OP_ASSIGN,
+                transform.as_ffi() as _,
+            )
+        })
+    }
+    /// Resets the user transform to identity.
+    #[inline]
+    pub fn reset_transform(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_context_apply_transform_op(
+                &mut self.0,
+                BLTransformOp::BL_TRANSFORM_OP_RESET,
+                null(),
+            )
+        })
+    }
     #[inline]
     pub fn rotate_around(&mut self, angle: f64, x: f64, y: f64) -> Result<(), Error> {
         err_to_result(unsafe {
@@ -199,7 +479,7 @@ impl Default for Context {
         let mut ctx = std::mem::MaybeUninit::<ffi::BLContextCore>::uninit();
         unsafe {
             ffi::bl_context_init(ctx.as_mut_ptr());
-            Context(ctx.assume_init())
+            Context(ctx.assume_init(), LayerStack::default())
         }
     }
 }
@@ -212,3 +492,29 @@ impl Drop for Context {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::layer::tests::{RED, pixel, render};
+    use super::*;
+    use crate::geometry::BoxI;
+
+    #[test]
+    fn test_fill_region() {
+        let region = Region::from_boxes(&[BoxI::new(0, 0, 1, 2), BoxI::new(3, 2, 4, 4)]);
+        let image = render(|ctx| {
+            ctx.set_fill_style_rgba32(RED)?;
+            ctx.fill_region(&region)
+        });
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 0, 1), RED);
+        assert_eq!(pixel(&image, 3, 2), RED);
+        assert_eq!(pixel(&image, 3, 3), RED);
+        // Neither the gap between the boxes nor the rest of their bounding
+        // box is filled.
+        assert_eq!(pixel(&image, 1, 1), 0);
+        assert_eq!(pixel(&image, 2, 2), 0);
+        assert_eq!(pixel(&image, 3, 0), 0);
+        assert_eq!(pixel(&image, 0, 3), 0);
+    }
+}
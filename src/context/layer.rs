@@ -0,0 +1,650 @@
+//! Offscreen layers for group opacity, blending and path and region
+//! clipping.
+
+use std::ffi::c_void;
+use std::mem::{self, MaybeUninit};
+
+use crate::context::{CompOp, Context};
+use crate::geometry::{self, BoxI, FillRule, Matrix2D, MatrixType, Point, PointI, RectI};
+use crate::image::{Format, Image};
+use crate::path::Path;
+use crate::region::Region;
+use crate::{Error, err_to_result};
+
+/// Maximum number of released layer images kept for reuse.
+const MAX_POOLED_IMAGES: usize = 4;
+
+struct Layer {
+    /// While the layer is pushed this holds the state of the parent context.
+    context: Context,
+    image: Image,
+    /// Bounds of the layer in the device pixels of the root target.
+    bounds: RectI,
+    composite: Composite,
+}
+
+/// How a layer is composited onto its parent when popped.
+enum Composite {
+    /// Blended like an image with the given opacity and comp op.
+    Group { opacity: f64, comp_op: CompOp },
+    /// The layer starts out as a copy of the parent and replaces it inside of
+    /// the A8 mask.
+    Clip { mask: Image },
+}
+
+#[derive(Default)]
+pub(super) struct LayerStack {
+    layers: Vec<Layer>,
+    pool: Vec<Image>,
+}
+
+impl LayerStack {
+    /// Takes a pooled image of the given format that is at least `w` by `h`
+    /// pixels large or creates a new one.
+    pub(super) fn take_image(&mut self, w: i32, h: i32, format: Format) -> Result<Image, Error> {
+        let fits = |image: &Image| {
+            let size = image.size();
+            size.w >= w && size.h >= h && image.format() == format
+        };
+        match self.pool.iter().position(fits) {
+            Some(index) => Ok(self.pool.swap_remove(index)),
+            None => Image::new(w, h, format),
+        }
+    }
+
+    pub(super) fn release_image(&mut self, image: Image) {
+        if self.pool.len() < MAX_POOLED_IMAGES {
+            self.pool.push(image);
+        }
+    }
+}
+
+/// Begins rendering into `image`, cleared and clipped to `w` by `h` pixels.
+pub(super) fn begin_cleared(image: &mut Image, w: i32, h: i32) -> Result<Context, Error> {
+    let mut context = Context::default();
+    context.begin(image)?;
+    err_to_result(unsafe { ffi::bl_context_clear_all(&mut context.0) })?;
+    // A pooled image may be larger than requested.
+    context.clip_to_rect(&RectI::new(0, 0, w, h))?;
+    Ok(context)
+}
+
+/// Returns the bounding box of `b` mapped through `transform`.
+fn device_bounds(b: geometry::Box, transform: &Matrix2D) -> geometry::Box {
+    let mut corners = [
+        Point::new(b.x0, b.y0),
+        Point::new(b.x1, b.y0),
+        Point::new(b.x0, b.y1),
+        Point::new(b.x1, b.y1),
+    ];
+    transform.map_points(&mut corners);
+    let (xs, ys) = (corners.map(|p| p.x), corners.map(|p| p.y));
+    geometry::Box::new(
+        xs.into_iter().fold(f64::INFINITY, f64::min),
+        ys.into_iter().fold(f64::INFINITY, f64::min),
+        xs.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        ys.into_iter().fold(f64::NEG_INFINITY, f64::max),
+    )
+}
+
+/// Copies the fill and stroke styles of `from` to `to`.
+fn copy_styles(from: &Context, to: &mut Context) -> Result<(), Error> {
+    let mut style = MaybeUninit::<ffi::BLVarCore>::uninit();
+    unsafe {
+        ffi::bl_var_init_null(style.as_mut_ptr() as *mut c_void);
+        let style = style.as_mut_ptr();
+        let result = err_to_result(ffi::bl_context_get_fill_style(&from.0, style as _))
+            .and_then(|_| err_to_result(ffi::bl_context_set_fill_style(&mut to.0, style as _)))
+            .and_then(|_| err_to_result(ffi::bl_context_get_stroke_style(&from.0, style as _)))
+            .and_then(|_| err_to_result(ffi::bl_context_set_stroke_style(&mut to.0, style as _)));
+        ffi::bl_var_destroy(style as *mut c_void);
+        result
+    }
+}
+
+impl Context {
+    /// Starts rendering into an offscreen layer covering `bounds` in device
+    /// pixels.
+    ///
+    /// Everything rendered until the matching [`Context::pop_layer`] goes
+    /// into the layer, which is then composited onto the parent with the
+    /// given `opacity` and `comp_op`. The layer starts out transparent and
+    /// inherits the fill and stroke styles, stroke options, fill rule and
+    /// transform of the parent. Inside the layer the comp op is
+    /// [`CompOp::SrcOver`] and the global alpha is `1.0`, the parent's are
+    /// replaced by `comp_op` and `opacity`. The parent's clip is applied
+    /// when the layer is composited.
+    ///
+    /// The layer has its own rendering state, so [`Context::save`] and
+    /// [`Context::restore`] calls have to be balanced between pushing and
+    /// popping it. The bounds of nested layers are given in the device pixels
+    /// of the image the context was begun on as well.
+    pub fn push_layer(
+        &mut self,
+        bounds: RectI,
+        opacity: f64,
+        comp_op: CompOp,
+    ) -> Result<(), Error> {
+        if bounds.w <= 0 || bounds.h <= 0 {
+            return Err(Error);
+        }
+        let origin = self.layer_origin();
+        let local = RectI::new(bounds.x - origin.x, bounds.y - origin.y, bounds.w, bounds.h);
+        self.push_local_layer(local, Composite::Group { opacity, comp_op })
+    }
+
+    /// Restricts rendering to the inside of `path`, using `fill_rule`, until
+    /// the matching [`Context::pop_layer`].
+    ///
+    /// The path is rasterized with the current transform into an A8 mask.
+    /// Drawing goes into a layer covering the path that starts out as a copy
+    /// of the parent and keeps its whole rendering state, including the comp
+    /// op and global alpha, so everything renders as it would without the
+    /// clip. When popped, the layer replaces the parent inside of the mask,
+    /// so the clip is anti-aliased. Like with [`Context::push_layer`],
+    /// [`Context::save`] and [`Context::restore`] calls have to be balanced
+    /// between clipping and popping.
+    pub fn clip_to_path(&mut self, path: &Path, fill_rule: FillRule) -> Result<(), Error> {
+        let bounds = self.device_path(path)?.bounding_box().ok();
+        self.push_clip_layer(bounds, |mask_context| {
+            mask_context.set_fill_rule(fill_rule)?;
+            mask_context.fill_path_rgba32(path, 0xFFFFFFFF)
+        })
+    }
+
+    /// Renders `f` clipped to `path`, see [`Context::clip_to_path`].
+    ///
+    /// The clip is removed even if `f` fails.
+    pub fn with_clip_path<F>(&mut self, path: &Path, fill_rule: FillRule, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Context) -> Result<(), Error>,
+    {
+        self.clip_to_path(path, fill_rule)?;
+        let result = f(self);
+        let popped = self.pop_layer();
+        result.and(popped)
+    }
+
+    /// Restricts rendering to the boxes of `region`, transformed by the
+    /// current transform, until the matching [`Context::pop_layer`].
+    ///
+    /// Works like [`Context::clip_to_path`], the boxes are rasterized into
+    /// the A8 mask of a clip layer, so pixels between the boxes of a complex
+    /// region are left untouched. To clip to a single rectangle without a
+    /// layer use [`Context::clip_to_rect`].
+    pub fn clip_to_region(&mut self, region: &Region) -> Result<(), Error> {
+        let bounds = match region.is_empty() {
+            true => None,
+            false => Some(device_bounds(
+                geometry::Box::from(region.bounding_box()),
+                &self.final_transform()?,
+            )),
+        };
+        self.push_clip_layer(bounds, |mask_context| {
+            mask_context.set_fill_style_rgba32(0xFFFFFFFF)?;
+            mask_context.fill_region(region)
+        })
+    }
+
+    /// Renders `f` clipped to `region`, see [`Context::clip_to_region`].
+    ///
+    /// A region of a single box under a transform that keeps rectangles
+    /// axis-aligned is clipped with [`Context::clip_to_rect`] between a
+    /// [`Context::save`] and [`Context::restore`] instead of a layer. Either
+    /// way the clip is removed even if `f` fails.
+    pub fn with_clip_region<F>(&mut self, region: &Region, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Context) -> Result<(), Error>,
+    {
+        let rectilinear = matches!(
+            self.final_transform()?.matrix_type(),
+            MatrixType::Identity | MatrixType::Translate | MatrixType::Scale | MatrixType::Swap
+        );
+        if region.is_rect() && rectilinear {
+            self.save()?;
+            let result = self
+                .clip_to_rect(&RectI::from(region.bounding_box()))
+                .and_then(|_| f(self));
+            let restored = self.restore();
+            return result.and(restored);
+        }
+        self.clip_to_region(region)?;
+        let result = f(self);
+        let popped = self.pop_layer();
+        result.and(popped)
+    }
+
+    /// Pushes a clip layer covering `bounds`, given in the device pixels of
+    /// the current target and cut to it, whose A8 mask `draw` renders with
+    /// the current transform.
+    fn push_clip_layer<F>(&mut self, bounds: Option<geometry::Box>, draw: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Context) -> Result<(), Error>,
+    {
+        let target = self.target_size()?.round();
+        let bounds = bounds
+            .and_then(|b| {
+                b.round_out()
+                    .intersect(&BoxI::new(0, 0, target.w, target.h))
+            })
+            .filter(|b| !b.is_empty())
+            .map(RectI::from)
+            // Everything is clipped away, an empty mask keeps the layer
+            // invisible.
+            .unwrap_or(RectI::new(0, 0, 1, 1));
+        let transform =
+            self.final_transform()? * Matrix2D::translation(-bounds.x as f64, -bounds.y as f64);
+
+        let mut mask = self.1.take_image(bounds.w, bounds.h, Format::A8)?;
+        let result = (|| {
+            let mut mask_context = begin_cleared(&mut mask, bounds.w, bounds.h)?;
+            mask_context.set_transform(&transform)?;
+            draw(&mut mask_context)?;
+            mask_context.end()
+        })();
+        if let Err(e) = result {
+            self.1.release_image(mask);
+            return Err(e);
+        }
+
+        self.push_local_layer(bounds, Composite::Clip { mask })
+    }
+
+    /// Returns `path` transformed into the device pixels of the current
+    /// target.
+    pub(super) fn device_path(&self, path: &Path) -> Result<Path, Error> {
+        let mut device_path = Path::default();
+        device_path.add_path(path, ..)?;
+        device_path.transform(&self.final_transform()?, ..)?;
+        Ok(device_path)
+    }
+
+    /// Sets the user transform so that user space maps to device pixels
+    /// regardless of the meta transform.
+    pub(super) fn reset_final_transform(&mut self) -> Result<(), Error> {
+        let meta = self.meta_transform()?;
+        self.set_transform(&meta.invert().ok_or(Error)?)
+    }
+
+    /// Device pixel offset of the current target relative to the root target.
+    fn layer_origin(&self) -> PointI {
+        self.1
+            .layers
+            .last()
+            .map_or(PointI::new(0, 0), |l| PointI::new(l.bounds.x, l.bounds.y))
+    }
+
+    /// Pushes a layer whose bounds are relative to the current target.
+    fn push_local_layer(&mut self, local: RectI, composite: Composite) -> Result<(), Error> {
+        let mut image = match self.1.take_image(local.w, local.h, Format::PRgb32) {
+            Ok(image) => image,
+            Err(e) => {
+                if let Composite::Clip { mask } = composite {
+                    self.1.release_image(mask);
+                }
+                return Err(e);
+            },
+        };
+        let mut context = match self.begin_layer(&mut image, local, &composite) {
+            Ok(context) => context,
+            Err(e) => {
+                self.1.release_image(image);
+                if let Composite::Clip { mask } = composite {
+                    self.1.release_image(mask);
+                }
+                return Err(e);
+            },
+        };
+
+        let origin = self.layer_origin();
+        mem::swap(&mut self.0, &mut context.0);
+        self.1.layers.push(Layer {
+            context,
+            image,
+            bounds: RectI::new(local.x + origin.x, local.y + origin.y, local.w, local.h),
+            composite,
+        });
+        Ok(())
+    }
+
+    /// Begins rendering into the layer `image` covering `local` and copies
+    /// the rendering state, and for clip layers the pixels, of this context.
+    fn begin_layer(
+        &mut self,
+        image: &mut Image,
+        local: RectI,
+        composite: &Composite,
+    ) -> Result<Context, Error> {
+        let mut context = begin_cleared(image, local.w, local.h)?;
+        let offset = Matrix2D::translation(-local.x as f64, -local.y as f64);
+
+        if let Composite::Clip { .. } = composite {
+            err_to_result(unsafe {
+                ffi::bl_context_flush(&mut self.0, ffi::BLContextFlushFlags::BL_CONTEXT_FLUSH_SYNC)
+            })?;
+            // The target stays owned by the parent context, `Image` is a
+            // transparent wrapper of its core.
+            let target = unsafe {
+                &*(ffi::bl_context_get_target_image(&self.0) as *const ffi::BLImageCore
+                    as *const Image)
+            };
+            context.set_comp_op(CompOp::SrcCopy)?;
+            context.blit_image(PointI::new(-local.x, -local.y), target, None)?;
+            context.set_comp_op(self.comp_op())?;
+            context.set_global_alpha(self.global_alpha())?;
+        }
+
+        // Keep the split between the meta and user transform, so resetting
+        // the transform in the layer behaves like it does in the parent.
+        context.set_transform(&(self.meta_transform()? * offset))?;
+        context.user_to_meta()?;
+        context.set_transform(&self.user_transform()?)?;
+
+        copy_styles(self, &mut context)?;
+        context.set_stroke_options(&self.stroke_options()?)?;
+        context.set_fill_rule(self.fill_rule())?;
+        Ok(context)
+    }
+
+    /// Finishes the innermost layer, started by [`Context::push_layer`],
+    /// [`Context::clip_to_path`] or [`Context::clip_to_region`], and
+    /// composites it onto the parent.
+    ///
+    /// Fails if no layer is pushed.
+    pub fn pop_layer(&mut self) -> Result<(), Error> {
+        let Layer {
+            mut context,
+            mut image,
+            bounds,
+            composite,
+        } = self.1.layers.pop().ok_or(Error)?;
+        mem::swap(&mut self.0, &mut context.0);
+        let result = context
+            .end()
+            .and_then(|_| self.composite_layer(&mut image, bounds, &composite));
+
+        if let Composite::Clip { mask } = composite {
+            self.1.release_image(mask);
+        }
+        self.1.release_image(image);
+        result
+    }
+
+    /// Composites the finished layer `image` covering `bounds` onto the
+    /// current target.
+    fn composite_layer(
+        &mut self,
+        image: &mut Image,
+        bounds: RectI,
+        composite: &Composite,
+    ) -> Result<(), Error> {
+        let origin = self.layer_origin();
+        let at = PointI::new(bounds.x - origin.x, bounds.y - origin.y);
+        let area = RectI::new(0, 0, bounds.w, bounds.h);
+
+        if let Composite::Clip { mask } = composite {
+            let mut mask_context = Context::default();
+            mask_context.begin(image)?;
+            mask_context.set_comp_op(CompOp::DstIn)?;
+            mask_context.fill_mask(PointI::new(0, 0), mask, &area)?;
+            mask_context.end()?;
+        }
+
+        self.save()?;
+        let result = self.reset_final_transform().and_then(|_| match composite {
+            Composite::Group { opacity, comp_op } => {
+                self.set_comp_op(*comp_op)?;
+                self.set_global_alpha(*opacity)?;
+                self.blit_image(at, image, &area)
+            },
+            Composite::Clip { mask } => {
+                // parent * (1 - mask) + layer * mask
+                self.set_global_alpha(1.0)?;
+                self.set_comp_op(CompOp::DstOut)?;
+                self.set_fill_style_rgba32(0xFFFFFFFF)?;
+                self.fill_mask(at, mask, &area)?;
+                self.set_comp_op(CompOp::Plus)?;
+                self.blit_image(at, image, &area)
+            },
+        });
+        let restored = self.restore();
+        result.and(restored)
+    }
+
+    /// Renders `f` into a layer, see [`Context::push_layer`].
+    ///
+    /// The layer is popped even if `f` fails.
+    pub fn with_layer<F>(
+        &mut self,
+        bounds: RectI,
+        opacity: f64,
+        comp_op: CompOp,
+        f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Context) -> Result<(), Error>,
+    {
+        self.push_layer(bounds, opacity, comp_op)?;
+        let result = f(self);
+        let popped = self.pop_layer();
+        result.and(popped)
+    }
+
+    /// The number of currently pushed layers.
+    #[inline]
+    pub fn layer_count(&self) -> usize {
+        self.1.layers.len()
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+
+    pub(in crate::context) const RED: u32 = 0xFFFF0000;
+    pub(in crate::context) const BLUE: u32 = 0xFF0000FF;
+
+    pub(in crate::context) fn pixel(image: &Image, x: usize, y: i32) -> u32 {
+        let row = image.row(y).unwrap();
+        u32::from_ne_bytes(row[x * 4..x * 4 + 4].try_into().unwrap())
+    }
+
+    pub(in crate::context) fn render(f: impl FnOnce(&mut Context) -> Result<(), Error>) -> Image {
+        let mut image = Image::new(4, 4, Format::PRgb32).unwrap();
+        let mut context = begin_cleared(&mut image, 4, 4).unwrap();
+        f(&mut context).unwrap();
+        context.end().unwrap();
+        image
+    }
+
+    #[test]
+    fn test_layer_opacity() {
+        let image = render(|ctx| {
+            ctx.with_layer(RectI::new(0, 0, 2, 4), 0.5, CompOp::SrcOver, |ctx| {
+                ctx.fill_all_rgba32(RED)
+            })
+        });
+        let alpha = pixel(&image, 0, 0) >> 24;
+        assert!((127..=128).contains(&alpha));
+        assert_eq!(pixel(&image, 1, 3) >> 24, alpha);
+        assert_eq!(pixel(&image, 2, 0), 0);
+    }
+
+    #[test]
+    fn test_layer_comp_op() {
+        let image = render(|ctx| {
+            ctx.fill_all_rgba32(BLUE)?;
+            ctx.push_layer(RectI::new(0, 0, 4, 4), 1.0, CompOp::SrcCopy)?;
+            ctx.set_fill_style_rgba32(RED)?;
+            ctx.fill_rect(&Rect::new(0.0, 0.0, 2.0, 4.0))?;
+            ctx.pop_layer()
+        });
+        // The transparent half of the layer replaces the parent as well.
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 3, 3), 0);
+    }
+
+    #[test]
+    fn test_nested_layers() {
+        let image = render(|ctx| {
+            ctx.push_layer(RectI::new(1, 1, 3, 3), 1.0, CompOp::SrcOver)?;
+            ctx.push_layer(RectI::new(2, 2, 2, 2), 1.0, CompOp::SrcOver)?;
+            assert_eq!(ctx.layer_count(), 2);
+            ctx.fill_all_rgba32(RED)?;
+            ctx.pop_layer()?;
+            ctx.pop_layer()?;
+            assert!(ctx.pop_layer().is_err());
+            Ok(())
+        });
+        assert_eq!(pixel(&image, 2, 2), RED);
+        assert_eq!(pixel(&image, 3, 3), RED);
+        assert_eq!(pixel(&image, 1, 1), 0);
+    }
+
+    #[test]
+    fn test_layer_inherits_state() {
+        let image = render(|ctx| {
+            ctx.set_fill_style_rgba32(RED)?;
+            ctx.set_fill_rule(FillRule::EvenOdd)?;
+            ctx.set_stroke_width(3.0)?;
+            ctx.set_comp_op(CompOp::Multiply)?;
+            ctx.set_transform(&Matrix2D::translation(1.0, 0.0))?;
+            ctx.with_layer(RectI::new(0, 0, 4, 4), 1.0, CompOp::SrcOver, |ctx| {
+                assert_eq!(ctx.fill_rule(), FillRule::EvenOdd);
+                assert_eq!(ctx.stroke_options()?.width(), 3.0);
+                assert_eq!(ctx.comp_op(), CompOp::SrcOver);
+                assert_eq!(ctx.user_transform()?, Matrix2D::translation(1.0, 0.0));
+                ctx.fill_rect(&Rect::new(0.0, 0.0, 1.0, 1.0))
+            })?;
+            assert_eq!(ctx.comp_op(), CompOp::Multiply);
+            Ok(())
+        });
+        assert_eq!(pixel(&image, 1, 0), RED);
+        assert_eq!(pixel(&image, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_layer_meta_transform() {
+        let image = render(|ctx| {
+            ctx.set_transform(&Matrix2D::translation(2.0, 0.0))?;
+            ctx.user_to_meta()?;
+            ctx.with_layer(RectI::new(2, 0, 2, 4), 1.0, CompOp::SrcOver, |ctx| {
+                ctx.fill_all_rgba32(RED)
+            })
+        });
+        assert_eq!(pixel(&image, 2, 0), RED);
+        assert_eq!(pixel(&image, 3, 3), RED);
+        assert_eq!(pixel(&image, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_begin_with_layers() {
+        let mut image = Image::new(4, 4, Format::PRgb32).unwrap();
+        let mut other = Image::new(4, 4, Format::PRgb32).unwrap();
+        let mut context = begin_cleared(&mut image, 4, 4).unwrap();
+        context
+            .push_layer(RectI::new(0, 0, 4, 4), 1.0, CompOp::SrcOver)
+            .unwrap();
+        assert!(context.begin(&mut other).is_err());
+        assert_eq!(context.layer_count(), 1);
+        context.end().unwrap();
+        assert_eq!(context.layer_count(), 0);
+    }
+
+    pub(in crate::context) fn rect_path(x: f64, y: f64, w: f64, h: f64) -> Path {
+        let mut path = Path::default();
+        path.move_to(x, y).unwrap();
+        path.line_to(x + w, y).unwrap();
+        path.line_to(x + w, y + h).unwrap();
+        path.line_to(x, y + h).unwrap();
+        path.close().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_clip_to_path() {
+        let image = render(|ctx| {
+            ctx.fill_all_rgba32(BLUE)?;
+            ctx.with_clip_path(&rect_path(0.0, 0.0, 2.0, 4.0), FillRule::NonZero, |ctx| {
+                ctx.fill_all_rgba32(RED)
+            })
+        });
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 1, 3), RED);
+        assert_eq!(pixel(&image, 2, 0), BLUE);
+        assert_eq!(pixel(&image, 3, 3), BLUE);
+    }
+
+    #[test]
+    fn test_clip_keeps_state() {
+        let image = render(|ctx| {
+            ctx.fill_all_rgba32(BLUE)?;
+            ctx.set_fill_style_rgba32(RED)?;
+            ctx.set_fill_rule(FillRule::EvenOdd)?;
+            ctx.set_stroke_width(3.0)?;
+            ctx.set_global_alpha(0.5)?;
+            ctx.set_transform(&Matrix2D::translation(1.0, 0.0))?;
+            ctx.clip_to_path(&rect_path(0.0, 0.0, 2.0, 2.0), FillRule::NonZero)?;
+            assert_eq!(ctx.fill_rule(), FillRule::EvenOdd);
+            assert_eq!(ctx.stroke_options()?.width(), 3.0);
+            assert_eq!(ctx.global_alpha(), 0.5);
+            ctx.set_global_alpha(1.0)?;
+            ctx.fill_all()?;
+            ctx.pop_layer()?;
+
+            // Comp ops blend against the parent, not a transparent layer.
+            ctx.set_comp_op(CompOp::DstOut)?;
+            ctx.clip_to_path(&rect_path(0.0, 2.0, 2.0, 2.0), FillRule::NonZero)?;
+            assert_eq!(ctx.comp_op(), CompOp::DstOut);
+            ctx.fill_all()?;
+            ctx.pop_layer()
+        });
+        assert_eq!(pixel(&image, 0, 0), BLUE);
+        assert_eq!(pixel(&image, 1, 0), RED);
+        assert_eq!(pixel(&image, 2, 1), RED);
+        assert_eq!(pixel(&image, 3, 1), BLUE);
+        assert_eq!(pixel(&image, 0, 2), BLUE);
+        assert_eq!(pixel(&image, 1, 2), 0);
+        assert_eq!(pixel(&image, 2, 3), 0);
+        assert_eq!(pixel(&image, 3, 3), BLUE);
+    }
+
+    #[test]
+    fn test_clip_to_region() {
+        let region = Region::from_boxes(&[BoxI::new(0, 0, 1, 4), BoxI::new(3, 0, 4, 4)]);
+        let image = render(|ctx| {
+            ctx.fill_all_rgba32(BLUE)?;
+            ctx.with_clip_region(&region, |ctx| ctx.fill_all_rgba32(RED))?;
+            ctx.set_transform(&Matrix2D::translation(1.0, 0.0))?;
+            ctx.clip_to_region(&Region::from_boxes(&[BoxI::new(0, 0, 1, 1)]))?;
+            ctx.fill_all_rgba32(0xFF00FF00)?;
+            ctx.pop_layer()
+        });
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 3, 3), RED);
+        // The gap between the boxes isn't painted.
+        assert_eq!(pixel(&image, 1, 1), BLUE);
+        assert_eq!(pixel(&image, 2, 3), BLUE);
+        assert_eq!(pixel(&image, 1, 0), 0xFF00FF00);
+        assert_eq!(pixel(&image, 2, 0), BLUE);
+    }
+
+    #[test]
+    fn test_clip_to_single_box_region() {
+        let region = Region::from_boxes(&[BoxI::new(1, 1, 3, 3)]);
+        let image = render(|ctx| {
+            ctx.with_clip_region(&region, |ctx| {
+                assert_eq!(ctx.layer_count(), 0);
+                ctx.fill_all_rgba32(RED)
+            })?;
+            // The clip is gone afterwards.
+            ctx.set_fill_style_rgba32(BLUE)?;
+            ctx.fill_rect(&Rect::new(0.0, 0.0, 1.0, 1.0))
+        });
+        assert_eq!(pixel(&image, 1, 1), RED);
+        assert_eq!(pixel(&image, 2, 2), RED);
+        assert_eq!(pixel(&image, 3, 3), 0);
+        assert_eq!(pixel(&image, 0, 0), BLUE);
+    }
+}
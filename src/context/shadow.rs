@@ -0,0 +1,161 @@
+//! Drop shadows rendered through blurred A8 masks.
+
+use crate::context::Context;
+use crate::context::layer::begin_cleared;
+use crate::geometry::{BoxI, Matrix2D, Point, PointI, RectI, SizeI};
+use crate::image::{Format, Image};
+use crate::path::Path;
+use crate::{Error, Rgba32};
+
+impl Context {
+    /// Fills `path` with the current fill style on top of a blurred shadow of
+    /// its shape.
+    ///
+    /// The shadow is offset by `offset` device pixels and, like the
+    /// `shadowBlur` of an HTML canvas, blurred with a Gaussian of standard
+    /// deviation `blur / 2`.
+    pub fn fill_with_shadow(
+        &mut self,
+        path: &Path,
+        offset: Point,
+        blur: f64,
+        color: impl Into<Rgba32>,
+    ) -> Result<(), Error> {
+        let sigma = blur.max(0.0) / 2.0;
+        // Pixels just outside the target still blur into it, the blur is cut
+        // off at the size of the target.
+        let target = self.target_size()?.round();
+        let pad = ((sigma * 3.0).ceil() as i32).min(target.w.max(target.h)) + 1;
+        let mut shadow_path = self.device_path(path)?;
+        shadow_path.translate(offset, ..)?;
+
+        let bounds = shadow_path
+            .bounding_box()
+            .ok()
+            .map(|b| b.round_out().inflate(pad, pad))
+            .and_then(|b| b.intersect(&BoxI::new(-pad, -pad, target.w + pad, target.h + pad)))
+            .filter(|b| !b.is_empty())
+            .map(RectI::from);
+
+        if let Some(bounds) = bounds {
+            let mut mask = self.1.take_image(bounds.w, bounds.h, Format::A8)?;
+            let result = self.fill_shadow(&mut mask, &shadow_path, bounds, sigma, color.into());
+            self.1.release_image(mask);
+            result?;
+        }
+        self.fill_path(path)
+    }
+
+    /// Rasterizes `shadow_path` into `mask`, blurs it and fills the shadow
+    /// color through it.
+    fn fill_shadow(
+        &mut self,
+        mask: &mut Image,
+        shadow_path: &Path,
+        bounds: RectI,
+        sigma: f64,
+        color: Rgba32,
+    ) -> Result<(), Error> {
+        let mut mask_context = begin_cleared(mask, bounds.w, bounds.h)?;
+        mask_context.set_transform(&Matrix2D::translation(-bounds.x as f64, -bounds.y as f64))?;
+        mask_context.fill_path_rgba32(shadow_path, 0xFFFFFFFF)?;
+        mask_context.end()?;
+        mask.plane(SizeI::new(bounds.w, bounds.h))?
+            .gaussian_blur(sigma);
+
+        let area = RectI::new(0, 0, bounds.w, bounds.h);
+        self.save()?;
+        let result = self
+            .reset_final_transform()
+            .and_then(|_| self.set_fill_style_rgba32(color))
+            .and_then(|_| self.fill_mask(PointI::new(bounds.x, bounds.y), mask, &area));
+        self.restore()?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::CompOp;
+    use crate::context::layer::tests::{BLUE, RED, pixel, rect_path, render};
+
+    fn fill_with_shadow(ctx: &mut Context, x: f64, y: f64, blur: f64) -> Result<(), Error> {
+        ctx.set_fill_style_rgba32(RED)?;
+        ctx.fill_with_shadow(&rect_path(0.0, 0.0, 2.0, 2.0), Point::new(x, y), blur, BLUE)
+    }
+
+    #[test]
+    fn test_shadow_offset() {
+        let image = render(|ctx| fill_with_shadow(ctx, 2.0, 2.0, 0.0));
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 1, 1), RED);
+        assert_eq!(pixel(&image, 2, 2), BLUE);
+        assert_eq!(pixel(&image, 3, 3), BLUE);
+        assert_eq!(pixel(&image, 2, 0), 0);
+        assert_eq!(pixel(&image, 0, 2), 0);
+    }
+
+    #[test]
+    fn test_shadow_under_shape() {
+        let image = render(|ctx| fill_with_shadow(ctx, 1.0, 1.0, 0.0));
+        assert_eq!(pixel(&image, 1, 1), RED);
+        assert_eq!(pixel(&image, 2, 1), BLUE);
+        assert_eq!(pixel(&image, 2, 2), BLUE);
+        assert_eq!(pixel(&image, 3, 3), 0);
+    }
+
+    #[test]
+    fn test_shadow_blur() {
+        let image = render(|ctx| fill_with_shadow(ctx, 2.0, 2.0, 2.0));
+        assert_eq!(pixel(&image, 0, 0), RED);
+        // The blur spreads the shadow beyond its shape and softens it.
+        let alpha = |x, y| pixel(&image, x, y) >> 24;
+        assert!((1..255).contains(&alpha(3, 3)));
+        assert!((1..255).contains(&alpha(2, 0)));
+        assert!(alpha(2, 2) > alpha(2, 0));
+    }
+
+    #[test]
+    fn test_shadow_outside_target() {
+        let shape = render(|ctx| {
+            ctx.set_fill_style_rgba32(RED)?;
+            ctx.fill_path(&rect_path(0.0, 0.0, 2.0, 2.0))
+        });
+        for offset in [10.0, -10.0, 1e9] {
+            let image = render(|ctx| fill_with_shadow(ctx, offset, offset, 1.0));
+            assert_eq!(image.pixels(), shape.pixels());
+        }
+    }
+
+    #[test]
+    fn test_shadow_meta_transform() {
+        let image = render(|ctx| {
+            ctx.set_transform(&Matrix2D::translation(1.0, 0.0))?;
+            ctx.user_to_meta()?;
+            // The offset is in device pixels, it isn't scaled.
+            ctx.set_transform(&Matrix2D::scaling(0.5, 0.5))?;
+            fill_with_shadow(ctx, 1.0, 2.0, 0.0)
+        });
+        assert_eq!(pixel(&image, 1, 0), RED);
+        assert_eq!(pixel(&image, 0, 0), 0);
+        assert_eq!(pixel(&image, 2, 2), BLUE);
+        assert_eq!(pixel(&image, 3, 2), 0);
+        assert_eq!(pixel(&image, 2, 1), 0);
+    }
+
+    #[test]
+    fn test_shadow_in_layer() {
+        let image = render(|ctx| {
+            ctx.with_layer(RectI::new(1, 1, 3, 3), 1.0, CompOp::SrcOver, |ctx| {
+                ctx.set_transform(&Matrix2D::translation(1.0, 1.0))?;
+                fill_with_shadow(ctx, 1.0, 1.0, 0.0)
+            })
+        });
+        assert_eq!(pixel(&image, 1, 1), RED);
+        assert_eq!(pixel(&image, 2, 2), RED);
+        assert_eq!(pixel(&image, 3, 3), BLUE);
+        assert_eq!(pixel(&image, 3, 1), 0);
+        assert_eq!(pixel(&image, 0, 0), 0);
+    }
+}
@@ -0,0 +1,292 @@
+//! File access through Blend2D's file and file system API.
+//!
+//! Blend2D's file system functions can memory-map files instead of reading
+//! them, see [`read_file`] and [`ReadFlags`].
+use std::os::raw::c_void;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{fmt, io, mem};
+
+use bitflags::bitflags;
+
+use crate::array::Array;
+use crate::util::path_to_c_string;
+use crate::{Error, err_to_result};
+
+use ffi::BLFileOpenFlags::*;
+bitflags! {
+    /// Flags for [`File::open`].
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct OpenFlags: u32 {
+        /// Opens the file for reading.
+        const READ              = BL_FILE_OPEN_READ as u32;
+        /// Opens the file for writing.
+        const WRITE             = BL_FILE_OPEN_WRITE as u32;
+        /// Opens the file for reading and writing.
+        const RW                = BL_FILE_OPEN_RW as u32;
+        /// Creates the file if it doesn't exist.
+        const CREATE            = BL_FILE_OPEN_CREATE as u32;
+        /// Opens the file for deleting or renaming (Windows).
+        const DELETE            = BL_FILE_OPEN_DELETE as u32;
+        /// Truncates the file to zero length.
+        const TRUNCATE          = BL_FILE_OPEN_TRUNCATE as u32;
+        /// Denies other processes to read the file (Windows).
+        const READ_EXCLUSIVE    = BL_FILE_OPEN_READ_EXCLUSIVE as u32;
+        /// Denies other processes to write the file (Windows).
+        const WRITE_EXCLUSIVE   = BL_FILE_OPEN_WRITE_EXCLUSIVE as u32;
+        /// Denies other processes to read and write the file (Windows).
+        const RW_EXCLUSIVE      = BL_FILE_OPEN_RW_EXCLUSIVE as u32;
+        /// Fails if the file already exists, requires `CREATE`.
+        const CREATE_EXCLUSIVE  = BL_FILE_OPEN_CREATE_EXCLUSIVE as u32;
+        /// Denies other processes to delete or rename the file (Windows).
+        const DELETE_EXCLUSIVE  = BL_FILE_OPEN_DELETE_EXCLUSIVE as u32;
+    }
+}
+
+use ffi::BLFileReadFlags::*;
+bitflags! {
+    /// Flags for [`read_file`].
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct ReadFlags: u32 {
+        /// Memory-maps the file if possible.
+        const MMAP_ENABLED     = BL_FILE_READ_MMAP_ENABLED as u32;
+        /// Reads small files instead of memory-mapping them.
+        const MMAP_AVOID_SMALL = BL_FILE_READ_MMAP_AVOID_SMALL as u32;
+        /// Fails if the file can't be memory-mapped instead of reading it.
+        const MMAP_NO_FALLBACK = BL_FILE_READ_MMAP_NO_FALLBACK as u32;
+    }
+}
+
+/// A file handle.
+///
+/// The file is closed when dropped.
+pub struct File(ffi::BLFileCore);
+
+impl File {
+    /// Opens the file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P, flags: OpenFlags) -> Result<File, Error> {
+        let path = path_to_c_string(path.as_ref())?;
+        let mut file = mem::MaybeUninit::<ffi::BLFileCore>::uninit();
+        unsafe {
+            ffi::bl_file_init(file.as_mut_ptr());
+            let mut file = File(file.assume_init());
+            err_to_result(ffi::bl_file_open(
+                &mut file.0,
+                path.as_ptr(),
+                flags.bits() as _,
+            ))?;
+            Ok(file)
+        }
+    }
+
+    /// Closes the file, reporting errors that dropping the file would ignore.
+    #[inline]
+    pub fn close(mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_file_close(&mut self.0) })
+    }
+
+    /// The size of the file in bytes.
+    #[inline]
+    pub fn size(&mut self) -> Result<u64, Error> {
+        let mut size = 0;
+        err_to_result(unsafe { ffi::bl_file_get_size(&mut self.0, &mut size) }).map(|_| size)
+    }
+
+    /// Truncates the file to at most `max_size` bytes.
+    #[inline]
+    pub fn truncate(&mut self, max_size: u64) -> Result<(), Error> {
+        let max_size = i64::try_from(max_size).map_err(|_| Error)?;
+        err_to_result(unsafe { ffi::bl_file_truncate(&mut self.0, max_size) })
+    }
+
+    fn seek_raw(&mut self, offset: i64, seek_type: ffi::BLFileSeekType::Type) -> io::Result<u64> {
+        let mut position = 0;
+        err_to_result(unsafe { ffi::bl_file_seek(&mut self.0, offset, seek_type, &mut position) })
+            .map_err(io_error)?;
+        Ok(position as u64)
+    }
+}
+
+fn io_error(_: Error) -> io::Error {
+    io::Error::other("blend2d file operation failed")
+}
+
+impl io::Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        err_to_result(unsafe {
+            ffi::bl_file_read(
+                &mut self.0,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                &mut n,
+            )
+        })
+        .map_err(io_error)?;
+        Ok(n)
+    }
+}
+
+impl io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut n = 0;
+        err_to_result(unsafe {
+            ffi::bl_file_write(
+                &mut self.0,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                &mut n,
+            )
+        })
+        .map_err(io_error)?;
+        Ok(n)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Seek for File {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        use ffi::BLFileSeekType::*;
+        match pos {
+            io::SeekFrom::Start(offset) => {
+                let offset = i64::try_from(offset).map_err(|_| io_error(Error))?;
+                self.seek_raw(offset, BL_FILE_SEEK_SET)
+            },
+            io::SeekFrom::Current(offset) => self.seek_raw(offset, BL_FILE_SEEK_CUR),
+            io::SeekFrom::End(offset) => self.seek_raw(offset, BL_FILE_SEEK_END),
+        }
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File")
+            .field("handle", &self.0.handle)
+            .finish()
+    }
+}
+
+impl Drop for File {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ffi::bl_file_reset(&mut self.0) };
+    }
+}
+
+/// Reads the whole file at `path`, memory-mapping it if requested by
+/// `flags`.
+///
+/// Fails if the file is larger than `max_size` bytes, zero means no limit.
+pub fn read_file<P: AsRef<Path>>(
+    path: P,
+    max_size: usize,
+    flags: ReadFlags,
+) -> Result<Array<u8>, Error> {
+    let path = path_to_c_string(path.as_ref())?;
+    let mut contents = Array::new();
+    err_to_result(unsafe {
+        ffi::bl_file_system_read_file(path.as_ptr(), &mut contents.0, max_size, flags.bits() as _)
+    })?;
+    Ok(contents)
+}
+
+/// Writes `data` to the file at `path`, replacing its contents.
+pub fn write_file<P: AsRef<Path>, R: AsRef<[u8]>>(path: P, data: R) -> Result<(), Error> {
+    let path = path_to_c_string(path.as_ref())?;
+    let data = data.as_ref();
+    let mut written = 0;
+    err_to_result(unsafe {
+        ffi::bl_file_system_write_file(
+            path.as_ptr(),
+            data.as_ptr() as *const c_void,
+            data.len(),
+            &mut written,
+        )
+    })?;
+    if written == data.len() {
+        Ok(())
+    } else {
+        Err(Error)
+    }
+}
+
+/// Writes `data` to the file at `path` atomically, so that readers see either
+/// the old or the new contents.
+///
+/// The data is written to a temporary file next to `path`, synced to disk
+/// and then replaces `path`. Every call uses a temporary file of its own, so
+/// concurrent writes to the same `path` don't interfere, the last one wins.
+/// The directory isn't synced, so the replacement itself may be lost on a
+/// crash.
+pub fn write_file_atomic<P: AsRef<Path>, R: AsRef<[u8]>>(path: P, data: R) -> Result<(), Error> {
+    static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = path.as_ref();
+    let mut tmp_name = path.file_name().ok_or(Error)?.to_owned();
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    tmp_name.push(format!(".{}.{}.tmp", std::process::id(), n));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    // Never touch a file this call didn't create.
+    let mut file = File::open(
+        &tmp_path,
+        OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::CREATE_EXCLUSIVE,
+    )?;
+    let result = (|| {
+        io::Write::write_all(&mut file, data.as_ref()).map_err(|_| Error)?;
+        file.close()?;
+        // Blend2D's file API can't flush to disk.
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&tmp_path)
+            .and_then(|f| f.sync_all())
+            .map_err(|_| Error)?;
+        std::fs::rename(&tmp_path, path).map_err(|_| Error)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_file_atomic() {
+        let dir = std::env::temp_dir().join(format!("blend2d-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        write_file_atomic(&path, b"first").unwrap();
+        write_file_atomic(&path, b"second").unwrap();
+        assert_eq!(
+            &*read_file(&path, 0, ReadFlags::MMAP_ENABLED).unwrap(),
+            b"second"
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_file_atomic_threads() {
+        let dir = std::env::temp_dir().join(format!("blend2d-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        std::thread::scope(|scope| {
+            for i in 0..8u8 {
+                let path = &path;
+                scope.spawn(move || write_file_atomic(path, [i; 64]).unwrap());
+            }
+        });
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len(), 64);
+        assert!(data.iter().all(|&b| b == data[0]));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,9 @@
+use std::ops;
+
 use ffi;
 
+use crate::err_to_result;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct PointI {
@@ -123,15 +127,587 @@ pub struct Triangle {
     pub y2: f64,
 }
 
+macro_rules! impl_vector_ops {
+    ($name:ident, $t:ty, $x:ident, $y:ident) => {
+        impl $name {
+            #[inline]
+            pub const fn new($x: $t, $y: $t) -> Self {
+                $name { $x, $y }
+            }
+        }
+
+        impl ops::Add for $name {
+            type Output = $name;
+
+            #[inline]
+            fn add(self, rhs: $name) -> $name {
+                $name::new(self.$x + rhs.$x, self.$y + rhs.$y)
+            }
+        }
+
+        impl ops::AddAssign for $name {
+            #[inline]
+            fn add_assign(&mut self, rhs: $name) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl ops::Sub for $name {
+            type Output = $name;
+
+            #[inline]
+            fn sub(self, rhs: $name) -> $name {
+                $name::new(self.$x - rhs.$x, self.$y - rhs.$y)
+            }
+        }
+
+        impl ops::SubAssign for $name {
+            #[inline]
+            fn sub_assign(&mut self, rhs: $name) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl ops::Mul<$t> for $name {
+            type Output = $name;
+
+            #[inline]
+            fn mul(self, rhs: $t) -> $name {
+                $name::new(self.$x * rhs, self.$y * rhs)
+            }
+        }
+
+        impl ops::MulAssign<$t> for $name {
+            #[inline]
+            fn mul_assign(&mut self, rhs: $t) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl ops::Neg for $name {
+            type Output = $name;
+
+            #[inline]
+            fn neg(self) -> $name {
+                $name::new(-self.$x, -self.$y)
+            }
+        }
+
+        impl From<($t, $t)> for $name {
+            #[inline]
+            fn from(($x, $y): ($t, $t)) -> Self {
+                $name { $x, $y }
+            }
+        }
+
+        impl From<$name> for ($t, $t) {
+            #[inline]
+            fn from(v: $name) -> Self {
+                (v.$x, v.$y)
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Point, f64, x, y);
+impl_vector_ops!(PointI, i32, x, y);
+impl_vector_ops!(Size, f64, w, h);
+impl_vector_ops!(SizeI, i32, w, h);
+
+macro_rules! impl_box_ops {
+    ($box:ident, $rect:ident, $point:ident, $t:ty) => {
+        impl $box {
+            #[inline]
+            pub const fn new(x0: $t, y0: $t, x1: $t, y1: $t) -> Self {
+                $box { x0, y0, x1, y1 }
+            }
+
+            #[inline]
+            pub fn width(&self) -> $t {
+                self.x1 - self.x0
+            }
+
+            #[inline]
+            pub fn height(&self) -> $t {
+                self.y1 - self.y0
+            }
+
+            /// Returns whether the box has no area.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                !(self.x0 < self.x1 && self.y0 < self.y1)
+            }
+
+            /// Returns whether `p` lies within the box, the right and bottom
+            /// edges are exclusive.
+            #[inline]
+            pub fn contains(&self, p: $point) -> bool {
+                p.x >= self.x0 && p.y >= self.y0 && p.x < self.x1 && p.y < self.y1
+            }
+
+            /// Returns the intersection of both boxes, or `None` if they do
+            /// not overlap.
+            #[inline]
+            pub fn intersect(&self, other: &Self) -> Option<Self> {
+                let b = $box::new(
+                    self.x0.max(other.x0),
+                    self.y0.max(other.y0),
+                    self.x1.min(other.x1),
+                    self.y1.min(other.y1),
+                );
+                if b.is_empty() { None } else { Some(b) }
+            }
+
+            /// Returns the smallest box containing both boxes.
+            #[inline]
+            pub fn union(&self, other: &Self) -> Self {
+                $box::new(
+                    self.x0.min(other.x0),
+                    self.y0.min(other.y0),
+                    self.x1.max(other.x1),
+                    self.y1.max(other.y1),
+                )
+            }
+
+            /// Grows the box by `dx` and `dy` on each side, negative values
+            /// shrink it.
+            #[inline]
+            pub fn inflate(&self, dx: $t, dy: $t) -> Self {
+                $box::new(self.x0 - dx, self.y0 - dy, self.x1 + dx, self.y1 + dy)
+            }
+        }
+
+        impl $rect {
+            #[inline]
+            pub const fn new(x: $t, y: $t, w: $t, h: $t) -> Self {
+                $rect { x, y, w, h }
+            }
+
+            #[inline]
+            pub fn width(&self) -> $t {
+                self.w
+            }
+
+            #[inline]
+            pub fn height(&self) -> $t {
+                self.h
+            }
+
+            /// Returns whether the rect has no area.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                $box::from(*self).is_empty()
+            }
+
+            /// Returns whether `p` lies within the rect, the right and bottom
+            /// edges are exclusive.
+            #[inline]
+            pub fn contains(&self, p: $point) -> bool {
+                $box::from(*self).contains(p)
+            }
+
+            /// Returns the intersection of both rects, or `None` if they do
+            /// not overlap.
+            #[inline]
+            pub fn intersect(&self, other: &Self) -> Option<Self> {
+                $box::from(*self)
+                    .intersect(&$box::from(*other))
+                    .map($rect::from)
+            }
+
+            /// Returns the smallest rect containing both rects.
+            #[inline]
+            pub fn union(&self, other: &Self) -> Self {
+                $box::from(*self).union(&$box::from(*other)).into()
+            }
+
+            /// Grows the rect by `dx` and `dy` on each side, negative values
+            /// shrink it.
+            #[inline]
+            pub fn inflate(&self, dx: $t, dy: $t) -> Self {
+                $box::from(*self).inflate(dx, dy).into()
+            }
+        }
+
+        impl From<$rect> for $box {
+            #[inline]
+            fn from(r: $rect) -> Self {
+                $box::new(r.x, r.y, r.x + r.w, r.y + r.h)
+            }
+        }
+
+        impl From<$box> for $rect {
+            #[inline]
+            fn from(b: $box) -> Self {
+                $rect::new(b.x0, b.y0, b.x1 - b.x0, b.y1 - b.y0)
+            }
+        }
+
+        impl From<($t, $t, $t, $t)> for $box {
+            #[inline]
+            fn from((x0, y0, x1, y1): ($t, $t, $t, $t)) -> Self {
+                $box::new(x0, y0, x1, y1)
+            }
+        }
+
+        impl From<($t, $t, $t, $t)> for $rect {
+            #[inline]
+            fn from((x, y, w, h): ($t, $t, $t, $t)) -> Self {
+                $rect::new(x, y, w, h)
+            }
+        }
+    };
+}
+
+impl_box_ops!(Box, Rect, Point, f64);
+impl_box_ops!(BoxI, RectI, PointI, i32);
+
+impl From<PointI> for Point {
+    #[inline]
+    fn from(p: PointI) -> Self {
+        Point::new(p.x.into(), p.y.into())
+    }
+}
+
+impl From<SizeI> for Size {
+    #[inline]
+    fn from(s: SizeI) -> Self {
+        Size::new(s.w.into(), s.h.into())
+    }
+}
+
+impl From<BoxI> for Box {
+    #[inline]
+    fn from(b: BoxI) -> Self {
+        Box::new(b.x0.into(), b.y0.into(), b.x1.into(), b.y1.into())
+    }
+}
+
+impl From<RectI> for Rect {
+    #[inline]
+    fn from(r: RectI) -> Self {
+        Rect::new(r.x.into(), r.y.into(), r.w.into(), r.h.into())
+    }
+}
+
+impl Point {
+    /// Rounds the coordinates to the nearest integers.
+    #[inline]
+    pub fn round(self) -> PointI {
+        PointI::new(self.x.round() as i32, self.y.round() as i32)
+    }
+}
+
+impl Size {
+    /// Rounds the dimensions to the nearest integers.
+    #[inline]
+    pub fn round(self) -> SizeI {
+        SizeI::new(self.w.round() as i32, self.h.round() as i32)
+    }
+}
+
+impl Box {
+    /// Returns the smallest integer box containing this box.
+    #[inline]
+    pub fn round_out(self) -> BoxI {
+        BoxI::new(
+            self.x0.floor() as i32,
+            self.y0.floor() as i32,
+            self.x1.ceil() as i32,
+            self.y1.ceil() as i32,
+        )
+    }
+}
+
+impl Rect {
+    /// Returns the smallest integer rect containing this rect.
+    #[inline]
+    pub fn round_out(self) -> RectI {
+        Box::from(self).round_out().into()
+    }
+}
+
+use ffi::BLTransformType::*;
+bl_enum! {
+    /// Classification of a [`Matrix2D`], from the simplest to the most
+    /// complex transformation.
+    pub enum MatrixType {
+        /// Identity matrix.
+        Identity  = BL_TRANSFORM_TYPE_IDENTITY,
+        /// Translation only.
+        Translate = BL_TRANSFORM_TYPE_TRANSLATE,
+        /// Scaling and translation.
+        Scale     = BL_TRANSFORM_TYPE_SCALE,
+        /// Swapping of the axes, scaling and translation.
+        Swap      = BL_TRANSFORM_TYPE_SWAP,
+        /// Generic affine transformation.
+        Affine    = BL_TRANSFORM_TYPE_AFFINE,
+        /// Invalid matrix, e.g. singular or containing NaNs.
+        Invalid   = BL_TRANSFORM_TYPE_INVALID,
+    }
+    Default => Identity
+}
+
+/// A 2D affine transformation matrix.
+///
+/// The matrix is stored in row-major order as `[m00, m01, m10, m11, m20,
+/// m21]` and maps points as row vectors:
+///
+/// ```text
+/// x' = x * m00 + y * m10 + m20
+/// y' = x * m01 + y * m11 + m21
+/// ```
+///
+/// Consequently `a * b` is the transformation that applies `a` first and `b`
+/// second.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Matrix2D([f64; 6]);
 
+/// The components of a [`Matrix2D`] as returned by [`Matrix2D::decompose`].
+///
+/// Recomposing applies the scaling first, followed by the skewing, the
+/// rotation and finally the translation.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MatrixDecomposition {
+    pub translation: Point,
+    /// Rotation angle in radians.
+    pub rotation: f64,
+    pub scale: Point,
+    /// Skewing angle along the x axis in radians.
+    pub skew: f64,
+}
+
 impl Matrix2D {
+    /// Creates a new matrix from its elements.
+    #[inline]
+    pub fn new(m00: f64, m01: f64, m10: f64, m11: f64, m20: f64, m21: f64) -> Matrix2D {
+        Matrix2D([m00, m01, m10, m11, m20, m21])
+    }
+
+    /// Creates an identity matrix.
+    #[inline]
+    pub fn identity() -> Matrix2D {
+        Matrix2D([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+    }
+
+    /// Creates a translation matrix.
     #[inline]
     pub fn translation(x: f64, y: f64) -> Matrix2D {
         Matrix2D([1.0, 0.0, 0.0, 1.0, x, y])
     }
+
+    /// Creates a scaling matrix.
+    #[inline]
+    pub fn scaling(x: f64, y: f64) -> Matrix2D {
+        Matrix2D([x, 0.0, 0.0, y, 0.0, 0.0])
+    }
+
+    /// Creates a matrix rotating by `angle` radians around `origin`.
+    #[inline]
+    pub fn rotation(angle: f64, origin: Point) -> Matrix2D {
+        let mut m = Matrix2D::identity();
+        unsafe { ffi::bl_matrix2d_set_rotation(m.as_ffi_mut(), angle, origin.x, origin.y) };
+        m
+    }
+
+    /// Creates a skewing matrix from the skewing angles in radians.
+    #[inline]
+    pub fn skewing(x: f64, y: f64) -> Matrix2D {
+        let mut m = Matrix2D::identity();
+        unsafe { ffi::bl_matrix2d_set_skewing(m.as_ffi_mut(), x, y) };
+        m
+    }
+
+    /// Creates a rotation matrix from the sine and cosine of the angle,
+    /// followed by a translation.
+    #[inline]
+    pub fn sin_cos(sin: f64, cos: f64, tx: f64, ty: f64) -> Matrix2D {
+        Matrix2D([cos, sin, -sin, cos, tx, ty])
+    }
+
+    #[inline]
+    pub fn m00(&self) -> f64 {
+        self.0[0]
+    }
+    #[inline]
+    pub fn m01(&self) -> f64 {
+        self.0[1]
+    }
+    #[inline]
+    pub fn m10(&self) -> f64 {
+        self.0[2]
+    }
+    #[inline]
+    pub fn m11(&self) -> f64 {
+        self.0[3]
+    }
+    #[inline]
+    pub fn m20(&self) -> f64 {
+        self.0[4]
+    }
+    #[inline]
+    pub fn m21(&self) -> f64 {
+        self.0[5]
+    }
+
+    /// The elements of the matrix in row-major order.
+    #[inline]
+    pub fn values(&self) -> &[f64; 6] {
+        &self.0
+    }
+
+    /// The elements of the matrix in row-major order.
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [f64; 6] {
+        &mut self.0
+    }
+
+    #[inline]
+    pub(crate) fn as_ffi(&self) -> *const ffi::BLMatrix2D {
+        self as *const _ as *const _
+    }
+
+    #[inline]
+    pub(crate) fn as_ffi_mut(&mut self) -> *mut ffi::BLMatrix2D {
+        self as *mut _ as *mut _
+    }
+
+    /// Classifies the transformation described by this matrix.
+    #[inline]
+    pub fn matrix_type(&self) -> MatrixType {
+        (unsafe { ffi::bl_matrix2d_get_type(self.as_ffi()) } as u32).into()
+    }
+
+    #[inline]
+    pub fn determinant(&self) -> f64 {
+        self.0[0] * self.0[3] - self.0[1] * self.0[2]
+    }
+
+    /// Returns the inverse of this matrix, or `None` if the matrix is not
+    /// invertible.
+    #[inline]
+    pub fn invert(&self) -> Option<Matrix2D> {
+        let mut m = Matrix2D::identity();
+        let res = unsafe { ffi::bl_matrix2d_invert(m.as_ffi_mut(), self.as_ffi()) };
+        err_to_result(res).ok().map(|_| m)
+    }
+
+    /// Maps a point through this matrix.
+    #[inline]
+    pub fn map_point(&self, p: Point) -> Point {
+        let [m00, m01, m10, m11, m20, m21] = self.0;
+        Point {
+            x: p.x * m00 + p.y * m10 + m20,
+            y: p.x * m01 + p.y * m11 + m21,
+        }
+    }
+
+    /// Maps a vector through this matrix, ignoring the translation.
+    #[inline]
+    pub fn map_vector(&self, v: Point) -> Point {
+        let [m00, m01, m10, m11, ..] = self.0;
+        Point {
+            x: v.x * m00 + v.y * m10,
+            y: v.x * m01 + v.y * m11,
+        }
+    }
+
+    /// Maps the given points through this matrix in place.
+    #[inline]
+    pub fn map_points(&self, points: &mut [Point]) {
+        let ptr = points.as_mut_ptr() as *mut ffi::BLPoint;
+        // Mapping in place is supported by blend2d, every point is read
+        // before it is written.
+        unsafe { ffi::bl_matrix2d_map_pointd_array(self.as_ffi(), ptr, ptr, points.len()) };
+    }
+
+    /// Decomposes the matrix into its scale, skew, rotation and translation
+    /// components, or returns `None` if the matrix is singular.
+    pub fn decompose(&self) -> Option<MatrixDecomposition> {
+        let [m00, m01, m10, m11, m20, m21] = self.0;
+        let sx = m00.hypot(m01);
+        if sx == 0.0 || !sx.is_finite() {
+            return None;
+        }
+        let (cos, sin) = (m00 / sx, m01 / sx);
+        // Project the second row onto the rotated axes.
+        let sy = m11 * cos - m10 * sin;
+        if sy == 0.0 || !sy.is_finite() {
+            return None;
+        }
+        let shear = (m10 * cos + m11 * sin) / sy;
+        Some(MatrixDecomposition {
+            translation: Point { x: m20, y: m21 },
+            rotation: sin.atan2(cos),
+            scale: Point { x: sx, y: sy },
+            skew: shear.atan(),
+        })
+    }
+}
+
+impl Default for Matrix2D {
+    #[inline]
+    fn default() -> Self {
+        Matrix2D::identity()
+    }
+}
+
+impl From<[f64; 6]> for Matrix2D {
+    #[inline]
+    fn from(values: [f64; 6]) -> Self {
+        Matrix2D(values)
+    }
+}
+
+impl From<Matrix2D> for [f64; 6] {
+    #[inline]
+    fn from(m: Matrix2D) -> Self {
+        m.0
+    }
+}
+
+impl From<MatrixDecomposition> for Matrix2D {
+    fn from(d: MatrixDecomposition) -> Self {
+        Matrix2D::scaling(d.scale.x, d.scale.y)
+            * Matrix2D::skewing(d.skew, 0.0)
+            * Matrix2D::rotation(d.rotation, Point::default())
+            * Matrix2D::translation(d.translation.x, d.translation.y)
+    }
+}
+
+impl ops::Mul for Matrix2D {
+    type Output = Matrix2D;
+
+    #[inline]
+    fn mul(self, rhs: Matrix2D) -> Matrix2D {
+        let [a00, a01, a10, a11, a20, a21] = self.0;
+        let [b00, b01, b10, b11, b20, b21] = rhs.0;
+        Matrix2D([
+            a00 * b00 + a01 * b10,
+            a00 * b01 + a01 * b11,
+            a10 * b00 + a11 * b10,
+            a10 * b01 + a11 * b11,
+            a20 * b00 + a21 * b10 + b20,
+            a20 * b01 + a21 * b11 + b21,
+        ])
+    }
+}
+
+impl ops::MulAssign for Matrix2D {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Matrix2D) {
+        *self = *self * rhs;
+    }
+}
+
+impl ops::Mul<Matrix2D> for Point {
+    type Output = Point;
+
+    #[inline]
+    fn mul(self, rhs: Matrix2D) -> Point {
+        rhs.map_point(self)
+    }
 }
 
 use ffi::BLStrokeCap::*;
@@ -146,3 +722,147 @@ bl_enum! {
     }
     Default => Butt
 }
+
+use ffi::BLStrokeJoin::*;
+bl_enum! {
+    /// Stroke join type.
+    pub enum StrokeJoin {
+        /// Miter-join possibly clipped at `miter_limit` [default].
+        MiterClip  = BL_STROKE_JOIN_MITER_CLIP,
+        /// Miter-join or bevel-join depending on miter_limit condition.
+        MiterBevel = BL_STROKE_JOIN_MITER_BEVEL,
+        /// Miter-join or round-join depending on miter_limit condition.
+        MiterRound = BL_STROKE_JOIN_MITER_ROUND,
+        /// Bevel-join.
+        Bevel      = BL_STROKE_JOIN_BEVEL,
+        /// Round-join.
+        Round      = BL_STROKE_JOIN_ROUND,
+    }
+    Default => MiterClip
+}
+
+use ffi::BLStrokeTransformOrder::*;
+bl_enum! {
+    /// Stroke transform order.
+    pub enum StrokeTransformOrder {
+        /// Transform stroke after stroking [default].
+        After  = BL_STROKE_TRANSFORM_ORDER_AFTER,
+        /// Transform stroke before stroking.
+        Before = BL_STROKE_TRANSFORM_ORDER_BEFORE,
+    }
+    Default => After
+}
+
+use ffi::BLFillRule::*;
+bl_enum! {
+    /// Fill rule.
+    pub enum FillRule {
+        /// Non-zero fill-rule.
+        NonZero = BL_FILL_RULE_NON_ZERO,
+        /// Even-odd fill-rule.
+        EvenOdd = BL_FILL_RULE_EVEN_ODD,
+    }
+    Default => NonZero
+}
+
+use ffi::BLHitTest::*;
+bl_enum! {
+    /// Hit-test result.
+    pub enum HitTest {
+        /// Fully in.
+        In      = BL_HIT_TEST_IN,
+        /// Partially in/out.
+        Part    = BL_HIT_TEST_PART,
+        /// Fully out.
+        Out     = BL_HIT_TEST_OUT,
+        /// Hit test failed (invalid argument, NaNs, etc).
+        Invalid = BL_HIT_TEST_INVALID,
+    }
+    Default => Invalid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_ops() {
+        let p = Point::new(1.0, 2.0) + Point::from((3.0, 4.0));
+        assert_eq!(p, Point::new(4.0, 6.0));
+        assert_eq!(p - Point::new(1.0, 1.0), Point::new(3.0, 5.0));
+        assert_eq!(PointI::new(1, -2) * 3, PointI::new(3, -6));
+        assert_eq!(Size::new(1.5, 2.0) * 2.0, Size::new(3.0, 4.0));
+        assert_eq!(Point::from(PointI::new(1, 2)), Point::new(1.0, 2.0));
+        assert_eq!(Point::new(1.4, -1.6).round(), PointI::new(1, -2));
+
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let b = Rect::new(5.0, 5.0, 10.0, 10.0);
+        assert_eq!(a.intersect(&b), Some(Rect::new(5.0, 5.0, 5.0, 5.0)));
+        assert_eq!(a.union(&b), Rect::new(0.0, 0.0, 15.0, 15.0));
+        assert_eq!(a.intersect(&Rect::new(10.0, 0.0, 1.0, 1.0)), None);
+        assert_eq!(a.inflate(1.0, 2.0), Rect::new(-1.0, -2.0, 12.0, 14.0));
+        assert!(a.contains(Point::new(0.0, 9.5)));
+        assert!(!a.contains(Point::new(10.0, 5.0)));
+
+        let b = BoxI::from(RectI::new(1, 2, 3, 4));
+        assert_eq!(b, BoxI::new(1, 2, 4, 6));
+        assert_eq!((b.width(), b.height()), (3, 4));
+        assert_eq!(
+            Box::new(0.5, 0.5, 1.5, 2.0).round_out(),
+            BoxI::new(0, 0, 2, 2)
+        );
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(
+            (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_matrix_ops() {
+        let p = Point { x: 1.0, y: 2.0 };
+        let m = Matrix2D::scaling(2.0, 3.0) * Matrix2D::translation(10.0, 20.0);
+        assert_eq!(m.map_point(p), Point { x: 12.0, y: 26.0 });
+        assert_eq!(m.map_vector(p), Point { x: 2.0, y: 6.0 });
+        assert_eq!(p * m, m.map_point(p));
+        assert_eq!(m.determinant(), 6.0);
+
+        let r = Matrix2D::rotation(std::f64::consts::FRAC_PI_2, Point { x: 1.0, y: 1.0 });
+        assert_near(
+            r.map_point(Point { x: 2.0, y: 1.0 }),
+            Point { x: 1.0, y: 2.0 },
+        );
+
+        let inv = (m * r).invert().unwrap();
+        assert_near((p * m * r) * inv, p);
+        assert_eq!(Matrix2D::scaling(0.0, 1.0).invert(), None);
+
+        let mut points = [p, Point { x: -1.0, y: 0.5 }];
+        m.map_points(&mut points);
+        assert_eq!(
+            points,
+            [m.map_point(p), m.map_point(Point { x: -1.0, y: 0.5 })]
+        );
+    }
+
+    #[test]
+    fn test_matrix_decompose() {
+        let d = MatrixDecomposition {
+            translation: Point { x: 5.0, y: -7.0 },
+            rotation: 0.5,
+            scale: Point { x: 2.0, y: -3.0 },
+            skew: 0.25,
+        };
+        let m = Matrix2D::from(d);
+        let decomposed = m.decompose().unwrap();
+        assert_near(decomposed.translation, d.translation);
+        assert_near(decomposed.scale, d.scale);
+        assert!((decomposed.rotation - d.rotation).abs() < 1e-9);
+        assert!((decomposed.skew - d.skew).abs() < 1e-9);
+        assert_eq!(Matrix2D::scaling(1.0, 0.0).decompose(), None);
+    }
+}
@@ -4,8 +4,9 @@ use std::ptr::null;
 
 use ffi;
 
-use crate::{Error, ExtendMode, err_to_result};
+use crate::{Error, ExtendMode, Rgba32, Rgba64, err_to_result};
 
+#[repr(transparent)]
 pub struct Gradient(pub(crate) ffi::BLGradientCore);
 
 #[repr(C)]
@@ -86,12 +87,16 @@ impl Gradient {
         }
     }
     #[inline]
-    pub fn add_stop_rgba32(&mut self, offset: f64, rgba32: u32) -> Result<(), Error> {
-        err_to_result(unsafe { ffi::bl_gradient_add_stop_rgba32(&mut self.0, offset, rgba32) })
+    pub fn add_stop_rgba32(&mut self, offset: f64, rgba32: impl Into<Rgba32>) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_gradient_add_stop_rgba32(&mut self.0, offset, rgba32.into().value)
+        })
     }
     #[inline]
-    pub fn add_stop_rgba64(&mut self, offset: f64, rgba64: u64) -> Result<(), Error> {
-        err_to_result(unsafe { ffi::bl_gradient_add_stop_rgba64(&mut self.0, offset, rgba64) })
+    pub fn add_stop_rgba64(&mut self, offset: f64, rgba64: impl Into<Rgba64>) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_gradient_add_stop_rgba64(&mut self.0, offset, rgba64.into().value)
+        })
     }
 }
 
@@ -1,11 +1,30 @@
 //! Image loading and handling.
 
-use std::{ffi::CStr, ptr::null};
+use std::io::{self, Read};
+use std::path::Path;
+use std::{fmt, ptr::null};
 
-use crate::geometry::SizeI;
+use crate::codec::ImageCodec;
+use crate::geometry::{Size, SizeI};
+use crate::util::{c_string, path_to_c_string};
 use crate::{Error, err_to_result};
 use ffi;
 
+mod blur;
+mod compare;
+pub use self::compare::ImageDiff;
+mod filter;
+pub use self::filter::{ColorMatrix, ConvolutionKernel};
+mod transform;
+pub use self::transform::SubImage;
+
+/// How many bytes from the start of a file [`Image::probe_file`] reads first.
+const PROBE_PREFIX_LEN: usize = 64 * 1024;
+/// How many bytes [`Image::probe_file`] reads at most when the headers of a
+/// recognized image extend beyond the prefix.
+const PROBE_MAX_LEN: usize = 4 * 1024 * 1024;
+
+#[repr(transparent)]
 pub struct Image(pub(crate) ffi::BLImageCore);
 
 use ffi::BLFormat::*;
@@ -22,6 +41,17 @@ bl_enum! {
     Default => PRgb32
 }
 
+impl Format {
+    /// The number of bytes a pixel of this format occupies.
+    #[inline]
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Format::PRgb32 | Format::XRgb32 => 4,
+            Format::A8 => 1,
+        }
+    }
+}
+
 use ffi::BLImageScaleFilter::*;
 bl_enum! {
     /// Pixel format.
@@ -38,6 +68,78 @@ bl_enum! {
     Default => Nearest
 }
 
+/// Information about an encoded image, as read by an image decoder.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImageInfo {
+    /// Image size in pixels.
+    pub size: SizeI,
+    /// Pixel density per one meter, can contain fractions.
+    pub density: Size,
+    /// Whether the image is progressive or interlaced.
+    pub progressive: bool,
+    /// Image depth in bits per pixel.
+    pub depth: u16,
+    /// Number of planes.
+    pub plane_count: u16,
+    /// Number of frames, zero means unknown or not specified.
+    pub frame_count: u64,
+    /// Number of times an animation repeats, zero means infinite.
+    pub repeat_count: u32,
+    /// Image format name, as provided by the codec.
+    pub format: String,
+    /// Image compression name, as provided by the codec.
+    pub compression: String,
+}
+
+impl ImageInfo {
+    pub(crate) fn from_ffi(info: &ffi::BLImageInfo) -> Self {
+        ImageInfo {
+            size: SizeI::new(info.size.w, info.size.h),
+            density: Size::new(info.density.w, info.density.h),
+            progressive: info.flags & ffi::BLImageInfoFlags::BL_IMAGE_INFO_FLAG_PROGRESSIVE as u32
+                != 0,
+            depth: info.depth,
+            plane_count: info.plane_count,
+            frame_count: info.frame_count,
+            repeat_count: info.repeat_count,
+            format: c_string(&info.format),
+            compression: c_string(&info.compression),
+        }
+    }
+
+    /// Converts the info to its C representation, truncating the format and
+    /// compression names to fit.
+    pub(crate) fn to_ffi(&self) -> ffi::BLImageInfo {
+        let mut info: ffi::BLImageInfo = unsafe { std::mem::zeroed() };
+        info.size = ffi::BLSizeI {
+            w: self.size.w,
+            h: self.size.h,
+        };
+        info.density = ffi::BLSize {
+            w: self.density.w,
+            h: self.density.h,
+        };
+        if self.progressive {
+            info.flags = ffi::BLImageInfoFlags::BL_IMAGE_INFO_FLAG_PROGRESSIVE as u32;
+        }
+        info.depth = self.depth;
+        info.plane_count = self.plane_count;
+        info.frame_count = self.frame_count;
+        info.repeat_count = self.repeat_count;
+        for (name, out) in [
+            (&self.format, &mut info.format),
+            (&self.compression, &mut info.compression),
+        ] {
+            // Keep the terminating nul.
+            let len = name.len().min(out.len() - 1);
+            for (c, &b) in out.iter_mut().zip(&name.as_bytes()[..len]) {
+                *c = b as _;
+            }
+        }
+        info
+    }
+}
+
 impl Image {
     #[inline]
     pub fn new(w: i32, h: i32, format: Format) -> Result<Self, Error> {
@@ -52,22 +154,129 @@ impl Image {
             Ok(Image(image.assume_init()))
         }
     }
+    /// Writes the image to the file at `path`, picking the codec by the file
+    /// extension.
     #[inline]
-    pub fn write_to_file(&self, filename: &CStr) -> Result<(), Error> {
-        err_to_result(unsafe { ffi::bl_image_write_to_file(&self.0, filename.as_ptr(), null()) })
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path_to_c_string(path.as_ref())?;
+        err_to_result(unsafe { ffi::bl_image_write_to_file(&self.0, path.as_ptr(), null()) })
     }
+    /// Reads an image from the file at `path` with the first built-in codec
+    /// that recognizes its contents.
     #[inline]
-    pub fn read_from_file(filename: &CStr) -> Result<Self, Error> {
-        let mut image = unsafe {
-            let mut image = std::mem::MaybeUninit::<ffi::BLImageCore>::uninit();
-            ffi::bl_image_init(image.as_mut_ptr());
-            image.assume_init()
-        };
-
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path_to_c_string(path.as_ref())?;
+        let mut image = Image::default();
         err_to_result(unsafe {
-            ffi::bl_image_read_from_file(&mut image, filename.as_ptr(), null())
+            ffi::bl_image_read_from_file(&mut image.0, path.as_ptr(), null())
         })?;
-        Ok(Image(image))
+        Ok(image)
+    }
+    /// Reads the [`ImageInfo`] of the encoded image `data` without decoding
+    /// its pixels.
+    ///
+    /// Fails if none of the built-in codecs recognizes the data.
+    pub fn probe<R: AsRef<[u8]>>(data: R) -> Result<ImageInfo, Error> {
+        let data = data.as_ref();
+        let codec = ImageCodec::find_by_data(data).ok_or(Error)?;
+        codec.create_decoder()?.read_info(data)
+    }
+    /// Reads the [`ImageInfo`] of the image file at `path` without decoding
+    /// its pixels, see [`Image::probe`].
+    ///
+    /// Only the first 64 KiB of the file are read. If a codec recognizes them
+    /// but the headers of the image extend beyond them, up to 4 MiB are read.
+    /// Fails with the error of reading the file, or with
+    /// [`io::ErrorKind::InvalidData`] if none of the built-in codecs
+    /// recognizes it or its headers don't fit.
+    pub fn probe_file<P: AsRef<Path>>(path: P) -> io::Result<ImageInfo> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "unrecognized image data");
+        let mut file = std::fs::File::open(path)?;
+        let mut data = Vec::with_capacity(PROBE_PREFIX_LEN);
+        let mut len = PROBE_PREFIX_LEN;
+        loop {
+            (&mut file)
+                .take((len - data.len()) as u64)
+                .read_to_end(&mut data)?;
+            let codec = ImageCodec::find_by_data(&data).ok_or_else(invalid)?;
+            let info = codec
+                .create_decoder()
+                .and_then(|mut decoder| decoder.read_info(&data));
+            match info {
+                Ok(info) => return Ok(info),
+                // Metadata such as JPEG's EXIF can push the headers beyond
+                // the prefix.
+                Err(_) if data.len() == len && len < PROBE_MAX_LEN => {
+                    len = (len * 4).min(PROBE_MAX_LEN);
+                },
+                Err(_) => return Err(invalid()),
+            }
+        }
+    }
+    #[inline]
+    fn image_data(&self) -> ffi::BLImageData {
+        let mut data = std::mem::MaybeUninit::<ffi::BLImageData>::uninit();
+        unsafe {
+            ffi::bl_image_get_data(&self.0, data.as_mut_ptr());
+            data.assume_init()
+        }
+    }
+    /// The size of the image in pixels.
+    #[inline]
+    pub fn size(&self) -> SizeI {
+        let size = self.image_data().size;
+        SizeI::new(size.w, size.h)
+    }
+    #[inline]
+    pub fn width(&self) -> i32 {
+        self.size().w
+    }
+    #[inline]
+    pub fn height(&self) -> i32 {
+        self.size().h
+    }
+    /// The pixel format of the image.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.image_data().format.into()
+    }
+    /// The number of bytes between the starts of two consecutive rows.
+    #[inline]
+    pub fn stride(&self) -> usize {
+        self.image_data().stride.max(0) as usize
+    }
+    /// The raw pixel data, `stride()` bytes for every row but the last, which
+    /// ends after its last pixel.
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        match pixel_parts(&self.image_data()) {
+            Some((ptr, len)) => unsafe { std::slice::from_raw_parts(ptr, len) },
+            None => &[],
+        }
+    }
+    /// The raw pixel data for modification.
+    ///
+    /// Makes the image data unique first if it is shared with other images.
+    #[inline]
+    pub fn pixels_mut(&mut self) -> Result<&mut [u8], Error> {
+        let mut data = std::mem::MaybeUninit::<ffi::BLImageData>::uninit();
+        unsafe {
+            err_to_result(ffi::bl_image_make_mutable(&mut self.0, data.as_mut_ptr()))?;
+            Ok(match pixel_parts(&data.assume_init()) {
+                Some((ptr, len)) => std::slice::from_raw_parts_mut(ptr, len),
+                None => &mut [],
+            })
+        }
+    }
+    /// The pixels of row `y` without any padding, or `None` if `y` is out of
+    /// bounds.
+    #[inline]
+    pub fn row(&self, y: i32) -> Option<&[u8]> {
+        let stride = self.stride();
+        let row_len = self.width().max(0) as usize * self.format().bytes_per_pixel();
+        (0..self.height())
+            .contains(&y)
+            .then(|| &self.pixels()[y as usize * stride..][..row_len])
     }
     #[inline]
     pub fn scale(&mut self, size: SizeI, filter: ScaleFilter) -> Result<(), Error> {
@@ -77,6 +286,42 @@ impl Image {
     }
 }
 
+/// Returns the pixel pointer and the byte length of the pixel data, if any.
+///
+/// The last row ends after its last pixel, images created from external data
+/// don't have to provide a full stride for it.
+fn pixel_parts(data: &ffi::BLImageData) -> Option<(*mut u8, usize)> {
+    if data.pixel_data.is_null() || data.stride <= 0 || data.size.w <= 0 || data.size.h <= 0 {
+        return None;
+    }
+    let bpp = Format::from(data.format).bytes_per_pixel();
+    Some((
+        data.pixel_data as *mut u8,
+        data.stride as usize * (data.size.h as usize - 1) + data.size.w as usize * bpp,
+    ))
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("size", &self.size())
+            .field("format", &self.format())
+            .finish()
+    }
+}
+
+impl Default for Image {
+    /// Creates an empty image.
+    #[inline]
+    fn default() -> Self {
+        let mut image = std::mem::MaybeUninit::<ffi::BLImageCore>::uninit();
+        unsafe {
+            ffi::bl_image_init(image.as_mut_ptr());
+            Image(image.assume_init())
+        }
+    }
+}
+
 impl Drop for Image {
     #[inline]
     fn drop(&mut self) {
@@ -85,3 +330,52 @@ impl Drop for Image {
         }
     }
 }
+
+/// Helpers for tests that build images from premultiplied `0xAARRGGBB`
+/// values and read them back.
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::filter::{load, store};
+    use super::{Format, Image};
+    use crate::Rgba32;
+
+    /// Two rows of three pixels with opaque, translucent, transparent, white
+    /// and black colors.
+    pub(crate) const PIXELS: [u32; 6] = [
+        0xFF102030, 0x80402000, 0x00000000, 0xFFFFFFFF, 0x40404040, 0xFF000000,
+    ];
+
+    /// Creates an image of `format` that is `w` pixels wide from rows of
+    /// `pixels`.
+    pub(crate) fn image(w: i32, format: Format, pixels: &[u32]) -> Image {
+        let h = pixels.len() as i32 / w;
+        let bpp = format.bytes_per_pixel();
+        let mut image = Image::new(w, h, format).unwrap();
+        let stride = image.stride();
+        let data = image.pixels_mut().unwrap();
+        for (y, row) in pixels.chunks_exact(w as usize).enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                store(
+                    format,
+                    Rgba32::from_value(pixel),
+                    &mut data[y * stride + x * bpp..][..bpp],
+                );
+            }
+        }
+        image
+    }
+
+    /// The pixels of a `row` of `format`.
+    pub(crate) fn row_values(format: Format, row: &[u8]) -> Vec<u32> {
+        row.chunks_exact(format.bytes_per_pixel())
+            .map(|bytes| load(format, bytes).value)
+            .collect()
+    }
+
+    /// The pixels of all rows of `image`.
+    pub(crate) fn values(image: &Image) -> Vec<u32> {
+        (0..image.height())
+            .flat_map(|y| row_values(image.format(), image.row(y).unwrap()))
+            .collect()
+    }
+}
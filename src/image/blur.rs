@@ -0,0 +1,319 @@
+//! Separable Gaussian and box blur.
+//!
+//! Both passes work on whole rows of interleaved channels at once so that the
+//! inner loops are plain slice arithmetic the compiler can vectorize. Pixels
+//! beyond the edges repeat the edge pixels.
+
+use crate::Error;
+use crate::geometry::SizeI;
+use crate::image::Image;
+
+/// Fixed point scale of the convolution weights.
+const ONE: u32 = 1 << 16;
+const HALF: u32 = ONE / 2;
+
+/// A rectangular area of pixels with interleaved 8-bit channels.
+pub(crate) struct Plane<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    channels: usize,
+}
+
+impl Plane<'_> {
+    /// Blurs the plane with a Gaussian of standard deviation `sigma`.
+    ///
+    /// The kernel is cut off at the size of the plane.
+    pub(crate) fn gaussian_blur(&mut self, sigma: f64) {
+        if sigma.is_nan() || sigma <= 0.0 || self.is_empty() {
+            return;
+        }
+        let kernel = gaussian_kernel(sigma, self.max_radius());
+        self.convolve_rows(&kernel);
+        self.convolve_columns(&kernel);
+    }
+
+    /// Blurs the plane with a box of `2 * radius + 1` pixels.
+    ///
+    /// The radius is clamped to the size of the plane.
+    pub(crate) fn box_blur(&mut self, radius: usize) {
+        if radius == 0 || self.is_empty() {
+            return;
+        }
+        let radius = radius.min(self.max_radius());
+        self.box_rows(radius);
+        self.box_columns(radius);
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The largest useful radius, beyond it only repeated edge pixels are
+    /// added.
+    #[inline]
+    fn max_radius(&self) -> usize {
+        self.width.max(self.height)
+    }
+
+    #[inline]
+    fn row_len(&self) -> usize {
+        self.width * self.channels
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        let n = self.row_len();
+        &mut self.data[y * self.stride..][..n]
+    }
+
+    /// Copies the rows into a compact buffer without stride padding.
+    fn compact_rows(&self) -> Vec<u8> {
+        let n = self.row_len();
+        let mut rows = Vec::with_capacity(n * self.height);
+        for y in 0..self.height {
+            rows.extend_from_slice(&self.data[y * self.stride..][..n]);
+        }
+        rows
+    }
+
+    fn convolve_rows(&mut self, kernel: &[u32]) {
+        let (c, r, n) = (self.channels, kernel.len() / 2, self.row_len());
+        let mut padded = vec![0; n + 2 * r * c];
+        let mut acc = vec![0u32; n];
+        for y in 0..self.height {
+            let row = self.row_mut(y);
+            pad_row(row, c, r, &mut padded);
+            acc.fill(HALF);
+            for (k, &w) in kernel.iter().enumerate() {
+                for (a, &s) in acc.iter_mut().zip(&padded[k * c..][..n]) {
+                    *a += w * s as u32;
+                }
+            }
+            for (d, &a) in row.iter_mut().zip(&acc) {
+                *d = (a >> 16) as u8;
+            }
+        }
+    }
+
+    fn convolve_columns(&mut self, kernel: &[u32]) {
+        let (r, n, h) = (kernel.len() / 2, self.row_len(), self.height);
+        let src = self.compact_rows();
+        let src_row = |y: usize, k: usize| {
+            let y = (y + k).saturating_sub(r).min(h - 1);
+            &src[y * n..][..n]
+        };
+        let mut acc = vec![0u32; n];
+        for y in 0..h {
+            acc.fill(HALF);
+            for (k, &w) in kernel.iter().enumerate() {
+                for (a, &s) in acc.iter_mut().zip(src_row(y, k)) {
+                    *a += w * s as u32;
+                }
+            }
+            for (d, &a) in self.row_mut(y).iter_mut().zip(&acc) {
+                *d = (a >> 16) as u8;
+            }
+        }
+    }
+
+    fn box_rows(&mut self, radius: usize) {
+        let (c, w, n) = (self.channels, self.width, self.row_len());
+        let size = 2 * radius + 1;
+        let mul = box_multiplier(size);
+        let mut padded = vec![0; n + 2 * radius * c];
+        let mut sums = vec![0u32; c];
+        for y in 0..self.height {
+            let row = self.row_mut(y);
+            pad_row(row, c, radius, &mut padded);
+            sums.fill(0);
+            for pixel in padded[..size * c].chunks_exact(c) {
+                for (sum, &s) in sums.iter_mut().zip(pixel) {
+                    *sum += s as u32;
+                }
+            }
+            for x in 0..w {
+                for (ch, sum) in sums.iter_mut().enumerate() {
+                    row[x * c + ch] = box_scale(*sum, mul);
+                    if x + 1 < w {
+                        *sum += padded[(x + size) * c + ch] as u32;
+                        *sum -= padded[x * c + ch] as u32;
+                    }
+                }
+            }
+        }
+    }
+
+    fn box_columns(&mut self, radius: usize) {
+        let (n, h) = (self.row_len(), self.height);
+        let mul = box_multiplier(2 * radius + 1);
+        let src = self.compact_rows();
+        let src_row = |y: usize| &src[y.min(h - 1) * n..][..n];
+        let mut sums = vec![0u32; n];
+        for k in 0..=2 * radius {
+            for (sum, &s) in sums.iter_mut().zip(src_row(k.saturating_sub(radius))) {
+                *sum += s as u32;
+            }
+        }
+        for y in 0..h {
+            for (d, &sum) in self.row_mut(y).iter_mut().zip(&sums) {
+                *d = box_scale(sum, mul);
+            }
+            let (added, removed) = (src_row(y + radius + 1), src_row(y.saturating_sub(radius)));
+            for ((sum, &a), &r) in sums.iter_mut().zip(added).zip(removed) {
+                *sum = *sum + a as u32 - r as u32;
+            }
+        }
+    }
+}
+
+/// Copies `row` into the middle of `padded`, repeating the first and last
+/// pixels `r` times on either side.
+fn pad_row(row: &[u8], c: usize, r: usize, padded: &mut [u8]) {
+    let n = row.len();
+    padded[r * c..][..n].copy_from_slice(row);
+    let (first, last) = (&row[..c], &row[n - c..]);
+    for i in 0..r {
+        padded[i * c..][..c].copy_from_slice(first);
+        padded[n + (r + i) * c..][..c].copy_from_slice(last);
+    }
+}
+
+/// Returns the fixed point weights of a Gaussian kernel covering three
+/// standard deviations but at most `max_radius` pixels, summing up to exactly
+/// [`ONE`].
+fn gaussian_kernel(sigma: f64, max_radius: usize) -> Vec<u32> {
+    // The float to int cast saturates for huge sigmas.
+    let radius = ((sigma * 3.0).ceil() as usize).min(max_radius);
+    let weights = (0..=2 * radius)
+        .map(|i| {
+            let x = i as f64 - radius as f64;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect::<Vec<_>>();
+    let sum: f64 = weights.iter().sum();
+    let mut kernel = weights
+        .iter()
+        .map(|w| (w / sum * ONE as f64).round() as u32)
+        .collect::<Vec<_>>();
+    // Put the rounding error into the center weight.
+    let total: u32 = kernel.iter().sum();
+    kernel[radius] = (kernel[radius] + ONE).saturating_sub(total);
+    kernel
+}
+
+#[inline]
+fn box_multiplier(size: usize) -> u64 {
+    ((1u64 << 32) + size as u64 / 2) / size as u64
+}
+
+#[inline]
+fn box_scale(sum: u32, mul: u64) -> u8 {
+    ((sum as u64 * mul + (1 << 31)) >> 32).min(255) as u8
+}
+
+impl Image {
+    /// Returns the top left `size` pixels of the image for modification.
+    pub(crate) fn plane(&mut self, size: SizeI) -> Result<Plane<'_>, Error> {
+        let image_size = self.size();
+        let channels = self.format().bytes_per_pixel();
+        let stride = self.stride();
+        Ok(Plane {
+            data: self.pixels_mut()?,
+            width: size.w.clamp(0, image_size.w) as usize,
+            height: size.h.clamp(0, image_size.h) as usize,
+            stride,
+            channels,
+        })
+    }
+
+    /// Blurs the image with a Gaussian of standard deviation `sigma` in
+    /// pixels.
+    ///
+    /// All channels are blurred, so premultiplied pixels stay valid. The
+    /// kernel is cut off at the larger of the image's width and height.
+    pub fn gaussian_blur(&mut self, sigma: f64) -> Result<(), Error> {
+        let size = self.size();
+        self.plane(size)?.gaussian_blur(sigma);
+        Ok(())
+    }
+
+    /// Blurs the image with a box of `2 * radius + 1` by `2 * radius + 1`
+    /// pixels.
+    ///
+    /// The radius is clamped to the larger of the image's width and height.
+    pub fn box_blur(&mut self, radius: u32) -> Result<(), Error> {
+        let size = self.size();
+        self.plane(size)?.box_blur(radius as usize);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plane(data: &mut [u8], width: usize, channels: usize) -> Plane<'_> {
+        let stride = width * channels;
+        Plane {
+            height: data.len() / stride,
+            data,
+            width,
+            stride,
+            channels,
+        }
+    }
+
+    #[test]
+    fn test_gaussian_blur() {
+        assert_eq!(gaussian_kernel(1.5, 100).iter().sum::<u32>(), ONE);
+
+        let mut data = vec![0u8; 9 * 9];
+        data[4 * 9 + 4] = 255;
+        plane(&mut data, 9, 1).gaussian_blur(1.0);
+        let center = data[4 * 9 + 4];
+        assert!(center > 0 && center < 255);
+        // The impulse spreads symmetrically and keeps its energy.
+        assert_eq!(data[4 * 9 + 3], data[4 * 9 + 5]);
+        assert_eq!(data[3 * 9 + 4], data[4 * 9 + 3]);
+        let total: u32 = data.iter().map(|&v| v as u32).sum();
+        assert!((245..=265).contains(&total));
+
+        let mut flat = vec![200u8; 5 * 4 * 4];
+        plane(&mut flat, 5, 4).gaussian_blur(2.0);
+        assert!(flat.iter().all(|&v| v == 200));
+    }
+
+    #[test]
+    fn test_box_blur() {
+        let mut data = vec![0, 0, 0, 90, 0, 0, 0];
+        plane(&mut data, 7, 1).box_blur(1);
+        assert_eq!(data, [0, 0, 30, 30, 30, 0, 0]);
+
+        // Premultiplied color never exceeds alpha.
+        let mut pixels = [[10, 20, 30, 40], [0, 0, 0, 0], [250, 0, 0, 255]].concat();
+        plane(&mut pixels, 3, 4).box_blur(2);
+        for pixel in pixels.chunks_exact(4) {
+            assert!(pixel[..3].iter().all(|&c| c <= pixel[3]));
+        }
+    }
+
+    #[test]
+    fn test_blur_clamp() {
+        let kernel = gaussian_kernel(1e300, 3);
+        assert_eq!(kernel.len(), 7);
+        assert_eq!(kernel.iter().sum::<u32>(), ONE);
+
+        let source = [0, 30, 60, 90, 255, 0];
+        let mut clamped = source;
+        plane(&mut clamped, 3, 2).box_blur(usize::MAX);
+        let mut expected = source;
+        plane(&mut expected, 3, 2).box_blur(3);
+        assert_eq!(clamped, expected);
+
+        let mut flat = [200u8; 4 * 2 * 2];
+        plane(&mut flat, 2, 4).gaussian_blur(f64::MAX);
+        assert!(flat.iter().all(|&v| v == 200));
+    }
+}
@@ -0,0 +1,181 @@
+//! Pixel-wise comparison of images.
+
+use super::filter::{load, store};
+use crate::geometry::BoxI;
+use crate::image::{Format, Image};
+use crate::{Error, Rgba32};
+
+/// The differences between two images, see [`Image::compare`].
+#[derive(Debug)]
+pub struct ImageDiff {
+    /// The largest difference of any premultiplied color component.
+    pub max_delta: u8,
+    /// The number of pixels that differ in any component.
+    pub differing_pixels: usize,
+    /// Peak signal-to-noise ratio in decibels, infinite for identical images.
+    pub psnr: f64,
+    /// The bounding box of the differing pixels, `None` for identical images.
+    pub bounding_box: Option<BoxI>,
+    /// A PRgb32 image that shows unchanged pixels in black and differing
+    /// pixels from yellow to red by their largest component delta, `None`
+    /// for identical images.
+    pub heatmap: Option<Image>,
+}
+
+impl ImageDiff {
+    /// Whether the images are pixel-wise identical.
+    #[inline]
+    pub fn is_identical(&self) -> bool {
+        self.differing_pixels == 0
+    }
+
+    /// Whether no component differs by more than `max_delta` and at most
+    /// `max_differing_pixels` pixels differ.
+    #[inline]
+    pub fn is_within(&self, max_delta: u8, max_differing_pixels: usize) -> bool {
+        self.max_delta <= max_delta && self.differing_pixels <= max_differing_pixels
+    }
+}
+
+impl Image {
+    /// Compares the pixels of this image to the pixels of `other`.
+    ///
+    /// Pixels are compared as premultiplied colors, so images of different
+    /// formats can be compared. Fails if the images differ in size.
+    pub fn compare(&self, other: &Image) -> Result<ImageDiff, Error> {
+        let size = self.size();
+        if size != other.size() {
+            return Err(Error);
+        }
+        let (w, h) = (size.w.max(0) as usize, size.h.max(0) as usize);
+        let (a_format, b_format) = (self.format(), other.format());
+        let (a_bpp, b_bpp) = (a_format.bytes_per_pixel(), b_format.bytes_per_pixel());
+        let (a_pixels, b_pixels) = (self.pixels(), other.pixels());
+        let (a_stride, b_stride) = (self.stride(), other.stride());
+
+        let mut deltas = vec![0u8; w * h];
+        let mut max_delta = 0;
+        let mut differing_pixels = 0;
+        let mut squared_error = 0u64;
+        let mut bounding_box: Option<BoxI> = None;
+        for y in 0..h {
+            let a_row = a_pixels[y * a_stride..][..w * a_bpp].chunks_exact(a_bpp);
+            let b_row = b_pixels[y * b_stride..][..w * b_bpp].chunks_exact(b_bpp);
+            for (x, (a, b)) in a_row.zip(b_row).enumerate() {
+                let (a, b) = (load(a_format, a), load(b_format, b));
+                let delta = [
+                    a.r().abs_diff(b.r()),
+                    a.g().abs_diff(b.g()),
+                    a.b().abs_diff(b.b()),
+                    a.a().abs_diff(b.a()),
+                ];
+                let pixel_delta = delta.iter().copied().max().unwrap_or(0);
+                if pixel_delta == 0 {
+                    continue;
+                }
+                squared_error += delta.iter().map(|&d| d as u64 * d as u64).sum::<u64>();
+                deltas[y * w + x] = pixel_delta;
+                max_delta = max_delta.max(pixel_delta);
+                differing_pixels += 1;
+                let pixel_box = BoxI::new(x as i32, y as i32, x as i32 + 1, y as i32 + 1);
+                bounding_box = Some(bounding_box.map_or(pixel_box, |b| b.union(&pixel_box)));
+            }
+        }
+
+        let psnr = if squared_error == 0 {
+            f64::INFINITY
+        } else {
+            let mse = squared_error as f64 / (w * h * 4) as f64;
+            10.0 * (255.0 * 255.0 / mse).log10()
+        };
+        let heatmap = if differing_pixels == 0 {
+            None
+        } else {
+            Some(heatmap(size.w, size.h, &deltas)?)
+        };
+        Ok(ImageDiff {
+            max_delta,
+            differing_pixels,
+            psnr,
+            bounding_box,
+            heatmap,
+        })
+    }
+}
+
+fn heatmap(w: i32, h: i32, deltas: &[u8]) -> Result<Image, Error> {
+    let mut image = Image::new(w, h, Format::PRgb32)?;
+    let stride = image.stride();
+    let pixels = image.pixels_mut()?;
+    for (y, row_deltas) in deltas.chunks_exact(w as usize).enumerate() {
+        let row = &mut pixels[y * stride..][..w as usize * 4];
+        for (bytes, &d) in row.chunks_exact_mut(4).zip(row_deltas) {
+            let color = match d {
+                0 => Rgba32::new(0, 0, 0, 255),
+                d => Rgba32::new(255, 255 - d, 0, 255),
+            };
+            store(Format::PRgb32, color, bytes);
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::{PIXELS, image};
+
+    #[test]
+    fn test_compare_identical() {
+        let a = image(3, Format::PRgb32, &PIXELS);
+        let diff = a.compare(&image(3, Format::PRgb32, &PIXELS)).unwrap();
+        assert!(diff.is_identical());
+        assert_eq!(diff.psnr, f64::INFINITY);
+        assert_eq!(diff.max_delta, 0);
+        assert!(diff.bounding_box.is_none());
+        assert!(diff.heatmap.is_none());
+    }
+
+    #[test]
+    fn test_compare_one_pixel() {
+        let a = image(3, Format::PRgb32, &PIXELS);
+        let mut pixels = PIXELS;
+        pixels[5] = 0xFF00000A;
+        let diff = a.compare(&image(3, Format::PRgb32, &pixels)).unwrap();
+        assert_eq!(diff.max_delta, 10);
+        assert_eq!(diff.differing_pixels, 1);
+        assert_eq!(diff.bounding_box, Some(BoxI::new(2, 1, 3, 2)));
+        assert!(diff.is_within(10, 1));
+        assert!(!diff.is_within(9, 1));
+        // 100 squared error over 24 components.
+        let psnr = 10.0 * (255.0f64 * 255.0 * 24.0 / 100.0).log10();
+        assert!((diff.psnr - psnr).abs() < 1e-9);
+
+        let heatmap = diff.heatmap.unwrap();
+        let row = heatmap.row(1).unwrap();
+        assert_eq!(
+            load(Format::PRgb32, &row[8..12]),
+            Rgba32::new(255, 245, 0, 255)
+        );
+        assert_eq!(load(Format::PRgb32, &row[..4]), Rgba32::new(0, 0, 0, 255));
+    }
+
+    #[test]
+    fn test_compare_formats() {
+        let a8 = image(2, Format::A8, &[0x80000000, 0xFF000000]);
+        let prgb = image(2, Format::PRgb32, &[0x80000000, 0xFF000000]);
+        assert!(a8.compare(&prgb).unwrap().is_identical());
+
+        let tinted = image(2, Format::PRgb32, &[0x80010000, 0xFF000000]);
+        let diff = a8.compare(&tinted).unwrap();
+        assert_eq!((diff.max_delta, diff.differing_pixels), (1, 1));
+        assert_eq!(diff.bounding_box, Some(BoxI::new(0, 0, 1, 1)));
+    }
+
+    #[test]
+    fn test_compare_size_mismatch() {
+        let a = image(3, Format::PRgb32, &PIXELS);
+        let b = image(2, Format::PRgb32, &PIXELS);
+        assert!(a.compare(&b).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! Color matrix, convolution and per-pixel adjustment filters.
+//!
+//! Filters that change color work on unpremultiplied pixels and premultiply
+//! the result again, so semi-transparent pixels keep their hue. A8 images are
+//! treated as black pixels with the stored alpha.
+
+use crate::image::{Format, Image};
+use crate::{Error, Rgba32};
+
+/// A 4x5 matrix transforming unpremultiplied RGBA colors, like the SVG
+/// `feColorMatrix` filter primitive.
+///
+/// The rows compute the red, green, blue and alpha components of the result.
+/// The first four columns weigh the components of the source color, the last
+/// column is an offset. Components are in the `0.0..=1.0` range, the weights
+/// and offsets can be any value, results are clamped to the range.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorMatrix(pub [f32; 20]);
+
+impl ColorMatrix {
+    /// The matrix that leaves colors unchanged.
+    pub const IDENTITY: ColorMatrix = ColorMatrix([
+        1.0, 0.0, 0.0, 0.0, 0.0, //
+        0.0, 1.0, 0.0, 0.0, 0.0, //
+        0.0, 0.0, 1.0, 0.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0, 0.0, //
+    ]);
+
+    /// Creates a matrix from the 3x3 matrix applied to the color components,
+    /// keeping alpha.
+    fn from_rgb(m: [f32; 9]) -> ColorMatrix {
+        ColorMatrix([
+            m[0], m[1], m[2], 0.0, 0.0, //
+            m[3], m[4], m[5], 0.0, 0.0, //
+            m[6], m[7], m[8], 0.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0, 0.0, //
+        ])
+    }
+
+    /// Converts colors to grayscale, `amount` of `0.0` leaves them unchanged
+    /// and `1.0` converts them completely.
+    pub fn grayscale(amount: f32) -> ColorMatrix {
+        let s = 1.0 - amount.clamp(0.0, 1.0);
+        ColorMatrix::from_rgb([
+            0.2126 + 0.7874 * s,
+            0.7152 - 0.7152 * s,
+            0.0722 - 0.0722 * s,
+            0.2126 - 0.2126 * s,
+            0.7152 + 0.2848 * s,
+            0.0722 - 0.0722 * s,
+            0.2126 - 0.2126 * s,
+            0.7152 - 0.7152 * s,
+            0.0722 + 0.9278 * s,
+        ])
+    }
+
+    /// Converts colors to sepia, `amount` of `0.0` leaves them unchanged and
+    /// `1.0` converts them completely.
+    pub fn sepia(amount: f32) -> ColorMatrix {
+        let s = 1.0 - amount.clamp(0.0, 1.0);
+        ColorMatrix::from_rgb([
+            0.393 + 0.607 * s,
+            0.769 - 0.769 * s,
+            0.189 - 0.189 * s,
+            0.349 - 0.349 * s,
+            0.686 + 0.314 * s,
+            0.168 - 0.168 * s,
+            0.272 - 0.272 * s,
+            0.534 - 0.534 * s,
+            0.131 + 0.869 * s,
+        ])
+    }
+
+    /// Scales the saturation, `0.0` removes all color and `1.0` leaves colors
+    /// unchanged.
+    pub fn saturate(s: f32) -> ColorMatrix {
+        ColorMatrix::from_rgb([
+            0.213 + 0.787 * s,
+            0.715 - 0.715 * s,
+            0.072 - 0.072 * s,
+            0.213 - 0.213 * s,
+            0.715 + 0.285 * s,
+            0.072 - 0.072 * s,
+            0.213 - 0.213 * s,
+            0.715 - 0.715 * s,
+            0.072 + 0.928 * s,
+        ])
+    }
+
+    /// Rotates the hue by `angle` radians.
+    pub fn hue_rotate(angle: f32) -> ColorMatrix {
+        let (sin, cos) = angle.sin_cos();
+        ColorMatrix::from_rgb([
+            0.213 + cos * 0.787 - sin * 0.213,
+            0.715 - cos * 0.715 - sin * 0.715,
+            0.072 - cos * 0.072 + sin * 0.928,
+            0.213 - cos * 0.213 + sin * 0.143,
+            0.715 + cos * 0.285 + sin * 0.140,
+            0.072 - cos * 0.072 - sin * 0.283,
+            0.213 - cos * 0.213 - sin * 0.787,
+            0.715 - cos * 0.715 + sin * 0.715,
+            0.072 + cos * 0.928 + sin * 0.072,
+        ])
+    }
+
+    /// Multiplies the color components by `amount`.
+    pub fn brightness(amount: f32) -> ColorMatrix {
+        let b = amount.max(0.0);
+        ColorMatrix::from_rgb([b, 0.0, 0.0, 0.0, b, 0.0, 0.0, 0.0, b])
+    }
+
+    /// Scales the distance of the color components from the middle gray,
+    /// `0.0` results in gray and `1.0` leaves colors unchanged.
+    pub fn contrast(amount: f32) -> ColorMatrix {
+        let c = amount.max(0.0);
+        let offset = 0.5 - 0.5 * c;
+        ColorMatrix([
+            c, 0.0, 0.0, 0.0, offset, //
+            0.0, c, 0.0, 0.0, offset, //
+            0.0, 0.0, c, 0.0, offset, //
+            0.0, 0.0, 0.0, 1.0, 0.0, //
+        ])
+    }
+
+    /// Returns the matrix that applies `self` and then `next`.
+    pub fn then(&self, next: &ColorMatrix) -> ColorMatrix {
+        let (a, b) = (&self.0, &next.0);
+        let mut m = [0.0; 20];
+        for row in 0..4 {
+            for col in 0..5 {
+                let mut v = (0..4).map(|k| b[row * 5 + k] * a[k * 5 + col]).sum::<f32>();
+                if col == 4 {
+                    v += b[row * 5 + 4];
+                }
+                m[row * 5 + col] = v;
+            }
+        }
+        ColorMatrix(m)
+    }
+
+    /// Transforms the unpremultiplied `color`.
+    pub fn transform(&self, color: Rgba32) -> Rgba32 {
+        let m = &self.0;
+        let c = [color.r(), color.g(), color.b(), color.a()].map(|v| v as f32 / 255.0);
+        let row = |i: usize| {
+            let v = m[i * 5] * c[0] + m[i * 5 + 1] * c[1] + m[i * 5 + 2] * c[2];
+            unit_to_u8(v + m[i * 5 + 3] * c[3] + m[i * 5 + 4])
+        };
+        Rgba32::new(row(0), row(1), row(2), row(3))
+    }
+}
+
+impl Default for ColorMatrix {
+    #[inline]
+    fn default() -> Self {
+        ColorMatrix::IDENTITY
+    }
+}
+
+impl From<[f32; 20]> for ColorMatrix {
+    #[inline]
+    fn from(m: [f32; 20]) -> Self {
+        ColorMatrix(m)
+    }
+}
+
+#[inline]
+fn unit_to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// A square convolution kernel, like the SVG `feConvolveMatrix` filter
+/// primitive.
+///
+/// The weights are laid out row by row and centered on the filtered pixel.
+/// Pixels beyond the edges repeat the edge pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvolutionKernel {
+    size: usize,
+    weights: Vec<f32>,
+    divisor: f32,
+    bias: f32,
+    preserve_alpha: bool,
+}
+
+impl ConvolutionKernel {
+    /// Creates a `size` by `size` kernel, `size` must be odd and `weights`
+    /// must hold `size * size` values.
+    ///
+    /// The divisor defaults to the sum of the weights, or `1.0` if they sum
+    /// up to zero.
+    pub fn new(size: usize, weights: &[f32]) -> Option<ConvolutionKernel> {
+        if size % 2 != 1 || weights.len() != size * size {
+            return None;
+        }
+        let sum: f32 = weights.iter().sum();
+        Some(ConvolutionKernel {
+            size,
+            weights: weights.to_vec(),
+            divisor: if sum == 0.0 { 1.0 } else { sum },
+            bias: 0.0,
+            preserve_alpha: false,
+        })
+    }
+
+    /// Sharpens edges.
+    pub fn sharpen() -> ConvolutionKernel {
+        let weights = [0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0];
+        ConvolutionKernel::new(3, &weights).unwrap()
+    }
+
+    /// Makes edges look raised, lit from the top left.
+    pub fn emboss() -> ConvolutionKernel {
+        let weights = [-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0];
+        ConvolutionKernel::new(3, &weights)
+            .unwrap()
+            .with_preserve_alpha(true)
+    }
+
+    /// Keeps only edges, flat areas turn black.
+    pub fn edge_detect() -> ConvolutionKernel {
+        let weights = [-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0];
+        ConvolutionKernel::new(3, &weights)
+            .unwrap()
+            .with_preserve_alpha(true)
+    }
+
+    /// Sets the value the weighted sum is divided by, zero is ignored.
+    pub fn with_divisor(mut self, divisor: f32) -> Self {
+        if divisor != 0.0 {
+            self.divisor = divisor;
+        }
+        self
+    }
+
+    /// Sets the value, in the `0.0..=1.0` range, added to the result.
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    /// Whether only the color components are convolved, on unpremultiplied
+    /// pixels, keeping the alpha of each pixel.
+    pub fn with_preserve_alpha(mut self, preserve_alpha: bool) -> Self {
+        self.preserve_alpha = preserve_alpha;
+        self
+    }
+
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+}
+
+/// Reads the pixel of `format` stored in `bytes` as a premultiplied color.
+#[inline]
+pub(super) fn load(format: Format, bytes: &[u8]) -> Rgba32 {
+    match format {
+        Format::A8 => Rgba32::new(0, 0, 0, bytes[0]),
+        Format::PRgb32 => Rgba32::from_value(u32::from_ne_bytes(bytes.try_into().unwrap())),
+        Format::XRgb32 => {
+            Rgba32::from_value(u32::from_ne_bytes(bytes.try_into().unwrap()) | 0xFF000000)
+        },
+    }
+}
+
+/// Stores the premultiplied `color` as a pixel of `format` into `bytes`.
+#[inline]
+pub(super) fn store(format: Format, color: Rgba32, bytes: &mut [u8]) {
+    match format {
+        Format::A8 => bytes[0] = color.a(),
+        Format::PRgb32 => bytes.copy_from_slice(&color.value.to_ne_bytes()),
+        Format::XRgb32 => bytes.copy_from_slice(&(color.value | 0xFF000000).to_ne_bytes()),
+    }
+}
+
+impl Image {
+    /// Replaces every pixel by the result of `f`, called with the coordinates
+    /// and the premultiplied color of the pixel.
+    fn update_pixels<F>(&mut self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize, Rgba32) -> Rgba32,
+    {
+        let (size, format, stride) = (self.size(), self.format(), self.stride());
+        let bpp = format.bytes_per_pixel();
+        let row_len = size.w.max(0) as usize * bpp;
+        let pixels = self.pixels_mut()?;
+        for y in 0..size.h.max(0) as usize {
+            let row = &mut pixels[y * stride..][..row_len];
+            for (x, bytes) in row.chunks_exact_mut(bpp).enumerate() {
+                store(format, f(x, y, load(format, bytes)), bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the unpremultiplied color of every pixel by the result of
+    /// `f`.
+    fn map_colors<F>(&mut self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(Rgba32) -> Rgba32,
+    {
+        self.update_pixels(|_, _, color| f(color.unpremultiply()).premultiply())
+    }
+
+    /// Transforms every pixel by the color matrix `m`.
+    pub fn apply_color_matrix(&mut self, m: &ColorMatrix) -> Result<(), Error> {
+        self.map_colors(|color| m.transform(color))
+    }
+
+    /// Multiplies the color components by `amount`, see
+    /// [`ColorMatrix::brightness`].
+    pub fn adjust_brightness(&mut self, amount: f32) -> Result<(), Error> {
+        self.apply_color_matrix(&ColorMatrix::brightness(amount))
+    }
+
+    /// Scales the contrast by `amount`, see [`ColorMatrix::contrast`].
+    pub fn adjust_contrast(&mut self, amount: f32) -> Result<(), Error> {
+        self.apply_color_matrix(&ColorMatrix::contrast(amount))
+    }
+
+    /// Raises the color components, in the `0.0..=1.0` range, to the power of
+    /// `gamma`.
+    pub fn adjust_gamma(&mut self, gamma: f32) -> Result<(), Error> {
+        let mut lut = [0u8; 256];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = unit_to_u8((i as f32 / 255.0).powf(gamma));
+        }
+        self.map_colors(|c| {
+            Rgba32::new(
+                lut[c.r() as usize],
+                lut[c.g() as usize],
+                lut[c.b() as usize],
+                c.a(),
+            )
+        })
+    }
+
+    /// Turns pixels with a luminance of at least `level` white and all others
+    /// black, keeping alpha.
+    ///
+    /// The alpha of A8 images is thresholded instead.
+    pub fn threshold(&mut self, level: u8) -> Result<(), Error> {
+        if self.format() == Format::A8 {
+            return self.update_pixels(|_, _, c| {
+                Rgba32::new(0, 0, 0, if c.a() >= level { 255 } else { 0 })
+            });
+        }
+        self.map_colors(|c| {
+            let luminance = 0.2126 * c.r() as f32 + 0.7152 * c.g() as f32 + 0.0722 * c.b() as f32;
+            let v = if luminance.round() >= level as f32 {
+                255
+            } else {
+                0
+            };
+            Rgba32::new(v, v, v, c.a())
+        })
+    }
+
+    /// Inverts the color components, keeping alpha.
+    ///
+    /// The alpha of A8 images is inverted instead.
+    pub fn invert(&mut self) -> Result<(), Error> {
+        if self.format() == Format::A8 {
+            return self.update_pixels(|_, _, c| Rgba32::new(0, 0, 0, 255 - c.a()));
+        }
+        self.map_colors(|c| Rgba32::new(255 - c.r(), 255 - c.g(), 255 - c.b(), c.a()))
+    }
+
+    /// Convolves the image with `kernel`.
+    ///
+    /// Unless the kernel preserves alpha, all components of the premultiplied
+    /// pixels are convolved and the color components are clamped to the
+    /// resulting alpha.
+    pub fn convolve(&mut self, kernel: &ConvolutionKernel) -> Result<(), Error> {
+        let (size, format, stride) = (self.size(), self.format(), self.stride());
+        let (w, h) = (size.w.max(0) as usize, size.h.max(0) as usize);
+        let bpp = format.bytes_per_pixel();
+        let pixels = self.pixels();
+        let mut src = Vec::with_capacity(w * h);
+        for y in 0..h {
+            let row = &pixels[y * stride..][..w * bpp];
+            src.extend(row.chunks_exact(bpp).map(|bytes| {
+                let color = load(format, bytes);
+                if kernel.preserve_alpha {
+                    color.unpremultiply()
+                } else {
+                    color
+                }
+            }));
+        }
+
+        let r = kernel.size / 2;
+        let bias = kernel.bias * 255.0;
+        self.update_pixels(|x, y, color| {
+            let mut sum = [0.0f32; 4];
+            for (i, &weight) in kernel.weights.iter().enumerate() {
+                let sx = (x + i % kernel.size).saturating_sub(r).min(w - 1);
+                let sy = (y + i / kernel.size).saturating_sub(r).min(h - 1);
+                let s = src[sy * w + sx];
+                for (sum, v) in sum.iter_mut().zip([s.r(), s.g(), s.b(), s.a()]) {
+                    *sum += weight * v as f32;
+                }
+            }
+            let [r, g, b, a] =
+                sum.map(|v| (v / kernel.divisor + bias).clamp(0.0, 255.0).round() as u8);
+            if kernel.preserve_alpha {
+                Rgba32::new(r, g, b, color.a()).premultiply()
+            } else {
+                Rgba32::new(r.min(a), g.min(a), b.min(a), a)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::{PIXELS, image, values};
+
+    #[test]
+    fn test_color_matrix() {
+        let color = Rgba32::new(200, 100, 50, 128);
+        assert_eq!(ColorMatrix::IDENTITY.transform(color), color);
+        assert_eq!(ColorMatrix::saturate(1.0).transform(color), color);
+        assert_eq!(ColorMatrix::hue_rotate(0.0).transform(color), color);
+
+        let gray = ColorMatrix::grayscale(1.0).transform(color);
+        assert_eq!((gray.r(), gray.a()), (gray.b(), 128));
+        assert_eq!(gray.r(), gray.g());
+
+        let m = ColorMatrix::brightness(0.5).then(&ColorMatrix::contrast(3.0));
+        // 200 / 255 * 0.5 * 3 - 1 = 45 / 255
+        assert_eq!(m.transform(color).r(), 45);
+        assert_eq!(m.transform(color).a(), 128);
+
+        assert!(ConvolutionKernel::new(2, &[1.0; 4]).is_none());
+        assert_eq!(ConvolutionKernel::edge_detect().divisor, 1.0);
+    }
+
+    #[test]
+    fn test_convolve() {
+        let mut img = image(3, Format::PRgb32, &PIXELS);
+        let identity = [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        img.convolve(&ConvolutionKernel::new(3, &identity).unwrap())
+            .unwrap();
+        assert_eq!(values(&img), PIXELS);
+
+        let mut flat = image(3, Format::PRgb32, &[0xFF336699; 9]);
+        flat.convolve(&ConvolutionKernel::sharpen()).unwrap();
+        assert_eq!(values(&flat), [0xFF336699; 9]);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let mut img = image(2, Format::PRgb32, &[0x80808080, 0xFF202020]);
+        img.threshold(200).unwrap();
+        assert_eq!(values(&img), [0x80808080, 0xFF000000]);
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut img = image(2, Format::PRgb32, &[0xFF102030, 0x80000000]);
+        img.invert().unwrap();
+        assert_eq!(values(&img), [0xFFEFDFCF, 0x80808080]);
+    }
+
+    #[test]
+    fn test_gamma() {
+        // Gamma applies to the unpremultiplied colors, both are half gray.
+        let mut img = image(2, Format::PRgb32, &[0x80404040, 0xFF808080]);
+        img.adjust_gamma(2.0).unwrap();
+        assert_eq!(values(&img), [0x80202020, 0xFF404040]);
+    }
+}
@@ -0,0 +1,311 @@
+//! Cropping, sub-images and geometric transforms of images.
+
+use crate::context::{CompOp, Context};
+use crate::geometry::{Matrix2D, Rect, RectI, SizeI};
+use crate::image::{Format, Image, ScaleFilter};
+use crate::pattern::{Pattern, PatternQuality};
+use crate::{Error, ExtendMode, err_to_result};
+
+/// Returns whether `area` is non-empty and within an image of `size`.
+fn area_within(size: SizeI, area: &RectI) -> bool {
+    area.x >= 0
+        && area.y >= 0
+        && area.w > 0
+        && area.h > 0
+        && area.x as i64 + area.w as i64 <= size.w as i64
+        && area.y as i64 + area.h as i64 <= size.h as i64
+}
+
+/// A read-only view into an area of another [`Image`], created by
+/// [`Image::sub_image`].
+///
+/// The view borrows the image and reads its pixels in place. Use
+/// [`SubImage::to_image`] to get an image of its own or
+/// [`Context::blit_sub_image`] to render it.
+#[derive(Copy, Clone, Debug)]
+pub struct SubImage<'a> {
+    source: &'a Image,
+    area: RectI,
+}
+
+impl<'a> SubImage<'a> {
+    /// The image this is a view into.
+    #[inline]
+    pub fn source(&self) -> &'a Image {
+        self.source
+    }
+
+    /// The area of the source image covered by the view.
+    #[inline]
+    pub fn area(&self) -> RectI {
+        self.area
+    }
+
+    #[inline]
+    pub fn size(&self) -> SizeI {
+        SizeI::new(self.area.w, self.area.h)
+    }
+
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.source.format()
+    }
+
+    /// The pixels of row `y` of the view, or `None` if `y` is out of bounds.
+    pub fn row(&self, y: i32) -> Option<&'a [u8]> {
+        if !(0..self.area.h).contains(&y) {
+            return None;
+        }
+        let bpp = self.format().bytes_per_pixel();
+        let row = self.source.row(self.area.y + y)?;
+        Some(&row[self.area.x as usize * bpp..][..self.area.w as usize * bpp])
+    }
+
+    /// Copies the pixels of the view into an image of its own.
+    pub fn to_image(&self) -> Result<Image, Error> {
+        self.source.crop(&self.area)
+    }
+
+    /// Returns a copy of the `area` of the view.
+    ///
+    /// Fails if `area` is empty or not within the view.
+    pub fn crop(&self, area: &RectI) -> Result<Image, Error> {
+        if !area_within(self.size(), area) {
+            return Err(Error);
+        }
+        let source_area = RectI::new(self.area.x + area.x, self.area.y + area.y, area.w, area.h);
+        self.source.crop(&source_area)
+    }
+}
+
+impl Image {
+    /// Fails unless `area` is a non-empty area within the image.
+    fn check_area(&self, area: &RectI) -> Result<(), Error> {
+        if area_within(self.size(), area) {
+            Ok(())
+        } else {
+            Err(Error)
+        }
+    }
+
+    /// Creates an image of `w` by `h` pixels with the format of this image
+    /// and fills it row by row with the pixels returned by `src`, which maps
+    /// destination coordinates to source coordinates.
+    fn remap<F>(&self, w: i32, h: i32, src: F) -> Result<Image, Error>
+    where
+        F: Fn(usize, usize) -> (usize, usize),
+    {
+        let format = self.format();
+        let bpp = format.bytes_per_pixel();
+        let mut dst = Image::new(w, h, format)?;
+        let (src_pixels, src_stride) = (self.pixels(), self.stride());
+        let dst_stride = dst.stride();
+        let dst_pixels = dst.pixels_mut()?;
+        for y in 0..h as usize {
+            let row = &mut dst_pixels[y * dst_stride..][..w as usize * bpp];
+            for (x, pixel) in row.chunks_exact_mut(bpp).enumerate() {
+                let (sx, sy) = src(x, y);
+                pixel.copy_from_slice(&src_pixels[sy * src_stride + sx * bpp..][..bpp]);
+            }
+        }
+        Ok(dst)
+    }
+
+    /// Returns a copy of the `area` of the image.
+    ///
+    /// Fails if `area` is empty or not within the image.
+    pub fn crop(&self, area: &RectI) -> Result<Image, Error> {
+        self.check_area(area)?;
+        let format = self.format();
+        let row_len = area.w as usize * format.bytes_per_pixel();
+        let offset = area.y as usize * self.stride() + area.x as usize * format.bytes_per_pixel();
+        let mut dst = Image::new(area.w, area.h, format)?;
+        let (src_pixels, src_stride) = (&self.pixels()[offset..], self.stride());
+        let dst_stride = dst.stride();
+        let dst_pixels = dst.pixels_mut()?;
+        for y in 0..area.h as usize {
+            dst_pixels[y * dst_stride..][..row_len]
+                .copy_from_slice(&src_pixels[y * src_stride..][..row_len]);
+        }
+        Ok(dst)
+    }
+
+    /// Returns a view of the `area` of the image without copying any pixels.
+    ///
+    /// Fails if `area` is empty or not within the image.
+    pub fn sub_image(&self, area: &RectI) -> Result<SubImage<'_>, Error> {
+        self.check_area(area)?;
+        Ok(SubImage {
+            source: self,
+            area: *area,
+        })
+    }
+
+    /// Returns a copy of the image scaled to `size`, leaving this image
+    /// unchanged.
+    pub fn scaled(&self, size: SizeI, filter: ScaleFilter) -> Result<Image, Error> {
+        let mut dst = Image::default();
+        err_to_result(unsafe {
+            ffi::bl_image_scale(&mut dst.0, &self.0, &raw const size as _, filter as i32)
+        })?;
+        Ok(dst)
+    }
+
+    /// Mirrors the image along its vertical axis.
+    pub fn flip_horizontal(&mut self) -> Result<(), Error> {
+        let (size, stride) = (self.size(), self.stride());
+        let bpp = self.format().bytes_per_pixel();
+        let w = size.w.max(0) as usize;
+        let pixels = self.pixels_mut()?;
+        for y in 0..size.h.max(0) as usize {
+            let row = &mut pixels[y * stride..][..w * bpp];
+            for x in 0..w / 2 {
+                for b in 0..bpp {
+                    row.swap(x * bpp + b, (w - 1 - x) * bpp + b);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Mirrors the image along its horizontal axis.
+    pub fn flip_vertical(&mut self) -> Result<(), Error> {
+        let (size, stride) = (self.size(), self.stride());
+        let h = size.h.max(0) as usize;
+        let row_len = size.w.max(0) as usize * self.format().bytes_per_pixel();
+        let pixels = self.pixels_mut()?;
+        for y in 0..h / 2 {
+            let (top, bottom) = pixels.split_at_mut((h - 1 - y) * stride);
+            top[y * stride..][..row_len].swap_with_slice(&mut bottom[..row_len]);
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the image rotated by 90 degrees clockwise.
+    pub fn rotated_90(&self) -> Result<Image, Error> {
+        let size = self.size();
+        let h = size.h as usize;
+        self.remap(size.h, size.w, |x, y| (y, h - 1 - x))
+    }
+
+    /// Returns a copy of the image rotated by 180 degrees.
+    pub fn rotated_180(&self) -> Result<Image, Error> {
+        let size = self.size();
+        let (w, h) = (size.w as usize, size.h as usize);
+        self.remap(size.w, size.h, |x, y| (w - 1 - x, h - 1 - y))
+    }
+
+    /// Returns a copy of the image rotated by 270 degrees clockwise.
+    pub fn rotated_270(&self) -> Result<Image, Error> {
+        let size = self.size();
+        let w = size.w as usize;
+        self.remap(size.h, size.w, |x, y| (w - 1 - y, x))
+    }
+
+    /// Renders the image transformed by `transform` into a new PRgb32 image
+    /// of `size`, sampled with `quality`.
+    ///
+    /// Pixels not covered by the transformed image are transparent.
+    pub fn transformed(
+        &self,
+        transform: &Matrix2D,
+        size: SizeI,
+        quality: PatternQuality,
+    ) -> Result<Image, Error> {
+        let mut dst = Image::new(size.w, size.h, Format::PRgb32)?;
+        let src_size = self.size();
+        let pattern = Pattern::new(self, None, ExtendMode::PadXPadY, None)?;
+
+        let mut ctx = Context::default();
+        ctx.begin(&mut dst)?;
+        ctx.set_comp_op(CompOp::SrcCopy)?;
+        ctx.fill_all_rgba32(0)?;
+        ctx.set_pattern_quality(quality)?;
+        ctx.set_transform(transform)?;
+        ctx.set_fill_style_pattern(&pattern)?;
+        ctx.fill_rect(&Rect::new(0.0, 0.0, src_size.w as f64, src_size.h as f64))?;
+        ctx.end()?;
+        Ok(dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::{image, row_values, values};
+
+    // 3 by 2 pixels:
+    // A B C
+    // D E F
+    const PIXELS: [u32; 6] = [
+        0xFF000001, 0xFF000002, 0xFF000003, 0xFF000004, 0xFF000005, 0xFF000006,
+    ];
+
+    #[test]
+    fn test_rotate() {
+        let source = image(3, Format::PRgb32, &PIXELS);
+        let rotated = source.rotated_90().unwrap();
+        assert_eq!(rotated.size(), SizeI::new(2, 3));
+        // D A
+        // E B
+        // F C
+        let [a, b, c, d, e, f] = PIXELS;
+        assert_eq!(values(&rotated), [d, a, e, b, f, c]);
+        assert_eq!(values(&rotated.rotated_270().unwrap()), PIXELS);
+        assert_eq!(values(&source.rotated_270().unwrap()), [c, f, b, e, a, d]);
+        assert_eq!(values(&source.rotated_180().unwrap()), [f, e, d, c, b, a]);
+    }
+
+    #[test]
+    fn test_crop() {
+        let source = image(3, Format::PRgb32, &PIXELS);
+        let [.., e, f] = PIXELS;
+        let cropped = source.crop(&RectI::new(1, 1, 2, 1)).unwrap();
+        assert_eq!(values(&cropped), [e, f]);
+        assert!(source.crop(&RectI::new(2, 1, 2, 1)).is_err());
+        assert!(source.crop(&RectI::new(0, 0, 0, 1)).is_err());
+    }
+
+    #[test]
+    fn test_sub_image() {
+        let source = image(3, Format::PRgb32, &PIXELS);
+        let [_, b, c, _, e, f] = PIXELS;
+        // The view ends at the last pixel of the image.
+        let view = source.sub_image(&RectI::new(1, 0, 2, 2)).unwrap();
+        assert_eq!(view.size(), SizeI::new(2, 2));
+        assert_eq!(
+            (0..2)
+                .flat_map(|y| row_values(view.format(), view.row(y).unwrap()))
+                .collect::<Vec<_>>(),
+            [b, c, e, f]
+        );
+        assert!(view.row(2).is_none());
+        assert_eq!(values(&view.to_image().unwrap()), [b, c, e, f]);
+        assert_eq!(values(&view.crop(&RectI::new(1, 1, 1, 1)).unwrap()), [f]);
+        assert!(view.crop(&RectI::new(1, 1, 2, 1)).is_err());
+    }
+
+    #[test]
+    fn test_flip() {
+        // 2 by 3 pixels, the middle row stays in place.
+        let mut flipped = image(2, Format::PRgb32, &PIXELS);
+        flipped.flip_vertical().unwrap();
+        let [a, b, c, d, e, f] = PIXELS;
+        assert_eq!(values(&flipped), [e, f, c, d, a, b]);
+        flipped.flip_horizontal().unwrap();
+        assert_eq!(values(&flipped), [f, e, d, c, b, a]);
+    }
+
+    #[test]
+    fn test_transformed_identity() {
+        let source = image(3, Format::PRgb32, &PIXELS);
+        let transformed = source
+            .transformed(
+                &Matrix2D::identity(),
+                source.size(),
+                PatternQuality::Nearest,
+            )
+            .unwrap();
+        assert_eq!(values(&transformed), PIXELS);
+    }
+}
@@ -5,19 +5,33 @@ mod error;
 pub use error::Error;
 pub(crate) use error::err_to_result;
 
+mod util;
+
+pub mod array;
+pub mod codec;
+pub mod color;
 pub mod context;
+pub mod file;
 pub mod geometry;
 pub mod gradient;
 pub mod image;
 pub mod path;
 pub mod pattern;
+pub mod region;
+pub mod runtime;
+pub mod string;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
+pub use array::Array;
+pub use color::{Rgba, Rgba32, Rgba64};
 pub use context::CompOp;
 pub use context::Context;
 pub use gradient::Gradient;
 pub use image::Image;
 pub use path::Path;
 pub use pattern::Pattern;
+pub use region::Region;
 
 use ffi::BLExtendMode::*;
 bl_enum! {
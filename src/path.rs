@@ -1,10 +1,458 @@
+use std::any::Any;
+use std::ops::{Range, RangeBounds};
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::{fmt, mem, ptr, slice};
+
+use bitflags::bitflags;
 use ffi;
 
+use crate::geometry::{
+    self, FillRule, HitTest, Matrix2D, Point, Rect, StrokeCap, StrokeJoin, StrokeTransformOrder,
+};
+use crate::util::bl_range;
 use crate::{Error, err_to_result};
 
+mod dash;
+
+mod flatten;
+pub use self::flatten::{Flatten, Polyline};
+
+mod measure;
+pub use self::measure::PathMeasure;
+
+mod svg;
+pub use self::svg::{ParseSvgPathError, SvgPathErrorKind};
+
+#[repr(transparent)]
 pub struct Path(pub(crate) ffi::BLPathCore);
 
+use ffi::BLPathFlags::*;
+bitflags! {
+    /// Path information flags, see [`Path::info_flags`].
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct PathFlags: u32 {
+        /// Path is empty (no commands or close commands only).
+        const EMPTY    = BL_PATH_FLAG_EMPTY as u32;
+        /// Path contains multiple figures.
+        const MULTIPLE = BL_PATH_FLAG_MULTIPLE as u32;
+        /// Path contains one or more quad curves.
+        const QUADS    = BL_PATH_FLAG_QUADS as u32;
+        /// Path contains one or more conic curves.
+        const CONICS   = BL_PATH_FLAG_CONICS as u32;
+        /// Path contains one or more cubic curves.
+        const CUBICS   = BL_PATH_FLAG_CUBICS as u32;
+        /// Path is invalid.
+        const INVALID  = BL_PATH_FLAG_INVALID as u32;
+    }
+}
+
+use ffi::BLPathCmd::*;
+bl_enum! {
+    /// Path command.
+    pub enum PathCmd {
+        /// Move-to command (starts a new figure).
+        Move   = BL_PATH_CMD_MOVE,
+        /// On-path command (interpreted as line-to or the end of a curve).
+        On     = BL_PATH_CMD_ON,
+        /// Quad-to control point.
+        Quad   = BL_PATH_CMD_QUAD,
+        /// Conic-to control point.
+        Conic  = BL_PATH_CMD_CONIC,
+        /// Cubic-to control point (always used as a pair of commands).
+        Cubic  = BL_PATH_CMD_CUBIC,
+        /// Close path.
+        Close  = BL_PATH_CMD_CLOSE,
+        /// Conic weight, stored as the x coordinate of its vertex.
+        Weight = BL_PATH_CMD_WEIGHT,
+    }
+    Default => Move
+}
+
+use ffi::BLPathReverseMode::*;
+bl_enum! {
+    /// Path reversal mode, see [`Path::reverse`].
+    pub enum PathReverseMode {
+        /// Reverse each figure and their order as well (default).
+        Complete = BL_PATH_REVERSE_MODE_COMPLETE,
+        /// Reverse each figure separately (keeps their order).
+        Separate = BL_PATH_REVERSE_MODE_SEPARATE,
+    }
+    Default => Complete
+}
+
+use ffi::BLModifyOp::*;
+bl_enum! {
+    /// Modify operation, see [`Path::modify_op`].
+    pub enum ModifyOp {
+        /// Assign operation, which reserves space only to fit the requested
+        /// input.
+        AssignFit  = BL_MODIFY_OP_ASSIGN_FIT,
+        /// Assign operation, which takes into consideration successive
+        /// appends.
+        AssignGrow = BL_MODIFY_OP_ASSIGN_GROW,
+        /// Append operation, which reserves space only to fit the current and
+        /// appended content.
+        AppendFit  = BL_MODIFY_OP_APPEND_FIT,
+        /// Append operation, which takes into consideration successive
+        /// appends.
+        AppendGrow = BL_MODIFY_OP_APPEND_GROW,
+    }
+    Default => AssignFit
+}
+
+/// A single segment of a [`Path`] as yielded by [`Path::segments`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// Starts a new figure at the given point.
+    MoveTo(Point),
+    /// A straight line to the given point.
+    LineTo(Point),
+    /// A quadratic curve with a control point and an end point.
+    QuadTo(Point, Point),
+    /// A cubic curve with two control points and an end point.
+    CubicTo(Point, Point, Point),
+    /// A rational quadratic curve with a control point, an end point and the
+    /// weight of the control point.
+    ConicTo(Point, Point, f64),
+    /// Closes the current figure.
+    Close,
+}
+
 impl Path {
+    /// Returns the number of vertices (and commands) stored in the path.
+    #[inline]
+    pub fn len(&self) -> usize {
+        unsafe { ffi::bl_path_get_size(&self.0) }
+    }
+    /// Returns true if the path contains no vertices.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Returns the number of vertices the path can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        unsafe { ffi::bl_path_get_capacity(&self.0) }
+    }
+    /// Returns the path's commands, see [`PathCmd`] for their meaning.
+    #[inline]
+    pub fn commands(&self) -> &[u8] {
+        let len = self.len();
+        if len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(ffi::bl_path_get_command_data(&self.0), len) }
+    }
+    /// Returns the path's vertices, there is exactly one vertex per command.
+    #[inline]
+    pub fn vertices(&self) -> &[Point] {
+        let len = self.len();
+        if len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(ffi::bl_path_get_vertex_data(&self.0) as *const Point, len) }
+    }
+    /// Returns the range of vertices describing the figure that contains the
+    /// vertex at `index`.
+    #[inline]
+    pub fn figure_range(&self, index: usize) -> Result<Range<usize>, Error> {
+        let mut range = ffi::BLRange { start: 0, end: 0 };
+        err_to_result(unsafe { ffi::bl_path_get_figure_range(&self.0, index, &mut range) })?;
+        Ok(range.start..range.end)
+    }
+    /// Returns the last vertex of the path, or `None` if the path is empty or
+    /// ends with a close command.
+    #[inline]
+    pub fn last_vertex(&self) -> Option<Point> {
+        let mut vtx = Point::default();
+        err_to_result(unsafe { ffi::bl_path_get_last_vertex(&self.0, &raw mut vtx as _) })
+            .ok()
+            .map(|_| vtx)
+    }
+    /// Clears the content of the path, keeping its capacity.
+    #[inline]
+    pub fn clear(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_path_clear(&mut self.0) })
+    }
+    /// Shrinks the capacity of the path to fit its current length.
+    #[inline]
+    pub fn shrink(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_path_shrink(&mut self.0) })
+    }
+    /// Reserves capacity for at least `n` vertices.
+    #[inline]
+    pub fn reserve(&mut self, n: usize) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_path_reserve(&mut self.0, n) })
+    }
+    /// Prepares the path for `n` new vertices according to the given
+    /// [`ModifyOp`] and returns the command and vertex storage of these
+    /// vertices, which the caller is expected to fill.
+    ///
+    /// The new vertices are initialized as move-to commands at the origin.
+    pub fn modify_op(
+        &mut self,
+        op: ModifyOp,
+        n: usize,
+    ) -> Result<(&mut [u8], &mut [Point]), Error> {
+        let mut cmd_data = ptr::null_mut();
+        let mut vtx_data = ptr::null_mut();
+        err_to_result(unsafe {
+            ffi::bl_path_modify_op(&mut self.0, op as _, n, &mut cmd_data, &mut vtx_data)
+        })?;
+        if n == 0 {
+            return Ok((&mut [], &mut []));
+        }
+        unsafe {
+            ptr::write_bytes(cmd_data, BL_PATH_CMD_MOVE as u8, n);
+            ptr::write_bytes(vtx_data, 0, n);
+            Ok((
+                slice::from_raw_parts_mut(cmd_data, n),
+                slice::from_raw_parts_mut(vtx_data as *mut Point, n),
+            ))
+        }
+    }
+    /// Replaces the vertex at `index` by `(x, y)`. The command is replaced as
+    /// well unless `cmd` is `None`.
+    #[inline]
+    pub fn set_vertex_at<C>(&mut self, index: usize, cmd: C, x: f64, y: f64) -> Result<(), Error>
+    where
+        C: Into<Option<PathCmd>>,
+    {
+        let cmd = cmd
+            .into()
+            .map_or(ffi::BLPathCmdExtra::BL_PATH_CMD_PRESERVE as u32, u32::from);
+        err_to_result(unsafe { ffi::bl_path_set_vertex_at(&mut self.0, index, cmd as _, x, y) })
+    }
+    /// Removes the vertices within the given range.
+    #[inline]
+    pub fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) -> Result<(), Error> {
+        let range = bl_range(range);
+        err_to_result(unsafe { ffi::bl_path_remove_range(&mut self.0, &range) })
+    }
+    /// Appends the vertices of `other` within the given range.
+    #[inline]
+    pub fn add_path<R: RangeBounds<usize>>(&mut self, other: &Path, range: R) -> Result<(), Error> {
+        let range = bl_range(range);
+        err_to_result(unsafe { ffi::bl_path_add_path(&mut self.0, &other.0, &range) })
+    }
+    /// Appends the vertices of `other` within the given range in reversed
+    /// order.
+    #[inline]
+    pub fn add_reversed_path<R: RangeBounds<usize>>(
+        &mut self,
+        other: &Path,
+        range: R,
+        mode: PathReverseMode,
+    ) -> Result<(), Error> {
+        let range = bl_range(range);
+        err_to_result(unsafe {
+            ffi::bl_path_add_reversed_path(&mut self.0, &other.0, &range, mode as _)
+        })
+    }
+    /// Transforms the vertices within the given range by the matrix `m`.
+    #[inline]
+    pub fn transform<R: RangeBounds<usize>>(
+        &mut self,
+        m: &Matrix2D,
+        range: R,
+    ) -> Result<(), Error> {
+        let range = bl_range(range);
+        err_to_result(unsafe { ffi::bl_path_transform(&mut self.0, &range, m as *const _ as _) })
+    }
+    /// Translates the vertices within the given range by `p`.
+    #[inline]
+    pub fn translate<R: RangeBounds<usize>>(&mut self, p: Point, range: R) -> Result<(), Error> {
+        let range = bl_range(range);
+        err_to_result(unsafe { ffi::bl_path_translate(&mut self.0, &range, &raw const p as _) })
+    }
+    /// Scales and translates the vertices within the given range so that
+    /// their bounding box fits `rect`.
+    #[inline]
+    pub fn fit_to<R: RangeBounds<usize>>(&mut self, rect: &Rect, range: R) -> Result<(), Error> {
+        let range = bl_range(range);
+        err_to_result(unsafe { ffi::bl_path_fit_to(&mut self.0, &range, rect as *const _ as _, 0) })
+    }
+    /// Strokes the vertices of `other` within the given range and appends the
+    /// resulting outline, which can then be filled like any other path.
+    #[inline]
+    pub fn add_stroked_path<R: RangeBounds<usize>>(
+        &mut self,
+        other: &Path,
+        range: R,
+        stroke_options: &StrokeOptions,
+        approximation_options: &ApproximationOptions,
+    ) -> Result<(), Error> {
+        let range = bl_range(range);
+        let approx = approximation_options.to_ffi();
+        err_to_result(unsafe {
+            ffi::bl_path_add_stroked_path(
+                &mut self.0,
+                &other.0,
+                &range,
+                stroke_options as *const _ as _,
+                &approx,
+            )
+        })
+    }
+    /// Strokes the vertices within the given range and streams the outline
+    /// into `sink` instead of appending it to a path.
+    ///
+    /// The stroker outputs the outline of every figure into three paths and
+    /// then calls `sink` with them and the range of input vertices they were
+    /// generated from. `a` holds the outer side of the outline, `b` the inner
+    /// side in reverse order and `c` an optional extra figure such as the end
+    /// cap; a sink reconstructing the whole outline appends `a`, the reversal
+    /// of `b` and `c`. The sink is responsible for clearing the paths if it
+    /// does not want the output to accumulate.
+    pub fn stroke_to_sink<R, F>(
+        &self,
+        range: R,
+        stroke_options: &StrokeOptions,
+        approximation_options: &ApproximationOptions,
+        sink: F,
+    ) -> Result<(), Error>
+    where
+        R: RangeBounds<usize>,
+        F: FnMut(&mut Path, &mut Path, &mut Path, Range<usize>) -> Result<(), Error>,
+    {
+        struct SinkData<F> {
+            sink: F,
+            error: Option<Error>,
+            panic: Option<std::boxed::Box<dyn Any + Send>>,
+        }
+
+        unsafe extern "C" fn sink_func<F>(
+            a: *mut ffi::BLPathCore,
+            b: *mut ffi::BLPathCore,
+            c: *mut ffi::BLPathCore,
+            input_start: usize,
+            input_end: usize,
+            user_data: *mut c_void,
+        ) -> ffi::BLResult
+        where
+            F: FnMut(&mut Path, &mut Path, &mut Path, Range<usize>) -> Result<(), Error>,
+        {
+            let data = unsafe { &mut *(user_data as *mut SinkData<F>) };
+            let (a, b, c) = unsafe {
+                (
+                    &mut *(a as *mut Path),
+                    &mut *(b as *mut Path),
+                    &mut *(c as *mut Path),
+                )
+            };
+            let sink = &mut data.sink;
+            match panic::catch_unwind(AssertUnwindSafe(|| sink(a, b, c, input_start..input_end))) {
+                Ok(Ok(())) => return ffi::BLResultCode::BL_SUCCESS as _,
+                Ok(Err(e)) => data.error = Some(e),
+                Err(payload) => data.panic = Some(payload),
+            }
+            ffi::BLResultCode::BL_ERROR_INVALID_STATE as _
+        }
+
+        let range = bl_range(range);
+        let approx = approximation_options.to_ffi();
+        let (mut a, mut b, mut c) = (Path::default(), Path::default(), Path::default());
+        let mut data = SinkData {
+            sink,
+            error: None,
+            panic: None,
+        };
+        let result = err_to_result(unsafe {
+            ffi::bl_path_stroke_to_sink(
+                &self.0,
+                &range,
+                stroke_options as *const _ as _,
+                &approx,
+                &mut a.0,
+                &mut b.0,
+                &mut c.0,
+                Some(sink_func::<F>),
+                &raw mut data as _,
+            )
+        });
+        if let Some(payload) = data.panic {
+            panic::resume_unwind(payload);
+        }
+        match data.error {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+    /// Reverses the vertices within the given range according to `mode`.
+    pub fn reverse<R: RangeBounds<usize>>(
+        &mut self,
+        range: R,
+        mode: PathReverseMode,
+    ) -> Result<(), Error> {
+        let ffi::BLRange { start, end } = bl_range(range);
+        let (start, end) = (start.min(self.len()), end.min(self.len()));
+        let mut reversed = Path::default();
+        reversed.reserve(self.len())?;
+        reversed.add_path(self, ..start)?;
+        reversed.add_reversed_path(self, start..end, mode)?;
+        reversed.add_path(self, end..)?;
+        std::mem::swap(self, &mut reversed);
+        Ok(())
+    }
+    /// Returns the path's [`PathFlags`].
+    #[inline]
+    pub fn info_flags(&self) -> Result<PathFlags, Error> {
+        let mut flags = 0;
+        err_to_result(unsafe { ffi::bl_path_get_info_flags(&self.0, &mut flags) })?;
+        Ok(PathFlags::from_bits_truncate(flags))
+    }
+    /// Returns the bounding box of all vertices and control points of the
+    /// path.
+    ///
+    /// Control points of curves are not necessarily on the curve, so the
+    /// control box is usually larger than the [`bounding_box`].
+    ///
+    /// [`bounding_box`]: Path::bounding_box
+    #[inline]
+    pub fn control_box(&self) -> Result<geometry::Box, Error> {
+        let mut bx = geometry::Box::default();
+        err_to_result(unsafe { ffi::bl_path_get_control_box(&self.0, &raw mut bx as _) })?;
+        Ok(bx)
+    }
+    /// Returns the bounding box of the path, computed from the curve extrema.
+    #[inline]
+    pub fn bounding_box(&self) -> Result<geometry::Box, Error> {
+        let mut bx = geometry::Box::default();
+        err_to_result(unsafe { ffi::bl_path_get_bounding_box(&self.0, &raw mut bx as _) })?;
+        Ok(bx)
+    }
+    /// Hit tests the given point `p` against the path using the given
+    /// [`FillRule`].
+    #[inline]
+    pub fn hit_test(&self, p: Point, fill_rule: FillRule) -> HitTest {
+        let result = unsafe { ffi::bl_path_hit_test(&self.0, &raw const p as _, fill_rule as _) };
+        (result as u32).into()
+    }
+    /// Returns the index of the vertex closest to `p` together with its
+    /// distance, or `None` if no vertex lies within `max_distance`.
+    #[inline]
+    pub fn closest_vertex(&self, p: Point, max_distance: f64) -> Option<(usize, f64)> {
+        let mut index = usize::MAX;
+        let mut distance = f64::NAN;
+        err_to_result(unsafe {
+            ffi::bl_path_get_closest_vertex(
+                &self.0,
+                &raw const p as _,
+                max_distance,
+                &mut index,
+                &mut distance,
+            )
+        })
+        .ok()
+        .filter(|_| index != usize::MAX)
+        .map(|_| (index, distance))
+    }
+    /// Returns an iterator over the segments of the path.
+    #[inline]
+    pub fn segments(&self) -> Segments<'_> {
+        Segments::new(self.commands(), self.vertices())
+    }
     #[inline]
     pub fn move_to(&mut self, x: f64, y: f64) -> Result<(), Error> {
         err_to_result(unsafe { ffi::bl_path_move_to(&mut self.0, x, y) })
@@ -25,8 +473,419 @@ impl Path {
     pub fn line_to(&mut self, x: f64, y: f64) -> Result<(), Error> {
         err_to_result(unsafe { ffi::bl_path_line_to(&mut self.0, x, y) })
     }
+    #[inline]
+    pub fn quad_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_path_quad_to(&mut self.0, x1, y1, x2, y2) })
+    }
+    #[inline]
+    pub fn conic_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, w: f64) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_path_conic_to(&mut self.0, x1, y1, x2, y2, w) })
+    }
+    /// Adds an elliptic arc from the current point to `(x1, y1)`, following
+    /// the semantics of the SVG `A` command. The rotation is in radians.
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn elliptic_arc_to(
+        &mut self,
+        rx: f64,
+        ry: f64,
+        x_axis_rotation: f64,
+        large_arc_flag: bool,
+        sweep_flag: bool,
+        x1: f64,
+        y1: f64,
+    ) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_path_elliptic_arc_to(
+                &mut self.0,
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc_flag,
+                sweep_flag,
+                x1,
+                y1,
+            )
+        })
+    }
+    #[inline]
+    pub fn close(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_path_close(&mut self.0) })
+    }
+
+    /// Returns an iterator over the figures of the path with all curves
+    /// approximated by line segments, using the flatten mode and tolerance of
+    /// the given `options`.
+    ///
+    /// The curves are flattened in Rust rather than by blend2d, so the points
+    /// differ from the ones blend2d's rasterizer and stroker produce, though
+    /// they stay within the same tolerance. The other fields of `options`
+    /// only apply to stroking and are ignored.
+    #[inline]
+    pub fn flatten(&self, options: &ApproximationOptions) -> Flatten<'_> {
+        Flatten::new(
+            self.segments(),
+            options.flatten_tolerance,
+            options.flatten_mode,
+        )
+    }
+
+    /// Returns a copy of this path with all curves replaced by line segments,
+    /// see [`Path::flatten`].
+    pub fn flattened(&self, options: &ApproximationOptions) -> Result<Path, Error> {
+        let mut path = Path::default();
+        for polyline in self.flatten(options) {
+            let (first, rest) = polyline.points.split_first().unwrap();
+            path.move_to(first.x, first.y)?;
+            for p in rest {
+                path.line_to(p.x, p.y)?;
+            }
+            if polyline.closed {
+                path.close()?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Measures the arc length of the path, approximating curves within
+    /// `tolerance`.
+    #[inline]
+    pub fn measure(&self, tolerance: f64) -> PathMeasure {
+        PathMeasure::new(self, tolerance)
+    }
+
+    /// Splits the path into dashes, alternating between `dashes` long on and
+    /// off intervals, starting `offset` units into the pattern.
+    ///
+    /// The pattern restarts at every figure and dashes crossing the start of
+    /// a closed figure are joined. Curves are preserved. An odd number of
+    /// dashes is repeated to get an even number, and an empty or invalid
+    /// pattern results in a copy of the path. Curves are measured within
+    /// `tolerance`, see [`Path::measure`].
+    ///
+    /// Fails if a figure would be split into more than a million dashes and
+    /// gaps.
+    pub fn dash(&self, dashes: &[f64], offset: f64, tolerance: f64) -> Result<Path, Error> {
+        dash::dash(self, dashes, offset, tolerance)
+    }
+
+    /// Parses SVG path data, as found in the `d` attribute of a `<path>`
+    /// element.
+    ///
+    /// All commands are supported in their absolute and relative forms. Arcs
+    /// are converted to curves by [`Path::elliptic_arc_to`].
+    pub fn from_svg_path_data(data: &str) -> Result<Path, ParseSvgPathError> {
+        svg::parse(data)
+    }
+
+    /// Serializes the path as SVG path data.
+    ///
+    /// Coordinates are written with at most `precision` fractional digits, or
+    /// with the shortest representation that round-trips exactly if `None`.
+    /// Conic segments have no SVG equivalent and are approximated by cubics.
+    pub fn to_svg_path_data<P: Into<Option<usize>>>(&self, precision: P) -> String {
+        svg::serialize(self, precision.into())
+    }
+}
+
+impl std::str::FromStr for Path {
+    type Err = ParseSvgPathError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Path::from_svg_path_data(s)
+    }
+}
+
+/// Options used to stroke paths, see [`Path::add_stroked_path`].
+#[repr(C)]
+pub struct StrokeOptions {
+    start_cap: u8,
+    end_cap: u8,
+    join: u8,
+    transform_order: u8,
+    reserved: [u8; 4],
+    width: f64,
+    miter_limit: f64,
+    dash_offset: f64,
+    dash_array: ffi::BLArrayCore,
+}
+
+impl StrokeOptions {
+    /// Creates stroke options with blend2d's defaults.
+    #[inline]
+    pub fn new() -> Self {
+        let mut options = mem::MaybeUninit::<StrokeOptions>::uninit();
+        unsafe {
+            ffi::bl_stroke_options_init(options.as_mut_ptr() as _);
+            options.assume_init()
+        }
+    }
+    #[inline]
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+    #[inline]
+    pub fn set_width(&mut self, width: f64) {
+        self.width = width;
+    }
+    #[inline]
+    pub fn miter_limit(&self) -> f64 {
+        self.miter_limit
+    }
+    #[inline]
+    pub fn set_miter_limit(&mut self, miter_limit: f64) {
+        self.miter_limit = miter_limit;
+    }
+    #[inline]
+    pub fn start_cap(&self) -> StrokeCap {
+        (self.start_cap as u32).into()
+    }
+    #[inline]
+    pub fn set_start_cap(&mut self, cap: StrokeCap) {
+        self.start_cap = cap as u8;
+    }
+    #[inline]
+    pub fn end_cap(&self) -> StrokeCap {
+        (self.end_cap as u32).into()
+    }
+    #[inline]
+    pub fn set_end_cap(&mut self, cap: StrokeCap) {
+        self.end_cap = cap as u8;
+    }
+    /// Sets both the start and the end cap.
+    #[inline]
+    pub fn set_caps(&mut self, cap: StrokeCap) {
+        self.set_start_cap(cap);
+        self.set_end_cap(cap);
+    }
+    #[inline]
+    pub fn join(&self) -> StrokeJoin {
+        (self.join as u32).into()
+    }
+    #[inline]
+    pub fn set_join(&mut self, join: StrokeJoin) {
+        self.join = join as u8;
+    }
+    #[inline]
+    pub fn transform_order(&self) -> StrokeTransformOrder {
+        (self.transform_order as u32).into()
+    }
+    #[inline]
+    pub fn set_transform_order(&mut self, order: StrokeTransformOrder) {
+        self.transform_order = order as u8;
+    }
+    #[inline]
+    pub fn dash_offset(&self) -> f64 {
+        self.dash_offset
+    }
+    #[inline]
+    pub fn set_dash_offset(&mut self, offset: f64) {
+        self.dash_offset = offset;
+    }
+    /// Returns the dash array, alternating dash and gap lengths.
+    #[inline]
+    pub fn dash_array(&self) -> &[f64] {
+        let len = unsafe { ffi::bl_array_get_size(&self.dash_array) };
+        if len == 0 {
+            return &[];
+        }
+        unsafe {
+            slice::from_raw_parts(ffi::bl_array_get_data(&self.dash_array) as *const f64, len)
+        }
+    }
+    /// Sets the dash array, alternating dash and gap lengths. An empty array
+    /// disables dashing.
+    #[inline]
+    pub fn set_dash_array(&mut self, dashes: &[f64]) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_array_assign_data(&mut self.dash_array, dashes.as_ptr() as _, dashes.len())
+        })
+    }
+}
+
+impl Default for StrokeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for StrokeOptions {
+    fn clone(&self) -> Self {
+        let mut options = mem::MaybeUninit::<StrokeOptions>::uninit();
+        unsafe {
+            ffi::bl_stroke_options_init_weak(options.as_mut_ptr() as _, self as *const _ as _);
+            options.assume_init()
+        }
+    }
+}
+
+impl fmt::Debug for StrokeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StrokeOptions")
+            .field("width", &self.width())
+            .field("miter_limit", &self.miter_limit())
+            .field("start_cap", &self.start_cap())
+            .field("end_cap", &self.end_cap())
+            .field("join", &self.join())
+            .field("transform_order", &self.transform_order())
+            .field("dash_offset", &self.dash_offset())
+            .field("dash_array", &self.dash_array())
+            .finish()
+    }
+}
+
+impl Drop for StrokeOptions {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ffi::bl_stroke_options_destroy(self as *mut _ as _) };
+    }
+}
+
+use ffi::BLFlattenMode::*;
+bl_enum! {
+    /// Mode used to flatten curves into lines.
+    pub enum FlattenMode {
+        /// Use default mode (decided by blend2d).
+        Default   = BL_FLATTEN_MODE_DEFAULT,
+        /// Recursive subdivision flattening.
+        Recursive = BL_FLATTEN_MODE_RECURSIVE,
+    }
+    Default => Default
+}
+
+use ffi::BLOffsetMode::*;
+bl_enum! {
+    /// Mode used to offset curves.
+    pub enum OffsetMode {
+        /// Use default mode (decided by blend2d).
+        Default   = BL_OFFSET_MODE_DEFAULT,
+        /// Iterative offset construction.
+        Iterative = BL_OFFSET_MODE_ITERATIVE,
+    }
+    Default => Default
 }
 
+/// Options used to describe how geometry is approximated.
+///
+/// These options are used when stroking or flattening paths.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ApproximationOptions {
+    /// Specifies how curves are flattened.
+    pub flatten_mode: FlattenMode,
+    /// Specifies how curves are offsetted (used by stroking).
+    pub offset_mode: OffsetMode,
+    /// Tolerance used to flatten curves.
+    pub flatten_tolerance: f64,
+    /// Tolerance used to approximate cubic curves with quadratic curves.
+    pub simplify_tolerance: f64,
+    /// Curve offsetting parameter, exact meaning depends on `offset_mode`.
+    pub offset_parameter: f64,
+}
+
+impl ApproximationOptions {
+    #[inline]
+    pub(crate) fn to_ffi(self) -> ffi::BLApproximationOptions {
+        ffi::BLApproximationOptions {
+            flatten_mode: self.flatten_mode as u8,
+            offset_mode: self.offset_mode as u8,
+            reserved_flags: [0; 6],
+            flatten_tolerance: self.flatten_tolerance,
+            simplify_tolerance: self.simplify_tolerance,
+            offset_parameter: self.offset_parameter,
+        }
+    }
+}
+
+impl Default for ApproximationOptions {
+    /// Returns blend2d's default approximation options.
+    #[inline]
+    fn default() -> Self {
+        ApproximationOptions {
+            flatten_mode: FlattenMode::Default,
+            offset_mode: OffsetMode::Default,
+            flatten_tolerance: 0.20,
+            simplify_tolerance: 0.05,
+            offset_parameter: 0.414213562,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Path {
+    type Item = PathSegment;
+    type IntoIter = Segments<'a>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments()
+    }
+}
+
+/// An iterator over the [`PathSegment`]s of a [`Path`].
+///
+/// Iteration stops early if the path data is malformed, i.e. if a curve is
+/// missing some of its vertices.
+#[derive(Clone, Debug)]
+pub struct Segments<'a> {
+    commands: &'a [u8],
+    vertices: &'a [Point],
+}
+
+impl<'a> Segments<'a> {
+    #[inline]
+    pub(crate) fn new(commands: &'a [u8], vertices: &'a [Point]) -> Self {
+        Segments { commands, vertices }
+    }
+
+    #[inline]
+    fn take(&mut self, n: usize, expected: &[u8]) -> Option<&'a [Point]> {
+        if self.commands.get(..n)? != expected {
+            return None;
+        }
+        let (vtx, rest) = self.vertices.split_at(n);
+        self.commands = &self.commands[n..];
+        self.vertices = rest;
+        Some(vtx)
+    }
+}
+
+impl Iterator for Segments<'_> {
+    type Item = PathSegment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        const MOVE: u8 = BL_PATH_CMD_MOVE as u8;
+        const ON: u8 = BL_PATH_CMD_ON as u8;
+        const QUAD: u8 = BL_PATH_CMD_QUAD as u8;
+        const CONIC: u8 = BL_PATH_CMD_CONIC as u8;
+        const CUBIC: u8 = BL_PATH_CMD_CUBIC as u8;
+        const CLOSE: u8 = BL_PATH_CMD_CLOSE as u8;
+        const WEIGHT: u8 = BL_PATH_CMD_WEIGHT as u8;
+
+        let segment = match *self.commands.first()? {
+            MOVE => self.take(1, &[MOVE]).map(|v| PathSegment::MoveTo(v[0])),
+            ON => self.take(1, &[ON]).map(|v| PathSegment::LineTo(v[0])),
+            QUAD => self
+                .take(2, &[QUAD, ON])
+                .map(|v| PathSegment::QuadTo(v[0], v[1])),
+            CONIC => self
+                .take(3, &[CONIC, WEIGHT, ON])
+                .map(|v| PathSegment::ConicTo(v[0], v[2], v[1].x)),
+            CUBIC => self
+                .take(3, &[CUBIC, CUBIC, ON])
+                .map(|v| PathSegment::CubicTo(v[0], v[1], v[2])),
+            CLOSE => self.take(1, &[CLOSE]).map(|_| PathSegment::Close),
+            _ => None,
+        };
+        if segment.is_none() {
+            self.commands = &[];
+            self.vertices = &[];
+        }
+        segment
+    }
+}
+
+impl std::iter::FusedIterator for Segments<'_> {}
+
 impl Default for Path {
     #[inline]
     fn default() -> Self {
@@ -46,3 +905,216 @@ impl Drop for Path {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        let p = |x, y| Point { x, y };
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0).unwrap();
+        path.line_to(1.0, 0.0).unwrap();
+        path.quad_to(2.0, 0.0, 2.0, 1.0).unwrap();
+        path.conic_to(2.0, 2.0, 1.0, 2.0, 0.5).unwrap();
+        path.cubic_to(0.5, 2.0, 0.0, 1.5, 0.0, 1.0).unwrap();
+        path.close().unwrap();
+
+        assert_eq!(path.len(), 11);
+        assert_eq!(path.commands().len(), path.vertices().len());
+        assert_eq!(
+            path.segments().collect::<Vec<_>>(),
+            [
+                PathSegment::MoveTo(p(0.0, 0.0)),
+                PathSegment::LineTo(p(1.0, 0.0)),
+                PathSegment::QuadTo(p(2.0, 0.0), p(2.0, 1.0)),
+                PathSegment::ConicTo(p(2.0, 2.0), p(1.0, 2.0), 0.5),
+                PathSegment::CubicTo(p(0.5, 2.0), p(0.0, 1.5), p(0.0, 1.0)),
+                PathSegment::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_queries() {
+        let p = |x, y| Point { x, y };
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0).unwrap();
+        path.quad_to(50.0, 100.0, 100.0, 0.0).unwrap();
+        path.close().unwrap();
+
+        assert_eq!(path.info_flags().unwrap(), PathFlags::QUADS);
+        assert_eq!(
+            path.control_box().unwrap(),
+            geometry::Box::new(0.0, 0.0, 100.0, 100.0)
+        );
+        assert_eq!(
+            path.bounding_box().unwrap(),
+            geometry::Box::new(0.0, 0.0, 100.0, 50.0)
+        );
+        assert_eq!(path.hit_test(p(50.0, 25.0), FillRule::NonZero), HitTest::In);
+        assert_eq!(
+            path.hit_test(p(50.0, 75.0), FillRule::NonZero),
+            HitTest::Out
+        );
+        assert_eq!(
+            path.closest_vertex(p(101.0, 1.0), 10.0),
+            Some((2, 2f64.sqrt()))
+        );
+        assert_eq!(path.closest_vertex(p(200.0, 200.0), 10.0), None);
+        assert!(
+            Path::default()
+                .info_flags()
+                .unwrap()
+                .contains(PathFlags::EMPTY)
+        );
+    }
+
+    fn figures(figures: &[&[(f64, f64)]]) -> Path {
+        let mut path = Path::default();
+        for figure in figures {
+            path.move_to(figure[0].0, figure[0].1).unwrap();
+            for &(x, y) in &figure[1..] {
+                path.line_to(x, y).unwrap();
+            }
+        }
+        path
+    }
+
+    #[test]
+    fn test_reverse_range() {
+        let mut path = figures(&[
+            &[(0.0, 0.0), (1.0, 0.0)],
+            &[(5.0, 5.0), (6.0, 5.0), (6.0, 6.0)],
+            &[(9.0, 9.0), (9.0, 8.0)],
+        ]);
+        path.reverse(2..5, PathReverseMode::Complete).unwrap();
+        let expected = figures(&[
+            &[(0.0, 0.0), (1.0, 0.0)],
+            &[(6.0, 6.0), (6.0, 5.0), (5.0, 5.0)],
+            &[(9.0, 9.0), (9.0, 8.0)],
+        ]);
+        assert_eq!(path.commands(), expected.commands());
+        assert_eq!(path.vertices(), expected.vertices());
+
+        let mut path = figures(&[&[(0.0, 0.0), (1.0, 0.0)], &[(5.0, 5.0), (6.0, 5.0)]]);
+        path.reverse(.., PathReverseMode::Separate).unwrap();
+        let expected = figures(&[&[(1.0, 0.0), (0.0, 0.0)], &[(6.0, 5.0), (5.0, 5.0)]]);
+        assert_eq!(path.vertices(), expected.vertices());
+        path.reverse(.., PathReverseMode::Complete).unwrap();
+        let expected = figures(&[&[(5.0, 5.0), (6.0, 5.0)], &[(0.0, 0.0), (1.0, 0.0)]]);
+        assert_eq!(path.vertices(), expected.vertices());
+    }
+
+    #[test]
+    fn test_edit_ranges() {
+        let p = |x, y| Point { x, y };
+        let mut path = figures(&[&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]]);
+        path.translate(p(10.0, 20.0), 1..).unwrap();
+        assert_eq!(path.vertices(), [p(0.0, 0.0), p(11.0, 20.0), p(11.0, 21.0)]);
+        path.transform(&Matrix2D::scaling(2.0, 2.0), ..=1).unwrap();
+        assert_eq!(path.vertices(), [p(0.0, 0.0), p(22.0, 40.0), p(11.0, 21.0)]);
+        path.set_vertex_at(2, None, 5.0, 5.0).unwrap();
+        assert_eq!(path.vertices()[2], p(5.0, 5.0));
+        assert_eq!(path.commands()[2], PathCmd::On as u8);
+        path.fit_to(&Rect::new(0.0, 0.0, 11.0, 20.0), ..).unwrap();
+        assert_eq!(path.vertices(), [p(0.0, 0.0), p(11.0, 20.0), p(2.5, 2.5)]);
+        path.remove_range(1..2).unwrap();
+        assert_eq!(path.vertices(), [p(0.0, 0.0), p(2.5, 2.5)]);
+        path.remove_range(5..).unwrap();
+        assert_eq!(path.len(), 2);
+
+        let (cmds, vtxs) = path.modify_op(ModifyOp::AppendGrow, 1).unwrap();
+        cmds[0] = PathCmd::On as u8;
+        vtxs[0] = p(3.0, 3.0);
+        assert_eq!(path.vertices(), [p(0.0, 0.0), p(2.5, 2.5), p(3.0, 3.0)]);
+    }
+
+    fn stroke_options(width: f64) -> StrokeOptions {
+        let mut options = StrokeOptions::new();
+        options.set_width(width);
+        options.set_caps(StrokeCap::Butt);
+        options
+    }
+
+    #[test]
+    fn test_add_stroked_path() {
+        let line = figures(&[&[(0.0, 0.0), (100.0, 0.0)]]);
+        let mut outline = Path::default();
+        outline
+            .add_stroked_path(&line, .., &stroke_options(10.0), &Default::default())
+            .unwrap();
+        assert_eq!(
+            outline.bounding_box().unwrap(),
+            geometry::Box::new(0.0, -5.0, 100.0, 5.0)
+        );
+        assert_eq!(
+            outline.hit_test(Point::new(50.0, 4.0), FillRule::NonZero),
+            HitTest::In
+        );
+        assert_eq!(
+            outline.hit_test(Point::new(50.0, 6.0), FillRule::NonZero),
+            HitTest::Out
+        );
+    }
+
+    #[test]
+    fn test_stroke_to_sink() {
+        let path = figures(&[&[(0.0, 0.0), (100.0, 0.0)], &[(0.0, 50.0), (0.0, 100.0)]]);
+        let options = stroke_options(10.0);
+        let approx = ApproximationOptions::default();
+
+        let mut ranges = Vec::new();
+        let mut outline = Path::default();
+        path.stroke_to_sink(.., &options, &approx, |a, b, c, range| {
+            ranges.push(range);
+            outline.add_path(a, ..)?;
+            outline.add_reversed_path(b, .., PathReverseMode::Complete)?;
+            outline.add_path(c, ..)?;
+            a.clear()?;
+            b.clear()?;
+            c.clear()
+        })
+        .unwrap();
+        assert_eq!(ranges, [0..2, 2..4]);
+        assert_eq!(
+            outline.bounding_box().unwrap(),
+            geometry::Box::new(-5.0, -5.0, 100.0, 100.0)
+        );
+
+        let result = path.stroke_to_sink(.., &options, &approx, |_, _, _, _| Err(Error));
+        assert!(result.is_err());
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            path.stroke_to_sink(.., &options, &approx, |_, _, _, _| panic!("sink failed"))
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"sink failed"));
+    }
+
+    #[test]
+    fn test_hit_test_fill_rules() {
+        let p = |x, y| Point { x, y };
+        let mut path = Path::default();
+        for (x0, x1) in [(0.0, 100.0), (25.0, 75.0)] {
+            path.move_to(x0, x0).unwrap();
+            path.line_to(x1, x0).unwrap();
+            path.line_to(x1, x1).unwrap();
+            path.line_to(x0, x1).unwrap();
+            path.close().unwrap();
+        }
+        assert_eq!(path.info_flags().unwrap(), PathFlags::MULTIPLE);
+        assert_eq!(path.hit_test(p(10.0, 10.0), FillRule::NonZero), HitTest::In);
+        assert_eq!(path.hit_test(p(10.0, 10.0), FillRule::EvenOdd), HitTest::In);
+        assert_eq!(path.hit_test(p(50.0, 50.0), FillRule::NonZero), HitTest::In);
+        assert_eq!(
+            path.hit_test(p(50.0, 50.0), FillRule::EvenOdd),
+            HitTest::Out
+        );
+        assert_eq!(
+            path.hit_test(p(150.0, 50.0), FillRule::EvenOdd),
+            HitTest::Out
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Conversion of paths into dashed paths.
+
+use crate::Error;
+use crate::path::{Path, PathMeasure};
+
+/// Upper bound of dashes and gaps a single figure is split into, which
+/// rejects patterns that are tiny compared to the figure.
+const MAX_INTERVALS: f64 = 1e6;
+
+/// Returns the on-intervals of the dash pattern along a figure of the given
+/// length. Zero-length dashes produce empty intervals.
+///
+/// Fails if the figure would be split into more than [`MAX_INTERVALS`]
+/// dashes and gaps.
+fn dash_intervals(dashes: &[f64], offset: f64, length: f64) -> Result<Vec<(f64, f64)>, Error> {
+    let pattern_length: f64 = dashes.iter().sum();
+    let count = (length + pattern_length) / pattern_length * dashes.len() as f64;
+    if count.is_nan() || count > MAX_INTERVALS {
+        return Err(Error);
+    }
+    let mut phase = offset.rem_euclid(pattern_length);
+    let mut i = 0;
+    while phase > dashes[i] {
+        phase -= dashes[i];
+        i = (i + 1) % dashes.len();
+    }
+
+    let mut intervals = Vec::new();
+    let mut pos = -phase;
+    while pos < length {
+        let end = pos + dashes[i];
+        if i % 2 == 0 && (end > 0.0 || (end == 0.0 && dashes[i] == 0.0)) {
+            intervals.push((pos.max(0.0), end.min(length)));
+        }
+        pos = end;
+        i = (i + 1) % dashes.len();
+    }
+    Ok(intervals)
+}
+
+pub(super) fn dash(
+    path: &Path,
+    dashes: &[f64],
+    offset: f64,
+    tolerance: f64,
+) -> Result<Path, Error> {
+    // Like SVG, an odd number of dashes is repeated to get an even number.
+    let dashes = if dashes.len() % 2 == 1 {
+        [dashes, dashes].concat()
+    } else {
+        dashes.to_vec()
+    };
+    let pattern_length: f64 = dashes.iter().sum();
+    let valid = !dashes.is_empty()
+        && dashes.iter().all(|&d| d >= 0.0)
+        && pattern_length.is_finite()
+        && pattern_length > 0.0;
+
+    let mut result = Path::default();
+    if !valid {
+        result.add_path(path, ..)?;
+        return Ok(result);
+    }
+
+    let measure = PathMeasure::new(path, tolerance);
+    let mut figure_start = 0.0;
+    for (index, &length) in measure.figure_lengths().iter().enumerate() {
+        let closed = measure.is_figure_closed(index);
+        let mut intervals = dash_intervals(&dashes, offset, length)?;
+        let at = |d: f64| figure_start + d;
+
+        if closed && intervals.len() == 1 && intervals[0] == (0.0, length) && length > 0.0 {
+            // A dash covering the whole closed figure keeps it closed.
+            let part = measure.segment(at(0.0)..at(length))?;
+            result.add_path(&part, ..)?;
+            result.close()?;
+            intervals.clear();
+        } else if closed && intervals.len() >= 2 {
+            // Dashes crossing the start of a closed figure are joined.
+            let (first, last) = (intervals[0], intervals[intervals.len() - 1]);
+            if first.0 == 0.0 && last.1 == length && first.1 > 0.0 && last.0 < length {
+                let mut part = measure.segment(at(last.0)..at(length))?;
+                let head = measure.segment(at(0.0)..at(first.1))?;
+                part.add_path(&head, 1..)?;
+                result.add_path(&part, ..)?;
+                intervals.remove(0);
+                intervals.pop();
+            }
+        }
+
+        for (start, end) in intervals {
+            if start < end {
+                let part = measure.segment(at(start)..at(end))?;
+                result.add_path(&part, ..)?;
+            } else if let Some(p) = measure.point_at(at(start)) {
+                // Keep zero-length dashes so that round and square caps
+                // still produce dots.
+                result.move_to(p.x, p.y)?;
+                result.line_to(p.x, p.y)?;
+            }
+        }
+        figure_start += length;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+    use crate::path::PathSegment;
+
+    #[test]
+    fn test_dash_intervals() {
+        assert_eq!(
+            dash_intervals(&[4.0, 2.0], 0.0, 13.0).unwrap(),
+            [(0.0, 4.0), (6.0, 10.0), (12.0, 13.0)]
+        );
+        assert_eq!(
+            dash_intervals(&[4.0, 2.0], 5.0, 10.0).unwrap(),
+            [(1.0, 5.0), (7.0, 10.0)]
+        );
+        assert_eq!(
+            dash_intervals(&[4.0, 2.0], -2.0, 6.0).unwrap(),
+            [(2.0, 6.0)]
+        );
+        assert_eq!(
+            dash_intervals(&[0.0, 5.0], 0.0, 10.0).unwrap(),
+            [(0.0, 0.0), (5.0, 5.0)]
+        );
+        assert!(dash_intervals(&[1e-9, 1e-9], 0.0, 1e6).is_err());
+        assert!(dash_intervals(&[1.0, 1.0], 0.0, f64::INFINITY).is_err());
+        assert_eq!(dash_intervals(&[1.0, 1.0], 0.0, 1e5).unwrap().len(), 50000);
+    }
+
+    #[test]
+    fn test_dash_closed() {
+        let p = |x, y| Point { x, y };
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0).unwrap();
+        path.line_to(10.0, 0.0).unwrap();
+        path.line_to(10.0, 10.0).unwrap();
+        path.line_to(0.0, 10.0).unwrap();
+        path.close().unwrap();
+
+        // The last dash wraps around the start of the square.
+        let dashed = path.dash(&[5.0, 5.0], 2.5, 0.2).unwrap();
+        assert_eq!(
+            dashed.segments().collect::<Vec<_>>(),
+            [
+                PathSegment::MoveTo(p(0.0, 2.5)),
+                PathSegment::LineTo(p(0.0, 0.0)),
+                PathSegment::LineTo(p(2.5, 0.0)),
+                PathSegment::MoveTo(p(7.5, 0.0)),
+                PathSegment::LineTo(p(10.0, 0.0)),
+                PathSegment::LineTo(p(10.0, 2.5)),
+                PathSegment::MoveTo(p(10.0, 7.5)),
+                PathSegment::LineTo(p(10.0, 10.0)),
+                PathSegment::LineTo(p(7.5, 10.0)),
+                PathSegment::MoveTo(p(2.5, 10.0)),
+                PathSegment::LineTo(p(0.0, 10.0)),
+                PathSegment::LineTo(p(0.0, 7.5)),
+            ]
+        );
+
+        let solid = path.dash(&[100.0], 0.0, 0.2).unwrap();
+        assert_eq!(solid.commands().last(), path.commands().last());
+
+        assert!(path.dash(&[1e-9, 1e-9], 0.0, 0.2).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+//! Approximation of path curves with line segments.
+
+use crate::geometry::Point;
+use crate::path::{FlattenMode, PathSegment, Segments};
+
+/// Maximum subdivision depth of [`FlattenMode::Recursive`].
+const MAX_DEPTH: u32 = 16;
+/// Upper bound of line segments a single curve is flattened into.
+const MAX_SEGMENTS: f64 = (1u32 << MAX_DEPTH) as f64;
+
+/// A flattened figure of a path, see [`Path::flatten`](crate::path::Path::flatten).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polyline {
+    /// The points of the polyline, starting at the start of the figure.
+    pub points: Vec<Point>,
+    /// Whether the figure was closed, in which case the last point connects
+    /// back to the first one.
+    pub closed: bool,
+}
+
+/// An iterator over the flattened figures of a path.
+///
+/// This struct is created by [`Path::flatten`](crate::path::Path::flatten).
+pub struct Flatten<'a> {
+    segments: Segments<'a>,
+    next_start: Option<Point>,
+    /// The start of the last figure, where a figure without a move-to starts.
+    figure_start: Point,
+    tolerance: f64,
+    mode: FlattenMode,
+}
+
+impl<'a> Flatten<'a> {
+    #[inline]
+    pub(crate) fn new(segments: Segments<'a>, tolerance: f64, mode: FlattenMode) -> Self {
+        Flatten {
+            segments,
+            next_start: None,
+            figure_start: Point::default(),
+            tolerance,
+            mode,
+        }
+    }
+}
+
+impl Iterator for Flatten<'_> {
+    type Item = Polyline;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut polyline = Polyline::default();
+        if let Some(start) = self.next_start.take() {
+            self.figure_start = start;
+            polyline.points.push(start);
+        }
+        for segment in self.segments.by_ref() {
+            let from = match (segment, polyline.points.last()) {
+                (PathSegment::MoveTo(p), None) => {
+                    self.figure_start = p;
+                    polyline.points.push(p);
+                    continue;
+                },
+                (PathSegment::MoveTo(p), Some(_)) => {
+                    self.next_start = Some(p);
+                    return Some(polyline);
+                },
+                (PathSegment::Close, None) => continue,
+                (PathSegment::Close, Some(_)) => {
+                    polyline.closed = true;
+                    return Some(polyline);
+                },
+                (_, Some(&from)) => from,
+                // A figure without a move-to starts where the previous figure
+                // started, like in SVG, or at the origin for the first one.
+                (_, None) => self.figure_start,
+            };
+            if polyline.points.is_empty() {
+                polyline.points.push(from);
+            }
+            flatten_segment(
+                from,
+                segment,
+                self.tolerance,
+                self.mode,
+                &mut polyline.points,
+            );
+        }
+        Some(polyline).filter(|p| !p.points.is_empty())
+    }
+}
+
+impl std::iter::FusedIterator for Flatten<'_> {}
+
+#[inline]
+pub(crate) fn lerp(a: Point, b: Point, t: f64) -> Point {
+    Point {
+        x: a.x + (b.x - a.x) * t,
+        y: a.y + (b.y - a.y) * t,
+    }
+}
+
+#[inline]
+fn second_difference(a: Point, b: Point, c: Point) -> f64 {
+    (a.x - 2.0 * b.x + c.x).hypot(a.y - 2.0 * b.y + c.y)
+}
+
+/// Evaluates the curve `segment` starting at `from` at the parameter `t`.
+pub(crate) fn eval_segment(from: Point, segment: PathSegment, t: f64) -> Point {
+    let mt = 1.0 - t;
+    match segment {
+        PathSegment::MoveTo(p) => p,
+        PathSegment::LineTo(p) => lerp(from, p, t),
+        PathSegment::QuadTo(c, p) => lerp(lerp(from, c, t), lerp(c, p, t), t),
+        PathSegment::ConicTo(c, p, w) => {
+            let (a, b, d) = (mt * mt, 2.0 * w * t * mt, t * t);
+            let denom = a + b + d;
+            Point {
+                x: (a * from.x + b * c.x + d * p.x) / denom,
+                y: (a * from.y + b * c.y + d * p.y) / denom,
+            }
+        },
+        PathSegment::CubicTo(c1, c2, p) => {
+            let q0 = lerp(from, c1, t);
+            let q1 = lerp(c1, c2, t);
+            let q2 = lerp(c2, p, t);
+            lerp(lerp(q0, q1, t), lerp(q1, q2, t), t)
+        },
+        PathSegment::Close => from,
+    }
+}
+
+/// Appends the points approximating `segment`, excluding `from`, to `out`.
+pub(crate) fn flatten_segment(
+    from: Point,
+    segment: PathSegment,
+    tolerance: f64,
+    mode: FlattenMode,
+    out: &mut Vec<Point>,
+) {
+    let end = match segment {
+        PathSegment::MoveTo(_) | PathSegment::Close => return,
+        PathSegment::LineTo(p) => {
+            out.push(p);
+            return;
+        },
+        PathSegment::QuadTo(_, p) | PathSegment::ConicTo(_, p, _) => p,
+        PathSegment::CubicTo(_, _, p) => p,
+    };
+    let tolerance = tolerance.max(f64::EPSILON);
+    match mode {
+        FlattenMode::Recursive => subdivide(from, segment, 0.0, from, 1.0, end, tolerance, 0, out),
+        FlattenMode::Default => {
+            let n = segment_steps(from, segment, tolerance);
+            out.extend((1..n).map(|i| eval_segment(from, segment, i as f64 / n as f64)));
+            out.push(end);
+        },
+    }
+}
+
+/// Returns the number of uniform steps that keep the approximation of the
+/// curve `segment` within `tolerance`, using Wang's formula.
+pub(crate) fn segment_steps(from: Point, segment: PathSegment, tolerance: f64) -> usize {
+    let tolerance = tolerance.max(f64::EPSILON);
+    let n = match segment {
+        PathSegment::QuadTo(c, p) => (second_difference(from, c, p) / (4.0 * tolerance)).sqrt(),
+        PathSegment::ConicTo(c, p, w) => {
+            (second_difference(from, c, p) * w.max(1.0) / (4.0 * tolerance)).sqrt()
+        },
+        PathSegment::CubicTo(c1, c2, p) => {
+            let dd = second_difference(from, c1, c2).max(second_difference(c1, c2, p));
+            (3.0 * dd / (4.0 * tolerance)).sqrt()
+        },
+        _ => 1.0,
+    };
+    n.ceil().clamp(1.0, MAX_SEGMENTS) as usize
+}
+
+#[inline]
+fn distance_to_line(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len = dx.hypot(dy);
+    if len == 0.0 {
+        (p.x - a.x).hypot(p.y - a.y)
+    } else {
+        ((p.x - a.x) * dy - (p.y - a.y) * dx).abs() / len
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn subdivide(
+    from: Point,
+    segment: PathSegment,
+    t0: f64,
+    p0: Point,
+    t1: f64,
+    p1: Point,
+    tolerance: f64,
+    depth: u32,
+    out: &mut Vec<Point>,
+) {
+    let at = |f: f64| eval_segment(from, segment, t0 + (t1 - t0) * f);
+    let mid = at(0.5);
+    let flat = [at(0.25), mid, at(0.75)]
+        .iter()
+        .all(|&p| distance_to_line(p, p0, p1) <= tolerance);
+    if flat || depth >= MAX_DEPTH {
+        out.push(p1);
+    } else {
+        let tm = (t0 + t1) * 0.5;
+        subdivide(from, segment, t0, p0, tm, mid, tolerance, depth + 1, out);
+        subdivide(from, segment, tm, mid, t1, p1, tolerance, depth + 1, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::Path;
+
+    #[test]
+    fn test_flatten() {
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0).unwrap();
+        path.quad_to(50.0, 100.0, 100.0, 0.0).unwrap();
+        path.close().unwrap();
+        path.move_to(0.0, 10.0).unwrap();
+        path.line_to(10.0, 10.0).unwrap();
+
+        for mode in [FlattenMode::Default, FlattenMode::Recursive] {
+            let polylines = Flatten::new(path.segments(), 0.25, mode).collect::<Vec<_>>();
+            assert_eq!(polylines.len(), 2);
+            let curve = &polylines[0];
+            assert!(curve.closed);
+            assert!(curve.points.len() > 4);
+            assert_eq!(curve.points.first(), Some(&Point { x: 0.0, y: 0.0 }));
+            assert_eq!(curve.points.last(), Some(&Point { x: 100.0, y: 0.0 }));
+            // The apex of the parabola is at (50, 50).
+            let apex = curve.points.iter().map(|p| p.y).fold(0.0, f64::max);
+            assert!((49.75..=50.0).contains(&apex));
+            assert_eq!(
+                polylines[1],
+                Polyline {
+                    points: vec![Point { x: 0.0, y: 10.0 }, Point { x: 10.0, y: 10.0 }],
+                    closed: false,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_move_to() {
+        use crate::path::{PathCmd, Segments};
+
+        let p = |x, y| Point { x, y };
+        let cmds = [PathCmd::Quad, PathCmd::On, PathCmd::Close, PathCmd::On].map(|c| c as u8);
+        let vtxs = [
+            p(5.0, 10.0),
+            p(10.0, 0.0),
+            p(f64::NAN, f64::NAN),
+            p(10.0, 10.0),
+        ];
+        let polylines = Flatten::new(Segments::new(&cmds, &vtxs), 0.25, FlattenMode::Default)
+            .collect::<Vec<_>>();
+        assert_eq!(polylines.len(), 2);
+        assert_eq!(polylines[0].points.first(), Some(&p(0.0, 0.0)));
+        assert_eq!(polylines[0].points.last(), Some(&p(10.0, 0.0)));
+        assert!(polylines[0].closed);
+        assert_eq!(polylines[1].points, [p(0.0, 0.0), p(10.0, 10.0)]);
+
+        let cmds = [PathCmd::Move, PathCmd::On, PathCmd::Close, PathCmd::On].map(|c| c as u8);
+        let vtxs = [
+            p(3.0, 4.0),
+            p(10.0, 0.0),
+            p(f64::NAN, f64::NAN),
+            p(10.0, 10.0),
+        ];
+        let polylines = Flatten::new(Segments::new(&cmds, &vtxs), 0.25, FlattenMode::Default)
+            .collect::<Vec<_>>();
+        assert_eq!(polylines[1].points, [p(3.0, 4.0), p(10.0, 10.0)]);
+    }
+}
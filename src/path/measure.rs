@@ -0,0 +1,383 @@
+//! Arc-length measurement of paths.
+
+use std::ops::Range;
+
+use crate::Error;
+use crate::geometry::Point;
+use crate::path::flatten::{eval_segment, lerp, segment_steps};
+use crate::path::{Path, PathSegment};
+
+/// A drawable segment with its arc-length samples.
+struct Measured {
+    from: Point,
+    segment: PathSegment,
+    figure: usize,
+    /// Distance along the path at which the segment starts.
+    start: f64,
+    /// Cumulative lengths at uniformly spaced curve parameters.
+    lengths: Vec<f64>,
+}
+
+impl Measured {
+    #[inline]
+    fn length(&self) -> f64 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// Maps a distance from the start of the segment to the curve parameter.
+    fn parameter_at(&self, distance: f64) -> f64 {
+        if distance <= 0.0 {
+            return 0.0;
+        }
+        let steps = (self.lengths.len() - 1) as f64;
+        let i = self.lengths.partition_point(|&l| l < distance).max(1);
+        if i >= self.lengths.len() {
+            return 1.0;
+        }
+        let (l0, l1) = (self.lengths[i - 1], self.lengths[i]);
+        let f = if l1 > l0 {
+            (distance - l0) / (l1 - l0)
+        } else {
+            0.0
+        };
+        ((i - 1) as f64 + f) / steps
+    }
+
+    fn derivative_at(&self, t: f64) -> Point {
+        let from = self.from;
+        let mt = 1.0 - t;
+        let sub = |a: Point, b: Point| Point {
+            x: b.x - a.x,
+            y: b.y - a.y,
+        };
+        match self.segment {
+            PathSegment::LineTo(p) => sub(from, p),
+            PathSegment::QuadTo(c, p) => lerp(sub(from, c), sub(c, p), t),
+            PathSegment::CubicTo(c1, c2, p) => {
+                let (a, b, d) = (sub(from, c1), sub(c1, c2), sub(c2, p));
+                lerp(lerp(a, b, t), lerp(b, d, t), t)
+            },
+            PathSegment::ConicTo(c, p, w) => {
+                // Numerator of the quotient rule, only the direction matters.
+                let (a, b, d) = (mt * mt, 2.0 * w * t * mt, t * t);
+                let (da, db, dd) = (-2.0 * mt, 2.0 * w * (1.0 - 2.0 * t), 2.0 * t);
+                let (den, dden) = (a + b + d, da + db + dd);
+                let num = |f: fn(&Point) -> f64| a * f(&from) + b * f(&c) + d * f(&p);
+                let dnum = |f: fn(&Point) -> f64| da * f(&from) + db * f(&c) + dd * f(&p);
+                Point {
+                    x: dnum(|p| p.x) * den - num(|p| p.x) * dden,
+                    y: dnum(|p| p.y) * den - num(|p| p.y) * dden,
+                }
+            },
+            PathSegment::MoveTo(_) | PathSegment::Close => Point::default(),
+        }
+    }
+
+    /// Appends the part of the segment between the parameters `t0` and `t1`
+    /// to `path`, whose current point is expected to be at `t0`.
+    fn append(&self, path: &mut Path, t0: f64, t1: f64) -> Result<(), Error> {
+        let from = self.from;
+        match self.segment {
+            PathSegment::LineTo(p) => {
+                let p = lerp(from, p, t1);
+                path.line_to(p.x, p.y)
+            },
+            PathSegment::QuadTo(c, p) => {
+                // Blossoming yields the control points of the sub-curve.
+                let c = lerp(lerp(from, c, t0), lerp(c, p, t0), t1);
+                let p = eval_segment(from, self.segment, t1);
+                path.quad_to(c.x, c.y, p.x, p.y)
+            },
+            PathSegment::CubicTo(c1, c2, p) => {
+                let blossom = |u: f64, v: f64, w: f64| {
+                    let (a, b, c) = (lerp(from, c1, u), lerp(c1, c2, u), lerp(c2, p, u));
+                    let (a, b) = (lerp(a, b, v), lerp(b, c, v));
+                    lerp(a, b, w)
+                };
+                let c1 = blossom(t0, t0, t1);
+                let c2 = blossom(t0, t1, t1);
+                let p = eval_segment(from, self.segment, t1);
+                path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y)
+            },
+            PathSegment::ConicTo(c, p, w) => {
+                // Blossom the curve in homogeneous coordinates, then bring
+                // the result back into the standard form with unit end
+                // weights.
+                let h = [
+                    (from.x, from.y, 1.0),
+                    (c.x * w, c.y * w, w),
+                    (p.x, p.y, 1.0),
+                ];
+                let blossom = |u: f64, v: f64| {
+                    let k = [(1.0 - u) * (1.0 - v), (1.0 - u) * v + u * (1.0 - v), u * v];
+                    (0..3).fold((0.0, 0.0, 0.0), |acc, i| {
+                        (
+                            acc.0 + k[i] * h[i].0,
+                            acc.1 + k[i] * h[i].1,
+                            acc.2 + k[i] * h[i].2,
+                        )
+                    })
+                };
+                let (r0, r1, r2) = (blossom(t0, t0), blossom(t0, t1), blossom(t1, t1));
+                let w = r1.2 / (r0.2 * r2.2).sqrt();
+                path.conic_to(r1.0 / r1.2, r1.1 / r1.2, r2.0 / r2.2, r2.1 / r2.2, w)
+            },
+            PathSegment::MoveTo(_) | PathSegment::Close => Ok(()),
+        }
+    }
+}
+
+/// Arc-length information of a path, used to query points and tangents at
+/// a given distance along the path.
+///
+/// The figures of the path are measured one after another, so distances
+/// past the end of a figure continue at the start of the next figure. The
+/// closing line of closed figures is part of the measurement.
+///
+/// This struct is created by [`Path::measure`].
+pub struct PathMeasure {
+    segments: Vec<Measured>,
+    figure_lengths: Vec<f64>,
+    figure_closed: Vec<bool>,
+}
+
+impl PathMeasure {
+    pub(crate) fn new(path: &Path, tolerance: f64) -> Self {
+        let mut segments = Vec::<Measured>::new();
+        let mut figure_lengths = Vec::new();
+        let mut figure_closed = Vec::new();
+        let mut current = Point::default();
+        let mut figure_start = Point::default();
+        let mut total = 0.0;
+        for segment in path.segments() {
+            let (segment, end) = match segment {
+                PathSegment::MoveTo(p) => {
+                    figure_lengths.push(0.0);
+                    figure_closed.push(false);
+                    current = p;
+                    figure_start = p;
+                    continue;
+                },
+                PathSegment::Close => {
+                    if let Some(closed) = figure_closed.last_mut() {
+                        *closed = true;
+                    }
+                    if current == figure_start {
+                        continue;
+                    }
+                    (PathSegment::LineTo(figure_start), figure_start)
+                },
+                PathSegment::LineTo(p) | PathSegment::QuadTo(_, p) => (segment, p),
+                PathSegment::ConicTo(_, p, _) | PathSegment::CubicTo(_, _, p) => (segment, p),
+            };
+            if figure_lengths.is_empty() {
+                figure_lengths.push(0.0);
+                figure_closed.push(false);
+            }
+            let steps = segment_steps(current, segment, tolerance);
+            let mut lengths = Vec::with_capacity(steps + 1);
+            let mut length = 0.0;
+            let mut prev = current;
+            lengths.push(0.0);
+            for i in 1..=steps {
+                let p = eval_segment(current, segment, i as f64 / steps as f64);
+                length += (p.x - prev.x).hypot(p.y - prev.y);
+                lengths.push(length);
+                prev = p;
+            }
+            segments.push(Measured {
+                from: current,
+                segment,
+                figure: figure_lengths.len() - 1,
+                start: total,
+                lengths,
+            });
+            *figure_lengths.last_mut().unwrap() += length;
+            total += length;
+            current = end;
+        }
+        PathMeasure {
+            segments,
+            figure_lengths,
+            figure_closed,
+        }
+    }
+
+    /// The total length of the path, including all figures.
+    #[inline]
+    pub fn length(&self) -> f64 {
+        self.figure_lengths.iter().sum()
+    }
+
+    /// The number of figures in the path.
+    #[inline]
+    pub fn figure_count(&self) -> usize {
+        self.figure_lengths.len()
+    }
+
+    /// The length of every figure of the path, in order.
+    #[inline]
+    pub fn figure_lengths(&self) -> &[f64] {
+        &self.figure_lengths
+    }
+
+    /// Returns whether the figure at `index` is closed.
+    #[inline]
+    pub fn is_figure_closed(&self, index: usize) -> bool {
+        self.figure_closed.get(index).copied().unwrap_or(false)
+    }
+
+    /// Finds the segment at `distance` and the curve parameter within it.
+    fn locate(&self, distance: f64) -> Option<(&Measured, f64)> {
+        if !(0.0..=self.length()).contains(&distance) {
+            return None;
+        }
+        let i = self
+            .segments
+            .partition_point(|s| s.start + s.length() < distance)
+            .min(self.segments.len().checked_sub(1)?);
+        let segment = &self.segments[i];
+        Some((segment, segment.parameter_at(distance - segment.start)))
+    }
+
+    /// Returns the point at `distance` along the path, or `None` if the
+    /// distance lies outside of the path.
+    pub fn point_at(&self, distance: f64) -> Option<Point> {
+        self.locate(distance)
+            .map(|(s, t)| eval_segment(s.from, s.segment, t))
+    }
+
+    /// Returns the unit tangent vector at `distance` along the path, or
+    /// `None` if the distance lies outside of the path.
+    pub fn tangent_at(&self, distance: f64) -> Option<Point> {
+        let (s, t) = self.locate(distance)?;
+        let mut d = s.derivative_at(t);
+        if d.x == 0.0 && d.y == 0.0 {
+            // Degenerate derivative, e.g. at a control point coinciding with
+            // an end point, use the direction towards a nearby point instead.
+            let (a, b) = if t < 0.5 {
+                (t, t + 1e-6)
+            } else {
+                (t - 1e-6, t)
+            };
+            let (a, b) = (
+                eval_segment(s.from, s.segment, a),
+                eval_segment(s.from, s.segment, b),
+            );
+            d = Point {
+                x: b.x - a.x,
+                y: b.y - a.y,
+            };
+        }
+        let len = d.x.hypot(d.y);
+        Some(if len > 0.0 {
+            Point {
+                x: d.x / len,
+                y: d.y / len,
+            }
+        } else {
+            d
+        })
+    }
+
+    /// Extracts the part of the path between the given distances into a new
+    /// path, keeping curves intact.
+    ///
+    /// The range is clamped to the length of the path, an empty range results
+    /// in an empty path. Ranges spanning multiple figures result in multiple
+    /// figures.
+    pub fn segment(&self, range: Range<f64>) -> Result<Path, Error> {
+        let mut path = Path::default();
+        let start = range.start.max(0.0);
+        let end = range.end.min(self.length());
+        let mut figure = None;
+        for s in &self.segments {
+            let length = s.length();
+            if length == 0.0 || s.start + length <= start || s.start >= end {
+                continue;
+            }
+            let t0 = s.parameter_at(start - s.start);
+            let t1 = s.parameter_at(end - s.start);
+            if figure != Some(s.figure) {
+                let p = eval_segment(s.from, s.segment, t0);
+                path.move_to(p.x, p.y)?;
+                figure = Some(s.figure);
+            }
+            s.append(&mut path, t0, t1)?;
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(
+            (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_measure() {
+        let p = |x, y| Point { x, y };
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0).unwrap();
+        path.line_to(10.0, 0.0).unwrap();
+        path.line_to(10.0, 10.0).unwrap();
+        path.close().unwrap();
+        path.move_to(20.0, 0.0).unwrap();
+        path.quad_to(25.0, 0.0, 30.0, 0.0).unwrap();
+
+        let measure = path.measure(0.01);
+        let diagonal = 200f64.sqrt();
+        assert_eq!(measure.figure_count(), 2);
+        assert!((measure.figure_lengths()[0] - (20.0 + diagonal)).abs() < 1e-9);
+        assert!((measure.figure_lengths()[1] - 10.0).abs() < 1e-9);
+
+        assert_near(measure.point_at(15.0).unwrap(), p(10.0, 5.0));
+        assert_near(measure.tangent_at(15.0).unwrap(), p(0.0, 1.0));
+        assert_near(
+            measure.point_at(20.0 + diagonal + 5.0).unwrap(),
+            p(25.0, 0.0),
+        );
+        assert_near(
+            measure.tangent_at(20.0 + diagonal + 5.0).unwrap(),
+            p(1.0, 0.0),
+        );
+        assert_eq!(measure.point_at(-1.0), None);
+        assert_eq!(measure.point_at(measure.length() + 1.0), None);
+
+        let part = measure.segment(5.0..15.0).unwrap();
+        assert_eq!(
+            part.segments().collect::<Vec<_>>(),
+            [
+                PathSegment::MoveTo(p(5.0, 0.0)),
+                PathSegment::LineTo(p(10.0, 0.0)),
+                PathSegment::LineTo(p(10.0, 5.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sub_curves() {
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0).unwrap();
+        path.cubic_to(0.0, 10.0, 10.0, 10.0, 10.0, 0.0).unwrap();
+        path.conic_to(15.0, -5.0, 20.0, 0.0, 0.7).unwrap();
+
+        let measure = path.measure(0.001);
+        let part = measure.segment(2.0..measure.length() - 2.0).unwrap();
+        let part_measure = part.measure(0.001);
+        assert!((part_measure.length() - (measure.length() - 4.0)).abs() < 1e-2);
+        for d in [0.0, 3.0, 7.5, 12.0] {
+            let a = measure.point_at(d + 2.0).unwrap();
+            let b = part_measure.point_at(d).unwrap();
+            assert!((a.x - b.x).hypot(a.y - b.y) < 1e-2, "{:?} != {:?}", a, b);
+        }
+    }
+}
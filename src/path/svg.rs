@@ -0,0 +1,472 @@
+//! SVG path data (`d` attribute) parsing and serialization.
+
+use std::fmt::{self, Write};
+
+use crate::Error;
+use crate::geometry::Point;
+use crate::path::{Path, PathSegment};
+
+/// The kind of error that occurred while parsing SVG path data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SvgPathErrorKind {
+    /// A command letter was expected, either because the data does not start
+    /// with a move-to or because an unknown character was encountered.
+    ExpectedCommand,
+    /// A number was expected.
+    ExpectedNumber,
+    /// An arc flag (`0` or `1`) was expected.
+    ExpectedFlag,
+    /// Blend2D failed to append the parsed segment to the path.
+    Path,
+}
+
+/// An error returned by [`Path::from_svg_path_data`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseSvgPathError {
+    position: usize,
+    kind: SvgPathErrorKind,
+}
+
+impl ParseSvgPathError {
+    /// The byte offset into the path data at which the error occurred.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    #[inline]
+    pub fn kind(&self) -> SvgPathErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for ParseSvgPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self.kind {
+            SvgPathErrorKind::ExpectedCommand => "expected a path command",
+            SvgPathErrorKind::ExpectedNumber => "expected a number",
+            SvgPathErrorKind::ExpectedFlag => "expected an arc flag",
+            SvgPathErrorKind::Path => "failed to build path",
+        };
+        write!(f, "{} at position {}", msg, self.position)
+    }
+}
+
+impl std::error::Error for ParseSvgPathError {}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    #[inline]
+    fn error(&self, kind: SvgPathErrorKind) -> ParseSvgPathError {
+        ParseSvgPathError {
+            position: self.pos,
+            kind,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r' | b'\x0C') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips whitespace and at most one comma.
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.peek() == Some(b',') {
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+    }
+
+    #[inline]
+    fn at_number(&self) -> bool {
+        matches!(self.peek(), Some(b'0'..=b'9' | b'.' | b'-' | b'+'))
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<f64, ParseSvgPathError> {
+        self.skip_separator();
+        let start = self.pos;
+        if let Some(b'-' | b'+') = self.peek() {
+            self.pos += 1;
+        }
+        let mut digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return Err(self.error(SvgPathErrorKind::ExpectedNumber));
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if let Some(b'-' | b'+') = self.peek() {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                // Not an exponent, e.g. `1em`, leave it for the next token.
+                self.pos = mantissa_end;
+            }
+        }
+        // The scanned bytes are ASCII digits, signs, dots and exponents only.
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+        text.parse().map_err(|_| ParseSvgPathError {
+            position: start,
+            kind: SvgPathErrorKind::ExpectedNumber,
+        })
+    }
+
+    fn flag(&mut self) -> Result<bool, ParseSvgPathError> {
+        self.skip_separator();
+        let flag = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(self.error(SvgPathErrorKind::ExpectedFlag)),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+
+    fn point(&mut self, origin: Point) -> Result<Point, ParseSvgPathError> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(Point {
+            x: origin.x + x,
+            y: origin.y + y,
+        })
+    }
+}
+
+/// The control point of the previous segment, used by the smooth commands.
+#[derive(Copy, Clone)]
+enum PrevControl {
+    None,
+    Quad(Point),
+    Cubic(Point),
+}
+
+#[inline]
+fn reflect(ctrl: Point, around: Point) -> Point {
+    Point {
+        x: 2.0 * around.x - ctrl.x,
+        y: 2.0 * around.y - ctrl.y,
+    }
+}
+
+pub(super) fn parse(data: &str) -> Result<Path, ParseSvgPathError> {
+    let mut parser = Parser {
+        data: data.as_bytes(),
+        pos: 0,
+    };
+    let mut path = Path::default();
+    let mut prev_command: Option<u8> = None;
+    let mut current = Point::default();
+    let mut figure_start = Point::default();
+    let mut prev_control = PrevControl::None;
+    let mut figure_closed = false;
+
+    loop {
+        parser.skip_whitespace();
+        let Some(c) = parser.peek() else {
+            break;
+        };
+        let command_pos = parser.pos;
+        let command = match prev_command {
+            _ if c.is_ascii_alphabetic() => {
+                parser.pos += 1;
+                c
+            },
+            // Additional argument groups repeat the previous command, with
+            // move-to turning into line-to.
+            Some(prev) if !matches!(prev, b'Z' | b'z') => {
+                if c == b',' {
+                    parser.pos += 1;
+                    parser.skip_whitespace();
+                }
+                if !parser.at_number() {
+                    return Err(parser.error(SvgPathErrorKind::ExpectedCommand));
+                }
+                match prev {
+                    b'M' => b'L',
+                    b'm' => b'l',
+                    prev => prev,
+                }
+            },
+            _ => return Err(parser.error(SvgPathErrorKind::ExpectedCommand)),
+        };
+        if prev_command.is_none() && !matches!(command, b'M' | b'm') {
+            return Err(ParseSvgPathError {
+                position: command_pos,
+                kind: SvgPathErrorKind::ExpectedCommand,
+            });
+        }
+
+        let origin = if command.is_ascii_lowercase() {
+            current
+        } else {
+            Point::default()
+        };
+        let upper = command.to_ascii_uppercase();
+        if figure_closed && upper != b'M' {
+            // A figure following a close command starts at the start of the
+            // closed figure.
+            path.move_to(figure_start.x, figure_start.y)
+                .map_err(|e| path_error(command_pos, e))?;
+        }
+        figure_closed = false;
+
+        let result = match upper {
+            b'M' => {
+                let p = parser.point(origin)?;
+                figure_start = p;
+                current = p;
+                prev_control = PrevControl::None;
+                path.move_to(p.x, p.y)
+            },
+            b'L' => {
+                let p = parser.point(origin)?;
+                current = p;
+                prev_control = PrevControl::None;
+                path.line_to(p.x, p.y)
+            },
+            b'H' => {
+                current.x = origin.x + parser.number()?;
+                prev_control = PrevControl::None;
+                path.line_to(current.x, current.y)
+            },
+            b'V' => {
+                current.y = origin.y + parser.number()?;
+                prev_control = PrevControl::None;
+                path.line_to(current.x, current.y)
+            },
+            b'C' | b'S' => {
+                let c1 = if upper == b'C' {
+                    parser.point(origin)?
+                } else {
+                    match prev_control {
+                        PrevControl::Cubic(ctrl) => reflect(ctrl, current),
+                        _ => current,
+                    }
+                };
+                let c2 = parser.point(origin)?;
+                let p = parser.point(origin)?;
+                current = p;
+                prev_control = PrevControl::Cubic(c2);
+                path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y)
+            },
+            b'Q' | b'T' => {
+                let c1 = if upper == b'Q' {
+                    parser.point(origin)?
+                } else {
+                    match prev_control {
+                        PrevControl::Quad(ctrl) => reflect(ctrl, current),
+                        _ => current,
+                    }
+                };
+                let p = parser.point(origin)?;
+                current = p;
+                prev_control = PrevControl::Quad(c1);
+                path.quad_to(c1.x, c1.y, p.x, p.y)
+            },
+            b'A' => {
+                let rx = parser.number()?.abs();
+                let ry = parser.number()?.abs();
+                let rotation = parser.number()?;
+                let large_arc = parser.flag()?;
+                let sweep = parser.flag()?;
+                let p = parser.point(origin)?;
+                let start = current;
+                current = p;
+                prev_control = PrevControl::None;
+                if p == start {
+                    Ok(())
+                } else if rx == 0.0 || ry == 0.0 {
+                    path.line_to(p.x, p.y)
+                } else {
+                    path.elliptic_arc_to(rx, ry, rotation.to_radians(), large_arc, sweep, p.x, p.y)
+                }
+            },
+            b'Z' => {
+                current = figure_start;
+                prev_control = PrevControl::None;
+                figure_closed = true;
+                path.close()
+            },
+            _ => {
+                return Err(ParseSvgPathError {
+                    position: command_pos,
+                    kind: SvgPathErrorKind::ExpectedCommand,
+                });
+            },
+        };
+        result.map_err(|e| path_error(command_pos, e))?;
+        prev_command = Some(command);
+    }
+    Ok(path)
+}
+
+#[inline]
+fn path_error(position: usize, _: Error) -> ParseSvgPathError {
+    ParseSvgPathError {
+        position,
+        kind: SvgPathErrorKind::Path,
+    }
+}
+
+struct Num(f64, Option<usize>);
+
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Num(value, precision) = *self;
+        let Some(precision) = precision else {
+            // `Display` for floats yields the shortest representation that
+            // round-trips.
+            return write!(f, "{}", value + 0.0);
+        };
+        let s = format!("{:.*}", precision, value);
+        let s = if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            &s
+        };
+        f.write_str(if s == "-0" { "0" } else { s })
+    }
+}
+
+pub(super) fn serialize(path: &Path, precision: Option<usize>) -> String {
+    let mut out = String::new();
+    let push = |out: &mut String, cmd: char, points: &[Point]| {
+        out.push(cmd);
+        for (i, p) in points.iter().enumerate() {
+            if i != 0 {
+                out.push(' ');
+            }
+            // Writing into a `String` cannot fail.
+            let _ = write!(out, "{} {}", Num(p.x, precision), Num(p.y, precision));
+        }
+    };
+    let mut current = Point::default();
+    let mut figure_start = Point::default();
+    for segment in path.segments() {
+        match segment {
+            PathSegment::MoveTo(p) => {
+                push(&mut out, 'M', &[p]);
+                figure_start = p;
+                current = p;
+            },
+            PathSegment::LineTo(p) => {
+                push(&mut out, 'L', &[p]);
+                current = p;
+            },
+            PathSegment::QuadTo(c, p) => {
+                push(&mut out, 'Q', &[c, p]);
+                current = p;
+            },
+            PathSegment::CubicTo(c1, c2, p) => {
+                push(&mut out, 'C', &[c1, c2, p]);
+                current = p;
+            },
+            PathSegment::ConicTo(c, p, w) => {
+                // SVG has no rational curves, so conics are approximated by a
+                // cubic. This is exact for parabolas (w = 1).
+                let k = 4.0 * w / (3.0 * (1.0 + w));
+                let c1 = Point {
+                    x: current.x + (c.x - current.x) * k,
+                    y: current.y + (c.y - current.y) * k,
+                };
+                let c2 = Point {
+                    x: p.x + (c.x - p.x) * k,
+                    y: p.y + (c.y - p.y) * k,
+                };
+                push(&mut out, 'C', &[c1, c2, p]);
+                current = p;
+            },
+            PathSegment::Close => {
+                out.push('Z');
+                current = figure_start;
+            },
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_parse() {
+        let path = Path::from_svg_path_data(
+            "M10,20 l5-5 H30 v10 c1 1 2 2 3 3 s1 1 2 2 Q0 0 1.5e1 .5 t1 1 z m1 1 2 2",
+        )
+        .unwrap();
+        assert_eq!(
+            path.segments().collect::<Vec<_>>(),
+            [
+                PathSegment::MoveTo(p(10.0, 20.0)),
+                PathSegment::LineTo(p(15.0, 15.0)),
+                PathSegment::LineTo(p(30.0, 15.0)),
+                PathSegment::LineTo(p(30.0, 25.0)),
+                PathSegment::CubicTo(p(31.0, 26.0), p(32.0, 27.0), p(33.0, 28.0)),
+                PathSegment::CubicTo(p(34.0, 29.0), p(34.0, 29.0), p(35.0, 30.0)),
+                PathSegment::QuadTo(p(0.0, 0.0), p(15.0, 0.5)),
+                PathSegment::QuadTo(p(30.0, 1.0), p(16.0, 1.5)),
+                PathSegment::Close,
+                PathSegment::MoveTo(p(11.0, 21.0)),
+                PathSegment::LineTo(p(13.0, 23.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |data: &str| {
+            let e = Path::from_svg_path_data(data).err().unwrap();
+            (e.position(), e.kind())
+        };
+        assert_eq!(err("L0 0"), (0, SvgPathErrorKind::ExpectedCommand));
+        assert_eq!(err("M0 0 L1"), (7, SvgPathErrorKind::ExpectedNumber));
+        assert_eq!(err("M0 0 X"), (5, SvgPathErrorKind::ExpectedCommand));
+        assert_eq!(
+            err("M0 0 A1 1 0 2 0 1 1"),
+            (12, SvgPathErrorKind::ExpectedFlag)
+        );
+        assert_eq!(err("M0 0 Z 1"), (7, SvgPathErrorKind::ExpectedCommand));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let data = "M0.1 -2L3 4Q5 6 7 8C1 2 3 4 5 6Z";
+        let path = Path::from_svg_path_data(data).unwrap();
+        assert_eq!(path.to_svg_path_data(None), data);
+        assert_eq!(
+            Path::from_svg_path_data("M0.123456 -0.0001")
+                .unwrap()
+                .to_svg_path_data(3),
+            "M0.123 0"
+        );
+    }
+}
@@ -4,8 +4,22 @@ use crate::ExtendMode;
 use crate::geometry::{Matrix2D, RectI};
 use crate::{Error, Image, err_to_result};
 
+#[repr(transparent)]
 pub struct Pattern(pub(crate) ffi::BLPatternCore);
 
+use ffi::BLPatternQuality::*;
+bl_enum! {
+    /// Quality of sampling patterns, set through
+    /// [`Context::set_pattern_quality`](crate::Context::set_pattern_quality).
+    pub enum PatternQuality {
+        /// Nearest neighbor interpolation.
+        Nearest  = BL_PATTERN_QUALITY_NEAREST,
+        /// Bilinear interpolation.
+        Bilinear = BL_PATTERN_QUALITY_BILINEAR,
+    }
+    Default => Nearest
+}
+
 impl Pattern {
     /// Creates a new pattern that borrows the given [`Image`] immutably for its
     /// lifetime.
@@ -1,51 +1,173 @@
+//! Integer regions made of non-overlapping boxes.
 use std::borrow::Borrow;
-use std::{fmt, slice};
+use std::ops;
 
-use crate::error::{errcode_to_result, expect_mem_err, OutOfMemory};
 use crate::geometry::{BoxI, HitTest, PointI, RectI};
-use crate::variant::WrappedBlCore;
-use crate::BooleanOp;
 
-use ffi::BLRegionType::*;
-bl_enum! {
-    pub enum RegionType {
-        Empty   = BL_REGION_TYPE_EMPTY,
-        Rect    = BL_REGION_TYPE_RECT,
-        Complex = BL_REGION_TYPE_COMPLEX,
-    }
-    Default => Empty
+/// The shape of a [`Region`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegionType {
+    /// The region contains no boxes.
+    Empty,
+    /// The region consists of a single box.
+    Rect,
+    /// The region consists of multiple boxes.
+    Complex,
 }
 
-#[repr(transparent)]
+/// A set of pixels described by non-overlapping [`BoxI`]s.
+///
+/// The boxes are stored y-x banded: they are sorted by their top edge, boxes
+/// in the same band share their top and bottom edges and are sorted by their
+/// left edge. Boxes touching horizontally and bands with identical spans
+/// touching vertically are always coalesced, which makes the representation
+/// of a set of pixels unique.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Region {
-    core: ffi::BLRegionCore,
+    boxes: Vec<BoxI>,
+    bounding_box: BoxI,
 }
 
-unsafe impl WrappedBlCore for Region {
-    type Core = ffi::BLRegionCore;
-    const IMPL_TYPE_INDEX: usize = crate::variant::ImplType::Region as usize;
+/// A horizontal span `[x0, x1)`.
+type Span = (i32, i32);
 
-    #[inline]
-    fn from_core(core: Self::Core) -> Self {
-        Region { core }
+/// Incrementally builds the banded representation of a region.
+struct Builder {
+    boxes: Vec<BoxI>,
+    /// Start of the last band in `boxes`.
+    last_band: usize,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Builder {
+            boxes: Vec::new(),
+            last_band: 0,
+        }
+    }
+
+    /// Appends a band, the bands have to be pushed from top to bottom.
+    fn push_band(&mut self, y0: i32, y1: i32, spans: &[Span]) {
+        if spans.is_empty() {
+            return;
+        }
+        let last = &mut self.boxes[self.last_band..];
+        let coalesce = last.first().is_some_and(|b| b.y1 == y0)
+            && last.len() == spans.len()
+            && last.iter().zip(spans).all(|(b, s)| (b.x0, b.x1) == *s);
+        if coalesce {
+            last.iter_mut().for_each(|b| b.y1 = y1);
+        } else {
+            self.last_band = self.boxes.len();
+            self.boxes
+                .extend(spans.iter().map(|&(x0, x1)| BoxI::new(x0, y0, x1, y1)));
+        }
+    }
+
+    fn finish(self) -> Region {
+        let bounding_box = match (self.boxes.first(), self.boxes.last()) {
+            (Some(first), Some(last)) => BoxI::new(
+                self.boxes.iter().map(|b| b.x0).min().unwrap(),
+                first.y0,
+                self.boxes.iter().map(|b| b.x1).max().unwrap(),
+                last.y1,
+            ),
+            _ => BoxI::default(),
+        };
+        Region {
+            boxes: self.boxes,
+            bounding_box,
+        }
+    }
+}
+
+/// Returns the sorted and deduplicated top and bottom edges of all boxes.
+fn y_edges<'a>(boxes: impl Iterator<Item = &'a BoxI>) -> Vec<i32> {
+    let mut ys = boxes.flat_map(|b| [b.y0, b.y1]).collect::<Vec<_>>();
+    ys.sort_unstable();
+    ys.dedup();
+    ys
+}
+
+/// Appends the spans of `a` and `b` combined by `op` to `out`, merging
+/// touching spans. Both inputs must be sorted and non-overlapping.
+fn combine_spans(a: &[Span], b: &[Span], op: fn(bool, bool) -> bool, out: &mut Vec<Span>) {
+    let mut xs = a
+        .iter()
+        .chain(b)
+        .flat_map(|&(x0, x1)| [x0, x1])
+        .collect::<Vec<_>>();
+    xs.sort_unstable();
+    xs.dedup();
+    let (mut ia, mut ib) = (0, 0);
+    for w in xs.windows(2) {
+        let (x0, x1) = (w[0], w[1]);
+        while ia < a.len() && a[ia].1 <= x0 {
+            ia += 1;
+        }
+        while ib < b.len() && b[ib].1 <= x0 {
+            ib += 1;
+        }
+        let in_a = ia < a.len() && a[ia].0 <= x0;
+        let in_b = ib < b.len() && b[ib].0 <= x0;
+        if op(in_a, in_b) {
+            match out.last_mut() {
+                Some(last) if last.1 == x0 => last.1 = x1,
+                _ => out.push((x0, x1)),
+            }
+        }
     }
 }
 
 impl Region {
+    /// Creates an empty region.
     #[inline]
     pub fn new() -> Self {
-        Region::from_core(*Self::none())
+        Self::default()
+    }
+
+    /// Creates a region covering the union of the given boxes, which may
+    /// overlap and be in any order.
+    pub fn from_boxes(boxes: &[BoxI]) -> Self {
+        let boxes = boxes.iter().filter(|b| !b.is_empty()).collect::<Vec<_>>();
+        let mut builder = Builder::new();
+        let mut spans = Vec::new();
+        let mut merged = Vec::new();
+        for w in y_edges(boxes.iter().copied()).windows(2) {
+            let (y0, y1) = (w[0], w[1]);
+            spans.clear();
+            spans.extend(
+                boxes
+                    .iter()
+                    .filter(|b| b.y0 <= y0 && b.y1 >= y1)
+                    .map(|b| (b.x0, b.x1)),
+            );
+            spans.sort_unstable();
+            merged.clear();
+            for &(x0, x1) in &spans {
+                match merged.last_mut() {
+                    Some((_, last_x1)) if *last_x1 >= x0 => *last_x1 = x1.max(*last_x1),
+                    _ => merged.push((x0, x1)),
+                }
+            }
+            builder.push_band(y0, y1, &merged);
+        }
+        builder.finish()
     }
 
     #[inline]
     pub fn region_type(&self) -> RegionType {
-        unsafe { ffi::blRegionGetType(self.core()).into() }
+        match self.boxes.len() {
+            0 => RegionType::Empty,
+            1 => RegionType::Rect,
+            _ => RegionType::Complex,
+        }
     }
 
     /// Returns true if this region is empty.
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.region_type() == RegionType::Empty
+        self.boxes.is_empty()
     }
 
     /// Returns true if this region is a rectangle.
@@ -60,217 +182,177 @@ impl Region {
         self.region_type() == RegionType::Complex
     }
 
-    /// The region's boxes.
+    /// The region's boxes in y-x banded order.
     #[inline]
     pub fn data(&self) -> &[BoxI] {
-        unsafe { slice::from_raw_parts(ffi::blRegionGetData(self.core()) as *const _, self.len()) }
+        &self.boxes
     }
 
     /// The number of [`BoxI`] this region contains.
     #[inline]
     pub fn len(&self) -> usize {
-        unsafe { ffi::blRegionGetSize(self.core()) }
-    }
-
-    /// Returns the currently allocated capacity of the region.
-    #[inline]
-    pub fn capacity(&self) -> usize {
-        unsafe { ffi::blRegionGetCapacity(self.core()) }
+        self.boxes.len()
     }
 
-    /// A bounding box representing this region.
+    /// A bounding box representing this region, all zeros if the region is
+    /// empty.
     #[inline]
-    pub fn bounding_box(&self) -> &BoxI {
-        unsafe { crate::util::cast_ref(&self.impl_().boundingBox) }
+    pub fn bounding_box(&self) -> BoxI {
+        self.bounding_box
     }
 
     /// Clears the region.
     #[inline]
     pub fn clear(&mut self) {
-        unsafe { expect_mem_err(ffi::blRegionClear(self.core_mut())) };
-    }
-
-    /// Reserves capacity for at least n boxes.
-    ///
-    /// # Panics
-    ///
-    /// Panics if blend2d returns an
-    /// [`OutOfMemory`](../error/enum.Error.html#variant.OutOfMemory) error
-    #[inline]
-    pub fn reserve(&mut self, n: usize) {
-        self.try_reserve(n).expect("memory allocation failed")
-    }
-
-    /// Reserves capacity for at least n boxes.
-    #[inline]
-    pub fn try_reserve(&mut self, n: usize) -> std::result::Result<(), OutOfMemory> {
-        unsafe { OutOfMemory::from_errcode(ffi::blRegionReserve(self.core_mut(), n)) }
-    }
-
-    /// Shrinks the region's allocated capacity down to its current length.
-    #[inline]
-    pub fn shrink_to_fit(&mut self) {
-        unsafe { expect_mem_err(ffi::blRegionShrink(self.core_mut())) };
+        self.boxes.clear();
+        self.bounding_box = BoxI::default();
+    }
+
+    /// Returns the bands of the region as `(y0, y1, spans)`.
+    fn bands(&self) -> Vec<(i32, i32, Vec<Span>)> {
+        let mut bands = Vec::<(i32, i32, Vec<Span>)>::new();
+        for b in &self.boxes {
+            match bands.last_mut() {
+                Some((y0, _, spans)) if *y0 == b.y0 => spans.push((b.x0, b.x1)),
+                _ => bands.push((b.y0, b.y1, vec![(b.x0, b.x1)])),
+            }
+        }
+        bands
+    }
+
+    fn combine(&self, other: &Region, op: fn(bool, bool) -> bool) -> Region {
+        let (a, b) = (self.bands(), other.bands());
+        let ys = y_edges(self.boxes.iter().chain(&other.boxes));
+        let mut builder = Builder::new();
+        let mut spans = Vec::new();
+        let (mut ia, mut ib) = (0, 0);
+        for w in ys.windows(2) {
+            let (y0, y1) = (w[0], w[1]);
+            while ia < a.len() && a[ia].1 <= y0 {
+                ia += 1;
+            }
+            while ib < b.len() && b[ib].1 <= y0 {
+                ib += 1;
+            }
+            let spans_a = a
+                .get(ia)
+                .filter(|band| band.0 <= y0)
+                .map_or(&[][..], |band| &band.2);
+            let spans_b = b
+                .get(ib)
+                .filter(|band| band.0 <= y0)
+                .map_or(&[][..], |band| &band.2);
+            spans.clear();
+            combine_spans(spans_a, spans_b, op, &mut spans);
+            builder.push_band(y0, y1, &spans);
+        }
+        builder.finish()
     }
 
+    /// Returns the region covering the pixels of either region.
     #[inline]
-    pub fn combine(&mut self, other: &Self, op: BooleanOp) {
-        unsafe {
-            expect_mem_err(ffi::blRegionCombine(
-                self.core_mut(),
-                self.core(),
-                other.core(),
-                op.into(),
-            ));
-        }
+    pub fn union(&self, other: &Region) -> Region {
+        self.combine(other, |a, b| a || b)
     }
 
+    /// Returns the region covering the pixels of both regions.
     #[inline]
-    pub fn combine_rb(&mut self, b: &BoxI, op: BooleanOp) {
-        unsafe {
-            expect_mem_err(ffi::blRegionCombineRB(
-                self.core_mut(),
-                self.core(),
-                &b as *const _ as *const _,
-                op.into(),
-            ))
-        };
+    pub fn intersect(&self, other: &Region) -> Region {
+        self.combine(other, |a, b| a && b)
     }
 
+    /// Returns the region covering the pixels of exactly one of the regions.
     #[inline]
-    pub fn combine_br(&mut self, b: &BoxI, op: BooleanOp) {
-        unsafe {
-            expect_mem_err(ffi::blRegionCombineBR(
-                self.core_mut(),
-                &b as *const _ as *const _,
-                self.core(),
-                op.into(),
-            ))
-        };
+    pub fn xor(&self, other: &Region) -> Region {
+        self.combine(other, |a, b| a != b)
     }
 
+    /// Returns the region covering the pixels of this region that are not
+    /// part of `other`.
     #[inline]
-    pub fn combine_bb(&mut self, b: &BoxI, b2: &BoxI, op: BooleanOp) {
-        unsafe {
-            expect_mem_err(ffi::blRegionCombineBB(
-                self.core_mut(),
-                &b as *const _ as *const _,
-                &b2 as *const _ as *const _,
-                op.into(),
-            ))
-        };
+    pub fn subtract(&self, other: &Region) -> Region {
+        self.combine(other, |a, b| a && !b)
     }
 
     /// Translates the region by the given [`PointI`].
-    /// Possible overflow will be handled by clipping to a maximum region
-    /// boundary, so the final region could be smaller than the region before
-    /// translation.
-    #[inline]
+    ///
+    /// Coordinates saturate at the integer bounds, so the final region could
+    /// be smaller than the region before translation.
     pub fn translate(&mut self, p: PointI) {
-        unsafe {
-            expect_mem_err(ffi::blRegionTranslate(
-                self.core_mut(),
-                self.core(),
-                &p as *const _ as *const _,
-            ))
-        };
-    }
-
-    /// Translates the region by the given [`PointI`] and clips it to the given
-    /// `[BoxI]`.
-    #[inline]
-    pub fn translate_and_clip(&mut self, p: PointI, clip: &BoxI) {
-        unsafe {
-            expect_mem_err(ffi::blRegionTranslateAndClip(
-                self.core_mut(),
-                self.core(),
-                &p as *const _ as *const _,
-                clip as *const _ as *const _,
-            ))
-        };
-    }
-    /// Translates the region by the given region and clips it to the given
-    /// `[BoxI]`.
-    #[inline]
-    pub fn intersect_and_clip(&mut self, region: &Region, clip: &BoxI) {
-        unsafe {
-            expect_mem_err(ffi::blRegionIntersectAndClip(
-                self.core_mut(),
-                self.core(),
-                region.core(),
-                clip as *const _ as *const _,
-            ))
+        let saturated = self.boxes.iter().any(|b| {
+            b.x0.checked_add(p.x).is_none()
+                || b.x1.checked_add(p.x).is_none()
+                || b.y0.checked_add(p.y).is_none()
+                || b.y1.checked_add(p.y).is_none()
+        });
+        let translate = |b: &BoxI| {
+            BoxI::new(
+                b.x0.saturating_add(p.x),
+                b.y0.saturating_add(p.y),
+                b.x1.saturating_add(p.x),
+                b.y1.saturating_add(p.y),
+            )
         };
+        if saturated {
+            *self = Region::from_boxes(&self.boxes.iter().map(translate).collect::<Vec<_>>());
+        } else {
+            self.boxes.iter_mut().for_each(|b| *b = translate(b));
+            self.bounding_box = translate(&self.bounding_box);
+        }
     }
 
     /// Tests if a given [`PointI`] is in the region.
-    #[inline]
     pub fn hit_test(&self, p: PointI) -> HitTest {
-        unsafe { ffi::blRegionHitTest(self.core(), &p as *const _ as *const _).into() }
+        let band_start = self.boxes.partition_point(|b| b.y1 <= p.y);
+        let hit = self.boxes[band_start..]
+            .iter()
+            .take_while(|b| b.y0 <= p.y)
+            .any(|b| b.contains(p));
+        if hit { HitTest::In } else { HitTest::Out }
     }
 
-    /// Tests if a given [`BoxI`] is in the region.
-    #[inline]
+    /// Tests if a given [`BoxI`] is fully, partially or not at all covered by
+    /// the region.
     pub fn hit_test_box(&self, b: &BoxI) -> HitTest {
-        unsafe { ffi::blRegionHitTestBoxI(self.core(), b as *const _ as *const _).into() }
+        if b.is_empty() || self.bounding_box.intersect(b).is_none() {
+            return HitTest::Out;
+        }
+        let boxed = Region::from(*b);
+        let intersection = self.intersect(&boxed);
+        if intersection.is_empty() {
+            HitTest::Out
+        } else if intersection == boxed {
+            HitTest::In
+        } else {
+            HitTest::Part
+        }
     }
 }
 
 impl From<BoxI> for Region {
+    #[inline]
     fn from(b: BoxI) -> Self {
-        let mut this = Self::new();
-        unsafe {
-            errcode_to_result(ffi::blRegionAssignBoxI(
-                this.core_mut(),
-                &b as *const _ as *const _,
-            ))
-            .unwrap()
-        };
-        this
+        Region::from_boxes(&[b])
     }
 }
 
-impl<'a> From<&'a [BoxI]> for Region {
-    fn from(b: &'a [BoxI]) -> Self {
-        let mut this = Self::new();
-        unsafe {
-            errcode_to_result(ffi::blRegionAssignBoxIArray(
-                this.core_mut(),
-                b.as_ptr() as *const _,
-                b.len(),
-            ))
-            .unwrap()
-        };
-        this
+impl From<RectI> for Region {
+    #[inline]
+    fn from(r: RectI) -> Self {
+        Region::from(BoxI::from(r))
     }
 }
 
-impl From<RectI> for Region {
-    fn from(r: RectI) -> Self {
-        let mut this = Self::new();
-        unsafe {
-            errcode_to_result(ffi::blRegionAssignRectI(
-                this.core_mut(),
-                &r as *const _ as *const _,
-            ))
-            .unwrap()
-        };
-        this
+impl<'a> From<&'a [BoxI]> for Region {
+    #[inline]
+    fn from(b: &'a [BoxI]) -> Self {
+        Region::from_boxes(b)
     }
 }
 
 impl<'a> From<&'a [RectI]> for Region {
     fn from(r: &'a [RectI]) -> Self {
-        let mut this = Self::new();
-        unsafe {
-            errcode_to_result(ffi::blRegionAssignRectIArray(
-                this.core_mut(),
-                r.as_ptr() as *const _,
-                r.len(),
-            ))
-            .unwrap()
-        };
-        this
+        Region::from_boxes(&r.iter().map(|&r| BoxI::from(r)).collect::<Vec<_>>())
     }
 }
 
@@ -288,37 +370,101 @@ impl Borrow<[BoxI]> for Region {
     }
 }
 
-impl Default for Region {
+impl ops::BitOr for &Region {
+    type Output = Region;
+
     #[inline]
-    fn default() -> Self {
-        Self::new()
+    fn bitor(self, rhs: &Region) -> Region {
+        self.union(rhs)
     }
 }
 
-impl PartialEq for Region {
+impl ops::BitAnd for &Region {
+    type Output = Region;
+
     #[inline]
-    fn eq(&self, other: &Self) -> bool {
-        unsafe { ffi::blRegionEquals(self.core(), other.core()) }
+    fn bitand(self, rhs: &Region) -> Region {
+        self.intersect(rhs)
     }
 }
 
-impl fmt::Debug for Region {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Region")
-            .field("region_type", &self.region_type())
-            .field("data", &self.data())
-            .finish()
+impl ops::BitXor for &Region {
+    type Output = Region;
+
+    #[inline]
+    fn bitxor(self, rhs: &Region) -> Region {
+        self.xor(rhs)
     }
 }
 
-impl Clone for Region {
-    fn clone(&self) -> Self {
-        Self::from_core(self.init_weak())
+impl ops::Sub for &Region {
+    type Output = Region;
+
+    #[inline]
+    fn sub(self, rhs: &Region) -> Region {
+        self.subtract(rhs)
     }
 }
 
-impl Drop for Region {
-    fn drop(&mut self) {
-        unsafe { ffi::blRegionReset(&mut self.core) };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b(x0: i32, y0: i32, x1: i32, y1: i32) -> BoxI {
+        BoxI::new(x0, y0, x1, y1)
+    }
+
+    #[test]
+    fn test_from_boxes() {
+        let region = Region::from_boxes(&[b(0, 0, 10, 10), b(5, 5, 15, 15)]);
+        assert_eq!(
+            region.data(),
+            [b(0, 0, 10, 5), b(0, 5, 15, 10), b(5, 10, 15, 15)]
+        );
+        assert_eq!(region.bounding_box(), b(0, 0, 15, 15));
+        assert_eq!(region.region_type(), RegionType::Complex);
+
+        // Touching boxes are coalesced in both directions.
+        let region = Region::from_boxes(&[b(0, 0, 5, 5), b(5, 0, 10, 5), b(0, 5, 10, 10)]);
+        assert_eq!(region.data(), [b(0, 0, 10, 10)]);
+        assert!(region.is_rect());
+    }
+
+    #[test]
+    fn test_boolean_ops() {
+        let a = Region::from(b(0, 0, 10, 10));
+        let c = Region::from(b(5, 5, 15, 15));
+        assert_eq!((&a & &c).data(), [b(5, 5, 10, 10)]);
+        assert_eq!((&a - &c).data(), [b(0, 0, 10, 5), b(0, 5, 5, 10)]);
+        assert_eq!(
+            (&a ^ &c).data(),
+            [
+                b(0, 0, 10, 5),
+                b(0, 5, 5, 10),
+                b(10, 5, 15, 10),
+                b(5, 10, 15, 15)
+            ]
+        );
+        assert_eq!(
+            &a | &c,
+            Region::from_boxes(&[b(0, 0, 10, 10), b(5, 5, 15, 15)])
+        );
+        assert_eq!(&(&a - &c) | &c, &a | &c);
+        assert!((&a - &a).is_empty());
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let mut region = Region::from_boxes(&[b(0, 0, 10, 10), b(20, 0, 30, 10)]);
+        assert_eq!(region.hit_test(PointI::new(5, 5)), HitTest::In);
+        assert_eq!(region.hit_test(PointI::new(15, 5)), HitTest::Out);
+        assert_eq!(region.hit_test(PointI::new(10, 5)), HitTest::Out);
+        assert_eq!(region.hit_test_box(&b(1, 1, 9, 9)), HitTest::In);
+        assert_eq!(region.hit_test_box(&b(5, 5, 25, 9)), HitTest::Part);
+        assert_eq!(region.hit_test_box(&b(11, 0, 19, 10)), HitTest::Out);
+
+        region.translate(PointI::new(1, 2));
+        assert_eq!(region.data(), [b(1, 2, 11, 12), b(21, 2, 31, 12)]);
+        assert_eq!(region.bounding_box(), b(1, 2, 31, 12));
     }
 }
@@ -1,6 +1,16 @@
+//! Blend2D runtime information and resource management.
+//!
+//! Blend2D's C API doesn't expose statistics of its thread pool. The closest
+//! are [`BuildInfo::max_thread_count`], [`SystemInfo::thread_count`] and
+//! [`SystemInfo::thread_stack_size`], and idle pool threads can be released
+//! with [`CleanupFlags::THREAD_POOL`].
+use std::mem;
+use std::os::raw::c_void;
+
 use bitflags::bitflags;
 
-use crate::error::{errcode_to_result, Result};
+use crate::util::c_string;
+use crate::{Error, err_to_result};
 
 use ffi::BLRuntimeBuildType::*;
 bl_enum! {
@@ -33,7 +43,10 @@ bl_enum! {
 use ffi::BLRuntimeCpuFeatures::*;
 bitflags! {
     /// CPU features Blend2D supports.
-    #[derive(Default)]
+    ///
+    /// The flags are specific to the [`CpuArch`] they were queried for, X86
+    /// and ARM features share the same bits.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
     pub struct CpuFeatures: u32 {
         const X86_SSE2   = BL_RUNTIME_CPU_FEATURE_X86_SSE2 as u32;
         const X86_SSE3   = BL_RUNTIME_CPU_FEATURE_X86_SSE3 as u32;
@@ -42,13 +55,20 @@ bitflags! {
         const X86_SSE4_2 = BL_RUNTIME_CPU_FEATURE_X86_SSE4_2 as u32;
         const X86_AVX    = BL_RUNTIME_CPU_FEATURE_X86_AVX as u32;
         const X86_AVX2   = BL_RUNTIME_CPU_FEATURE_X86_AVX2 as u32;
+        const X86_AVX512 = BL_RUNTIME_CPU_FEATURE_X86_AVX512 as u32;
+        const ARM_ASIMD  = BL_RUNTIME_CPU_FEATURE_ARM_ASIMD as u32;
+        const ARM_CRC32  = BL_RUNTIME_CPU_FEATURE_ARM_CRC32 as u32;
+        const ARM_AES    = BL_RUNTIME_CPU_FEATURE_ARM_AES as u32;
+        const ARM_PMULL  = BL_RUNTIME_CPU_FEATURE_ARM_PMULL as u32;
+        const ARM_SHA1   = BL_RUNTIME_CPU_FEATURE_ARM_SHA1 as u32;
+        const ARM_SHA2   = BL_RUNTIME_CPU_FEATURE_ARM_SHA2 as u32;
     }
 }
 
 use ffi::BLRuntimeCleanupFlags::*;
 bitflags! {
     /// Runtime cleanup flags that can be used through [`cleanup`].
-    #[derive(Default)]
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
     pub struct CleanupFlags: u32 {
         /// Cleanup object memory pool.
         const OBJECT_POOL = BL_RUNTIME_CLEANUP_OBJECT_POOL as u32;
@@ -56,21 +76,40 @@ bitflags! {
         const ZEROED_POOL = BL_RUNTIME_CLEANUP_ZEROED_POOL as u32;
         /// Cleanup thread pool (would join unused threads).
         const THREAD_POOL = BL_RUNTIME_CLEANUP_THREAD_POOL as u32;
+        /// Cleanup everything.
+        const EVERYTHING  = BL_RUNTIME_CLEANUP_EVERYTHING as u32;
     }
 }
 
 /// Tell the runtime to clean up resources according to the specified
 /// [`CleanupFlags`].
-pub fn cleanup(flags: CleanupFlags) -> Result<()> {
-    unsafe { errcode_to_result(ffi::blRuntimeCleanup(flags.bits())) }
+#[inline]
+pub fn cleanup(flags: CleanupFlags) -> Result<(), Error> {
+    err_to_result(unsafe { ffi::bl_runtime_cleanup(flags.bits() as _) })
+}
+
+/// Queries runtime info of the given type, `T` must be the plain C struct
+/// matching `info_type`.
+fn query_info<T>(info_type: ffi::BLRuntimeInfoType::Type) -> Result<T, Error> {
+    unsafe {
+        let mut info = mem::zeroed::<T>();
+        err_to_result(ffi::bl_runtime_query_info(
+            info_type,
+            &raw mut info as *mut c_void,
+        ))
+        .map(|_| info)
+    }
 }
 
 /// Blend2D build information.
-#[repr(C)]
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct BuildInfo {
-    /// Blend2D version stored as `((MAJOR << 16) | (MINOR << 8) | PATCH)`.
-    pub version: u32,
+    /// Major version number.
+    pub major_version: u32,
+    /// Minor version number.
+    pub minor_version: u32,
+    /// Patch version number.
+    pub patch_version: u32,
     /// Blend2D build type, see [`BuildType`].
     pub build_type: BuildType,
     /// Baseline CPU features, see [`CpuFeatures`].
@@ -80,15 +119,14 @@ pub struct BuildInfo {
     /// the minimum feature-set the target CPU must support to run Blend2D.
     ///
     /// Official Blend2D builds set baseline at SSE2 on X86 target and NEON on
-    /// ARM target. Custom builds can set use different baseline, which can be
-    /// read through `BLRuntimeBuildInfo`.
+    /// ARM target. Custom builds can set use different baseline.
     pub baseline_cpu_features: CpuFeatures,
     /// Supported CPU features, see [`CpuFeatures`].
     ///
     /// These features do not represent the features that the host CPU must
     /// support, instead, they represent all features that Blend2D can take
     /// advantage of in C++ code that uses instruction intrinsics. For
-    /// example if AVX2 is part of `supportedCpuFeatures` it means that
+    /// example if AVX2 is part of `supported_cpu_features` it means that
     /// Blend2D can take advantage of it if there is a separate code-path.
     pub supported_cpu_features: CpuFeatures,
     /// Maximum size of an image (both width and height).
@@ -96,35 +134,43 @@ pub struct BuildInfo {
     /// Maximum number of threads for asynchronous operations, including
     /// rendering.
     pub max_thread_count: u32,
-    /// Reserved, must be zero.
-    reserved: [u32; 2],
     /// Identification of the C++ compiler used to build Blend2D.
-    compiler_info: [u8; 32],
+    pub compiler_info: String,
 }
 
 impl BuildInfo {
     /// Queries the runtime's build info.
     #[inline]
-    pub fn query() -> Result<Self> {
+    pub fn query() -> Result<Self, Error> {
         query_build_info()
     }
+
+    /// The version as a `(major, minor, patch)` tuple.
+    #[inline]
+    pub fn version(&self) -> (u32, u32, u32) {
+        (self.major_version, self.minor_version, self.patch_version)
+    }
 }
 
 /// Queries the runtime's build info.
-pub fn query_build_info() -> Result<BuildInfo> {
-    unsafe {
-        let mut info = BuildInfo::default();
-        errcode_to_result(ffi::blRuntimeQueryInfo(
-            ffi::BLRuntimeInfoType::BL_RUNTIME_INFO_TYPE_BUILD as u32,
-            &mut info as *mut _ as *mut _,
-        ))
-        .map(|_| info)
-    }
+pub fn query_build_info() -> Result<BuildInfo, Error> {
+    let info =
+        query_info::<ffi::BLRuntimeBuildInfo>(ffi::BLRuntimeInfoType::BL_RUNTIME_INFO_TYPE_BUILD)?;
+    Ok(BuildInfo {
+        major_version: info.major_version,
+        minor_version: info.minor_version,
+        patch_version: info.patch_version,
+        build_type: info.build_type.into(),
+        baseline_cpu_features: CpuFeatures::from_bits_retain(info.baseline_cpu_features),
+        supported_cpu_features: CpuFeatures::from_bits_retain(info.supported_cpu_features),
+        max_image_size: info.max_image_size,
+        max_thread_count: info.max_thread_count,
+        compiler_info: c_string(&info.compiler_info),
+    })
 }
 
 /// System information queried by the runtime.
-#[repr(C)]
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct SystemInfo {
     /// Host CPU architecture, see [`CpuArch`].
     pub cpu_arch: CpuArch,
@@ -134,39 +180,43 @@ pub struct SystemInfo {
     pub core_count: u32,
     /// Number of threads of the host CPU/CPUs.
     pub thread_count: u32,
-    /// Minimum stack size of threads.
-    pub min_thread_stack_size: u32,
-    /// Minimum stack size of worker threads used by Blend2D.
-    pub min_worker_stack_size: u32,
+    /// Stack size of threads created by Blend2D.
+    pub thread_stack_size: u32,
     /// Allocation granularity of virtual memory (includes thread's stack).
     pub allocation_granularity: u32,
-    /// Reserved for future use.
-    reserved: [u32; 5],
+    /// Host CPU vendor string.
+    pub cpu_vendor: String,
+    /// Host CPU brand string.
+    pub cpu_brand: String,
 }
 
 impl SystemInfo {
     /// Queries the runtime's system info.
     #[inline]
-    pub fn query() -> Result<Self> {
+    pub fn query() -> Result<Self, Error> {
         query_system_info()
     }
 }
 
 /// Queries the runtime's system info.
-pub fn query_system_info() -> Result<SystemInfo> {
-    unsafe {
-        let mut info = SystemInfo::default();
-        errcode_to_result(ffi::blRuntimeQueryInfo(
-            ffi::BLRuntimeInfoType::BL_RUNTIME_INFO_TYPE_SYSTEM as u32,
-            &mut info as *mut _ as *mut _,
-        ))
-        .map(|_| info)
-    }
+pub fn query_system_info() -> Result<SystemInfo, Error> {
+    let info = query_info::<ffi::BLRuntimeSystemInfo>(
+        ffi::BLRuntimeInfoType::BL_RUNTIME_INFO_TYPE_SYSTEM,
+    )?;
+    Ok(SystemInfo {
+        cpu_arch: info.cpu_arch.into(),
+        cpu_features: CpuFeatures::from_bits_retain(info.cpu_features),
+        core_count: info.core_count,
+        thread_count: info.thread_count,
+        thread_stack_size: info.thread_stack_size,
+        allocation_granularity: info.allocation_granularity,
+        cpu_vendor: c_string(&info.cpu_vendor),
+        cpu_brand: c_string(&info.cpu_brand),
+    })
 }
 
-/// Blend2D memory information that provides how much memory Blend2D allocated
-/// and some other details about memory use.
-#[repr(C)]
+/// Blend2D resource information that provides how much memory Blend2D
+/// allocated and some other details about resource use.
 #[derive(Copy, Clone, Debug, Default)]
 pub struct MemoryInfo {
     /// Virtual memory used at this time.
@@ -187,24 +237,54 @@ pub struct MemoryInfo {
     pub zm_block_count: usize,
     /// Count of dynamic pipelines created and cached.
     pub dynamic_pipeline_count: usize,
+    /// Number of active file handles used by Blend2D.
+    pub file_handle_count: usize,
+    /// Number of active file mappings used by Blend2D.
+    pub file_mapping_count: usize,
 }
 
 impl MemoryInfo {
     /// Queries the runtime's memory info.
     #[inline]
-    pub fn query() -> Result<Self> {
+    pub fn query() -> Result<Self, Error> {
         query_memory_info()
     }
 }
 
 /// Queries the runtime's memory info.
-pub fn query_memory_info() -> Result<MemoryInfo> {
-    unsafe {
-        let mut info = MemoryInfo::default();
-        errcode_to_result(ffi::blRuntimeQueryInfo(
-            ffi::BLRuntimeInfoType::BL_RUNTIME_INFO_TYPE_MEMORY as u32,
-            &mut info as *mut _ as *mut _,
-        ))
-        .map(|_| info)
+pub fn query_memory_info() -> Result<MemoryInfo, Error> {
+    let info = query_info::<ffi::BLRuntimeResourceInfo>(
+        ffi::BLRuntimeInfoType::BL_RUNTIME_INFO_TYPE_RESOURCE,
+    )?;
+    Ok(MemoryInfo {
+        vm_used: info.vm_used,
+        vm_reserved: info.vm_reserved,
+        vm_overhead: info.vm_overhead,
+        vm_block_count: info.vm_block_count,
+        zm_used: info.zm_used,
+        zm_reserved: info.zm_reserved,
+        zm_overhead: info.zm_overhead,
+        zm_block_count: info.zm_block_count,
+        dynamic_pipeline_count: info.dynamic_pipeline_count,
+        file_handle_count: info.file_handle_count,
+        file_mapping_count: info.file_mapping_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_info() {
+        let build = query_build_info().unwrap();
+        assert_eq!(build.version().0, 0);
+        assert_eq!(build.version().1, 21);
+        assert!(build.max_image_size > 0);
+
+        let system = query_system_info().unwrap();
+        assert!(system.thread_count > 0);
+        assert!(query_memory_info().is_ok());
+        cleanup(CleanupFlags::EVERYTHING).unwrap();
     }
 }
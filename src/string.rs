@@ -0,0 +1,332 @@
+//! A growable UTF-8 string managed by Blend2D.
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::mem::MaybeUninit;
+use std::os::raw::c_char;
+use std::{fmt, ops, slice, str};
+
+use crate::util::bl_range;
+use crate::{Error, err_to_result};
+
+/// A growable UTF-8 string managed by Blend2D.
+///
+/// Blend2D returns names and other text as strings, for example the names of
+/// fonts. The string derefs to a `str` and copies are reference counted.
+/// Unless a Blend2D string is required, prefer [`std::string::String`].
+///
+/// Blend2D itself doesn't validate its strings, so this wrapper only creates
+/// and edits them from `str`s at character boundaries.
+#[repr(transparent)]
+pub struct String(pub(crate) ffi::BLStringCore);
+
+impl String {
+    /// Creates a new empty string.
+    #[inline]
+    pub fn new() -> Self {
+        let mut core = MaybeUninit::<ffi::BLStringCore>::uninit();
+        unsafe {
+            ffi::bl_string_init(core.as_mut_ptr());
+            String(core.assume_init())
+        }
+    }
+
+    /// Creates a new empty string with space for at least `capacity` bytes.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Result<Self, Error> {
+        let mut s = Self::new();
+        s.reserve(capacity)?;
+        Ok(s)
+    }
+
+    /// Returns the string as a `str`.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(self.as_bytes()) }
+    }
+
+    /// Returns the bytes of the string.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            let data = ffi::bl_string_get_data(&self.0) as *const u8;
+            match data.is_null() {
+                true => &[],
+                false => slice::from_raw_parts(data, self.len()),
+            }
+        }
+    }
+
+    /// The length of the string in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        unsafe { ffi::bl_string_get_size(&self.0) }
+    }
+
+    /// Returns true if the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of bytes the string can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        unsafe { ffi::bl_string_get_capacity(&self.0) }
+    }
+
+    /// Clears the string, keeping its capacity.
+    #[inline]
+    pub fn clear(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_string_clear(&mut self.0) })
+    }
+
+    /// Shrinks the capacity of the string to fit its length.
+    #[inline]
+    pub fn shrink(&mut self) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_string_shrink(&mut self.0) })
+    }
+
+    /// Reserves capacity for at least `n` bytes in total.
+    #[inline]
+    pub fn reserve(&mut self, n: usize) -> Result<(), Error> {
+        err_to_result(unsafe { ffi::bl_string_reserve(&mut self.0, n) })
+    }
+
+    /// Appends `s` to the end of the string.
+    #[inline]
+    pub fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        err_to_result(unsafe {
+            ffi::bl_string_apply_op_data(
+                &mut self.0,
+                ffi::BLModifyOp::BL_MODIFY_OP_APPEND_GROW,
+                s.as_ptr() as *const c_char,
+                s.len(),
+            )
+        })
+    }
+
+    /// Appends `c` to the end of the string.
+    #[inline]
+    pub fn push(&mut self, c: char) -> Result<(), Error> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Inserts `s` at the byte offset `index`.
+    ///
+    /// Fails if `index` is not on a character boundary.
+    #[inline]
+    pub fn insert_str(&mut self, index: usize, s: &str) -> Result<(), Error> {
+        if !self.as_str().is_char_boundary(index) {
+            return Err(Error);
+        }
+        err_to_result(unsafe {
+            ffi::bl_string_insert_data(&mut self.0, index, s.as_ptr() as *const c_char, s.len())
+        })
+    }
+
+    /// Shortens the string to `n` bytes, does nothing if it is shorter
+    /// already.
+    ///
+    /// Fails if `n` is not on a character boundary.
+    #[inline]
+    pub fn truncate(&mut self, n: usize) -> Result<(), Error> {
+        self.remove_range(n..)
+    }
+
+    /// Removes the bytes in `range`.
+    ///
+    /// Fails if the range doesn't start and end on character boundaries.
+    pub fn remove_range<R: ops::RangeBounds<usize>>(&mut self, range: R) -> Result<(), Error> {
+        let range = bl_range(range);
+        let end = range.end.min(self.len());
+        let s = self.as_str();
+        if range.start > end || !s.is_char_boundary(range.start) || !s.is_char_boundary(end) {
+            return Err(Error);
+        }
+        err_to_result(unsafe { ffi::bl_string_remove_range(&mut self.0, range.start, end) })
+    }
+}
+
+impl ops::Deref for String {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for String {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl AsRef<[u8]> for String {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<str> for String {
+    #[inline]
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl From<&str> for String {
+    /// # Panics
+    ///
+    /// Panics if Blend2D fails to allocate memory.
+    fn from(s: &str) -> Self {
+        let mut core = MaybeUninit::<ffi::BLStringCore>::uninit();
+        unsafe {
+            err_to_result(ffi::bl_string_init_with_data(
+                core.as_mut_ptr(),
+                s.as_ptr() as *const c_char,
+                s.len(),
+            ))
+            .expect("memory allocation failed");
+            String(core.assume_init())
+        }
+    }
+}
+
+impl From<std::string::String> for String {
+    /// # Panics
+    ///
+    /// Panics if Blend2D fails to allocate memory.
+    #[inline]
+    fn from(s: std::string::String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+impl From<&String> for std::string::String {
+    #[inline]
+    fn from(s: &String) -> Self {
+        s.as_str().to_owned()
+    }
+}
+
+impl fmt::Write for String {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl fmt::Display for String {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for String {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Default for String {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for String {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { ffi::bl_string_equals(&self.0, &other.0) }
+    }
+}
+
+impl Eq for String {}
+
+impl PartialEq<str> for String {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for String {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Hash for String {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Clone for String {
+    /// Creates a reference counted copy of the string, its contents are
+    /// copied once either copy is modified.
+    #[inline]
+    fn clone(&self) -> Self {
+        let mut core = MaybeUninit::<ffi::BLStringCore>::uninit();
+        unsafe {
+            ffi::bl_string_init_weak(core.as_mut_ptr(), &self.0);
+            String(core.assume_init())
+        }
+    }
+}
+
+impl Drop for String {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ffi::bl_string_destroy(&mut self.0) };
+    }
+}
+
+#[cfg(test)]
+mod test_string {
+    use std::fmt::Write;
+
+    use crate::string::String;
+
+    #[test]
+    fn test_string_edit() {
+        let mut s = String::from("hello");
+        s.push_str(" world").unwrap();
+        s.push('!').unwrap();
+        assert_eq!(s, "hello world!");
+        s.insert_str(5, ",").unwrap();
+        s.remove_range(6..12).unwrap();
+        assert_eq!(s, "hello,!");
+        write!(s, " {}", 42).unwrap();
+        assert_eq!(s.as_str(), "hello,! 42");
+        s.truncate(5).unwrap();
+        assert_eq!(s, String::from("hello"));
+    }
+
+    #[test]
+    fn test_string_char_boundary() {
+        let mut s = String::from("añb");
+        assert!(s.insert_str(2, "x").is_err());
+        assert!(s.remove_range(1..2).is_err());
+        assert!(s.truncate(2).is_err());
+        s.remove_range(1..3).unwrap();
+        assert_eq!(s, "ab");
+    }
+
+    #[test]
+    fn test_string_clone() {
+        let s = String::from("shared");
+        let mut copy = s.clone();
+        copy.push_str(" and modified").unwrap();
+        assert_eq!(s, "shared");
+        assert_eq!(copy, "shared and modified");
+    }
+}
@@ -0,0 +1,127 @@
+//! Golden image assertions for regression tests.
+//!
+//! Rendered images are compared against reference images stored on disk. On
+//! a mismatch the actual image and a diff heatmap are written next to the
+//! golden file as `<name>.actual.png` and `<name>.diff.png`. Setting the
+//! [`UPDATE_GOLDEN_ENV`] environment variable (re)writes the golden files
+//! instead.
+//!
+//! This module requires the `test-utils` feature.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::image::Image;
+
+/// Environment variable that makes [`assert_image_matches`] write the golden
+/// files instead of comparing against them.
+pub const UPDATE_GOLDEN_ENV: &str = "BLEND2D_UPDATE_GOLDEN";
+
+/// How much an image may differ from its golden image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tolerance {
+    /// The largest allowed difference of any premultiplied color component.
+    pub max_delta: u8,
+    /// The number of pixels that may differ.
+    pub max_differing_pixels: usize,
+}
+
+impl Tolerance {
+    /// No differences are allowed.
+    pub const EXACT: Tolerance = Tolerance::new(0, 0);
+
+    #[inline]
+    pub const fn new(max_delta: u8, max_differing_pixels: usize) -> Self {
+        Tolerance {
+            max_delta,
+            max_differing_pixels,
+        }
+    }
+}
+
+/// Asserts that `actual` matches the image stored at `golden` within
+/// `tolerance`, see the [module documentation](self).
+#[track_caller]
+pub fn assert_image_matches<P: AsRef<Path>>(actual: &Image, golden: P, tolerance: Tolerance) {
+    if let Err(message) = check_image_matches(actual, golden.as_ref(), tolerance) {
+        panic!("{message}");
+    }
+}
+
+/// Checks that `actual` matches the image stored at `golden` within
+/// `tolerance`, returning a description of the mismatch otherwise.
+pub fn check_image_matches(
+    actual: &Image,
+    golden: &Path,
+    tolerance: Tolerance,
+) -> Result<(), String> {
+    if env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        return write_image(actual, golden);
+    }
+    let actual_path = sibling_path(golden, "actual");
+    let Ok(expected) = Image::read_from_file(golden) else {
+        write_image(actual, &actual_path)?;
+        return Err(format!(
+            "golden image {} could not be read, wrote the actual image to {}",
+            golden.display(),
+            actual_path.display(),
+        ));
+    };
+    let Ok(diff) = actual.compare(&expected) else {
+        write_image(actual, &actual_path)?;
+        return Err(format!(
+            "image size {:?} differs from size {:?} of golden image {}, wrote the actual image \
+             to {}",
+            actual.size(),
+            expected.size(),
+            golden.display(),
+            actual_path.display(),
+        ));
+    };
+    if diff.is_within(tolerance.max_delta, tolerance.max_differing_pixels) {
+        return Ok(());
+    }
+
+    write_image(actual, &actual_path)?;
+    let diff_path = sibling_path(golden, "diff");
+    if let Some(heatmap) = &diff.heatmap {
+        write_image(heatmap, &diff_path)?;
+    }
+    Err(format!(
+        "image differs from golden image {}: {} pixels differ with a max delta of {} (PSNR \
+         {:.2} dB) in {:?}, allowed is {:?}; wrote {} and {}",
+        golden.display(),
+        diff.differing_pixels,
+        diff.max_delta,
+        diff.psnr,
+        diff.bounding_box,
+        tolerance,
+        actual_path.display(),
+        diff_path.display(),
+    ))
+}
+
+/// Returns `<dir>/<stem>.<suffix>.png` for the golden file `<dir>/<stem>.*`.
+fn sibling_path(golden: &Path, suffix: &str) -> PathBuf {
+    let stem = golden.file_stem().unwrap_or_default().to_string_lossy();
+    golden.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+fn write_image(image: &Image, path: &Path) -> Result<(), String> {
+    image
+        .write_to_file(path)
+        .map_err(|_| format!("failed to write image {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sibling_path() {
+        assert_eq!(
+            sibling_path(Path::new("tests/golden/circle.png"), "diff"),
+            Path::new("tests/golden/circle.diff.png")
+        );
+    }
+}
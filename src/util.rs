@@ -1,45 +1,84 @@
+use std::ffi::CString;
 use std::ops;
+use std::os::raw::c_char;
+use std::path::Path;
 
-pub(in crate) unsafe fn cast_ref<T, U>(t: &T) -> &U {
-    &*(t as *const _ as *const U)
-}
+use crate::Error;
 
 #[inline]
-pub(in crate) fn bl_range<R: ops::RangeBounds<usize>>(range: R) -> ffi::BLRange {
+pub(crate) fn bl_range<R: ops::RangeBounds<usize>>(range: R) -> ffi::BLRange {
     ffi::BLRange {
         start: match range.start_bound() {
             ops::Bound::Included(n) => *n,
-            ops::Bound::Excluded(_) => {
-                unreachable!("start_bound of a range cannot be an excluded bound")
-            },
+            ops::Bound::Excluded(n) => n.saturating_add(1),
             ops::Bound::Unbounded => 0,
         },
         end: match range.end_bound() {
-            ops::Bound::Included(n) => *n,
-            ops::Bound::Excluded(n) => *n - 1,
-            ops::Bound::Unbounded => 0,
+            ops::Bound::Included(n) => n.saturating_add(1),
+            ops::Bound::Excluded(n) => *n,
+            ops::Bound::Unbounded => usize::MAX,
         },
     }
 }
 
+/// Converts a nul-terminated, fixed size C string into a `String`.
+pub(crate) fn c_string(chars: &[c_char]) -> String {
+    let bytes = chars
+        .iter()
+        .map(|&c| c as u8)
+        .take_while(|&c| c != 0)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Returns the impl of a dynamic object, which Blend2D stores in the first
+/// word of the object's core.
+///
+/// # Safety
+///
+/// `core` must be a Blend2D object core holding a dynamic impl of type `I`.
 #[inline]
-pub(in crate) fn range_to_tuple<F, R>(range: R, len_func: F) -> (usize, usize)
-where
-    F: FnOnce() -> usize,
-    R: ops::RangeBounds<usize>,
-{
-    (
-        match range.start_bound() {
-            ops::Bound::Included(n) => *n,
-            ops::Bound::Excluded(_) => {
-                unreachable!("start_bound of a range cannot be an excluded bound")
-            },
-            ops::Bound::Unbounded => 0,
-        },
-        match range.end_bound() {
-            ops::Bound::Included(n) => *n,
-            ops::Bound::Excluded(n) => *n - 1,
-            ops::Bound::Unbounded => len_func(),
-        },
-    )
+pub(crate) unsafe fn object_impl<C, I>(core: &C) -> *mut I {
+    unsafe { *(core as *const C as *const *mut I) }
+}
+
+/// Converts a path into the nul-terminated file name Blend2D expects.
+///
+/// Blend2D treats file names as raw bytes on Unix and as UTF-8 elsewhere, so
+/// non-UTF-8 paths only fail on other platforms. Paths containing nul bytes
+/// always fail.
+pub(crate) fn path_to_c_string(path: &Path) -> Result<CString, Error> {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_str().ok_or(Error)?.as_bytes().to_vec();
+    CString::new(bytes).map_err(|_| Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use super::*;
+
+    fn range<R: ops::RangeBounds<usize>>(range: R) -> (usize, usize) {
+        let range = bl_range(range);
+        (range.start, range.end)
+    }
+
+    #[test]
+    fn test_bl_range() {
+        assert_eq!(range(..), (0, usize::MAX));
+        assert_eq!(range(2..5), (2, 5));
+        assert_eq!(range(2..=5), (2, 6));
+        assert_eq!(range(3..), (3, usize::MAX));
+        assert_eq!(range(..=0), (0, 1));
+        assert_eq!(range((Bound::Excluded(2), Bound::Included(4))), (3, 5));
+        assert_eq!(range((Bound::Excluded(2), Bound::Excluded(4))), (3, 4));
+        assert_eq!(range(..=usize::MAX), (0, usize::MAX));
+        assert_eq!(
+            range((Bound::Excluded(usize::MAX), Bound::Unbounded)),
+            (usize::MAX, usize::MAX)
+        );
+    }
 }